@@ -0,0 +1,202 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::options::KeywordChars;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AbbreviationMode {
+    Insert,
+    Command,
+}
+
+impl AbbreviationMode {
+    fn prefix(self) -> char {
+        match self {
+            AbbreviationMode::Insert => 'i',
+            AbbreviationMode::Command => 'c',
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Abbreviation {
+    mode: AbbreviationMode,
+    lhs: String,
+    rhs: String,
+}
+
+/// Abbreviations defined with `:abbreviate` and friends, shared between all buffers
+pub struct Abbreviations(Rc<RefCell<Vec<Abbreviation>>>);
+
+impl Abbreviations {
+    pub fn new() -> Self {
+        Abbreviations(Rc::new(RefCell::new(Vec::new())))
+    }
+
+    pub fn clone(&self) -> Abbreviations {
+        Abbreviations(Rc::clone(&self.0))
+    }
+
+    pub fn add(&self, modes: &[AbbreviationMode], lhs: &str, rhs: &str) {
+        self.0
+            .borrow_mut()
+            .retain(|abbreviation| !modes.contains(&abbreviation.mode) || abbreviation.lhs != lhs);
+
+        for mode in modes {
+            self.0.borrow_mut().push(Abbreviation {
+                mode: *mode,
+                lhs: lhs.to_string(),
+                rhs: rhs.to_string(),
+            });
+        }
+    }
+
+    /// Removes the abbreviation, falling back to the ones expanding to `lhs` so that an
+    /// argument which got expanded while typing still works. Returns false if none was found
+    pub fn remove(&self, modes: &[AbbreviationMode], lhs: &str) -> bool {
+        let mut abbreviations = self.0.borrow_mut();
+        let len = abbreviations.len();
+        abbreviations
+            .retain(|abbreviation| !modes.contains(&abbreviation.mode) || abbreviation.lhs != lhs);
+
+        if abbreviations.len() == len {
+            abbreviations.retain(|abbreviation| {
+                !modes.contains(&abbreviation.mode) || abbreviation.rhs != lhs
+            });
+        }
+
+        abbreviations.len() != len
+    }
+
+    pub fn clear(&self, modes: &[AbbreviationMode]) {
+        self.0
+            .borrow_mut()
+            .retain(|abbreviation| !modes.contains(&abbreviation.mode));
+    }
+
+    /// The abbreviations for the modes whose left side starts with `prefix`, formatted like Vim
+    pub fn list(&self, modes: &[AbbreviationMode], prefix: &str) -> Vec<String> {
+        self.0
+            .borrow()
+            .iter()
+            .filter(|abbreviation| {
+                modes.contains(&abbreviation.mode) && abbreviation.lhs.starts_with(prefix)
+            })
+            .map(|abbreviation| {
+                format!(
+                    "{}  {}  {}",
+                    abbreviation.mode.prefix(),
+                    abbreviation.lhs,
+                    abbreviation.rhs
+                )
+            })
+            .collect()
+    }
+
+    /// Finds an abbreviation that ends the text, returns the length of its left side and its expansion
+    pub fn find(
+        &self,
+        mode: AbbreviationMode,
+        iskeyword: &KeywordChars,
+        text: &str,
+    ) -> Option<(usize, String)> {
+        let is_keyword = |char| iskeyword.contains(char);
+
+        self.0
+            .borrow()
+            .iter()
+            .filter(|abbreviation| abbreviation.mode == mode)
+            .find(|abbreviation| {
+                let Some(before) = text.strip_suffix(&abbreviation.lhs) else {
+                    return false;
+                };
+
+                match before.chars().next_back() {
+                    None => true,
+                    Some(char) if abbreviation.lhs.starts_with(is_keyword) => !is_keyword(char),
+                    Some(char) => char.is_whitespace(),
+                }
+            })
+            .map(|abbreviation| (abbreviation.lhs.len(), expand_keys(&abbreviation.rhs)))
+    }
+}
+
+/// Replaces the key notation allowed in the right side, e.g. `<CR>`, with the characters
+fn expand_keys(rhs: &str) -> String {
+    rhs.replace("<CR>", "\n")
+        .replace("<Tab>", "\t")
+        .replace("<Space>", " ")
+        .replace("<lt>", "<")
+}
+
+/// Parses the arguments of the abbreviation commands into a left and right side
+pub fn parse_abbreviation(args: &str) -> (&str, &str) {
+    let args = args.trim_start();
+
+    match args.split_once(char::is_whitespace) {
+        Some((lhs, rhs)) => (lhs, rhs.trim_start()),
+        None => (args, ""),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use AbbreviationMode::{Command, Insert};
+
+    #[test]
+    fn finds_abbreviations() {
+        let keywords = KeywordChars::default();
+        let abbreviations = Abbreviations::new();
+        abbreviations.add(&[Insert], "teh", "the");
+        abbreviations.add(&[Insert, Command], "#i", "#include");
+
+        assert_eq!(
+            abbreviations.find(Insert, &keywords, "is teh"),
+            Some((3, String::from("the")))
+        );
+        assert_eq!(
+            abbreviations.find(Insert, &keywords, "teh"),
+            Some((3, String::from("the")))
+        );
+        assert_eq!(abbreviations.find(Insert, &keywords, "steh"), None);
+        assert_eq!(abbreviations.find(Command, &keywords, "teh"), None);
+        assert_eq!(
+            abbreviations.find(Command, &keywords, "x #i"),
+            Some((2, String::from("#include")))
+        );
+        assert_eq!(abbreviations.find(Command, &keywords, "x.#i"), None);
+    }
+
+    #[test]
+    fn removes_and_lists() {
+        let keywords = KeywordChars::default();
+        let abbreviations = Abbreviations::new();
+        abbreviations.add(&[Insert, Command], "lic", "MIT<CR>License");
+
+        assert_eq!(
+            abbreviations.list(&[Insert, Command], "l"),
+            vec!["i  lic  MIT<CR>License", "c  lic  MIT<CR>License"]
+        );
+        assert_eq!(
+            abbreviations.find(Insert, &keywords, "lic"),
+            Some((3, String::from("MIT\nLicense")))
+        );
+
+        assert!(abbreviations.remove(&[Command], "lic"));
+        assert!(!abbreviations.remove(&[Command], "lic"));
+        assert_eq!(
+            abbreviations.list(&[Insert, Command], ""),
+            vec!["i  lic  MIT<CR>License"]
+        );
+
+        abbreviations.clear(&[Insert]);
+        assert!(abbreviations.list(&[Insert, Command], "").is_empty());
+    }
+
+    #[test]
+    fn parses_arguments() {
+        assert_eq!(parse_abbreviation(" teh  the one"), ("teh", "the one"));
+        assert_eq!(parse_abbreviation("teh"), ("teh", ""));
+    }
+}
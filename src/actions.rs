@@ -1,49 +1,179 @@
-use delete::{delete_end, delete_line, delete_motion};
+use delete::{change_lines, change_motion, delete_char, delete_end, delete_lines, delete_motion};
+use insert::{
+    append, append_line_end, insert_column_zero, insert_last, insert_line_start, open_line_above,
+    open_line_below,
+};
+use yank::{yank_lines, yank_motion};
 
-use crate::{buffer::Buffer, mode::Mode, motion::Motion, utils::split_first_char};
+use crate::{
+    buffer::Buffer,
+    mode::Mode,
+    motion::{Force, Motion},
+    utils::split_first_char,
+};
 
 pub mod delete;
+pub mod insert;
+pub mod yank;
 
-#[derive(Debug, PartialEq)]
+/// Keys that take a motion and therefore accept a count between the key and the motion
+pub const OPERATORS: [char; 3] = ['d', 'c', 'y'];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Action {
-    Delete { motion: Motion },
+    Delete {
+        motion: Motion,
+        force: Option<Force>,
+    },
     DeleteLine,
     DeleteEnd,
-    Change { motion: Motion },
+    DeleteChar,
+    Change {
+        motion: Motion,
+        force: Option<Force>,
+    },
+    ChangeLine,
+    ChangeEnd,
+    Substitute,
+    Insert,
+    Append,
+    InsertLineStart,
+    AppendLineEnd,
+    InsertColumnZero,
+    InsertLast,
+    OpenBelow,
+    OpenAbove,
+    Yank {
+        motion: Motion,
+        force: Option<Force>,
+    },
+    YankLine,
 }
 
 impl Action {
     pub fn new(keys: &str) -> Option<Self> {
-        let (prefix, keys) = split_first_char(keys);
-        let action = match prefix {
-            'd' => match keys.as_str() {
-                "d" => Some(Action::DeleteLine),
-                keys => Motion::new(keys).map(|motion| Action::Delete { motion }),
-            },
-            'c' => Motion::new(&keys).map(|motion| Action::Change { motion }),
-            'D' => Some(Action::DeleteEnd),
-            _ => None,
+        let action = match keys {
+            "" => None,
+            "i" => Some(Action::Insert),
+            "a" => Some(Action::Append),
+            "I" => Some(Action::InsertLineStart),
+            "A" => Some(Action::AppendLineEnd),
+            "gI" => Some(Action::InsertColumnZero),
+            "gi" => Some(Action::InsertLast),
+            "o" => Some(Action::OpenBelow),
+            "O" => Some(Action::OpenAbove),
+            "s" => Some(Action::Substitute),
+            "S" => Some(Action::ChangeLine),
+            "C" => Some(Action::ChangeEnd),
+            "D" => Some(Action::DeleteEnd),
+            "x" => Some(Action::DeleteChar),
+            "Y" => Some(Action::YankLine),
+            keys => {
+                let (prefix, keys) = split_first_char(keys);
+                match prefix {
+                    'd' => match keys.as_str() {
+                        "d" => Some(Action::DeleteLine),
+                        keys => Self::operator_motion(keys)
+                            .map(|(motion, force)| Action::Delete { motion, force }),
+                    },
+                    'c' => match keys.as_str() {
+                        "c" => Some(Action::ChangeLine),
+                        keys => Self::operator_motion(keys)
+                            .map(|(motion, force)| Action::Change { motion, force }),
+                    },
+                    'y' => match keys.as_str() {
+                        "y" => Some(Action::YankLine),
+                        keys => Self::operator_motion(keys)
+                            .map(|(motion, force)| Action::Yank { motion, force }),
+                    },
+                    _ => None,
+                }
+            }
         };
         action
     }
 
-    pub fn execute(self, buf: &mut Buffer) {
+    /// The motion typed after an operator, with a `v`, `V` or `Ctrl-v` forcing its kind
+    fn operator_motion(keys: &str) -> Option<(Motion, Option<Force>)> {
+        let force = keys.chars().next().and_then(Force::new);
+        let keys = match force {
+            Some(_) => &keys[1..],
+            None => keys,
+        };
+
+        Motion::new(keys).map(|motion| (motion, force))
+    }
+
+    pub fn execute(self, buf: &mut Buffer, count: Option<usize>) {
+        let repeat = count.unwrap_or(1);
+
         match self {
-            Action::Delete { motion } => {
-                delete_motion(buf, motion);
+            Action::Delete { motion, force } => {
+                delete_motion(buf, motion, count, force);
             }
-            Action::Change { motion } => {
-                delete_motion(buf, motion);
-                buf.change_mode(Mode::Insert);
+            Action::Change { motion, force } => {
+                if change_motion(buf, motion, count, force) {
+                    buf.change_mode(Mode::Insert);
+                }
             }
             Action::DeleteLine => {
-                delete_line(buf);
+                delete_lines(buf, repeat);
             }
             Action::DeleteEnd => {
-                delete_end(buf);
+                delete_end(buf, repeat);
+            }
+            Action::DeleteChar => {
+                delete_char(buf, repeat);
             }
+            Action::ChangeLine => {
+                change_lines(buf, repeat);
+                buf.change_mode(Mode::Insert);
+            }
+            Action::ChangeEnd => {
+                delete_end(buf, repeat);
+                buf.change_mode(Mode::Insert);
+                buf.cursor.col = buf.row(buf.cursor.row).len();
+            }
+            Action::Substitute => {
+                let col = buf.cursor.col;
+                delete_char(buf, repeat);
+                buf.change_mode(Mode::Insert);
+                buf.cursor.col = col;
+            }
+            Action::Insert => buf.change_mode(Mode::Insert),
+            Action::Append => append(buf),
+            Action::InsertLineStart => insert_line_start(buf),
+            Action::AppendLineEnd => append_line_end(buf),
+            Action::InsertColumnZero => insert_column_zero(buf),
+            Action::InsertLast => insert_last(buf),
+            Action::OpenBelow => open_line_below(buf),
+            Action::OpenAbove => open_line_above(buf),
+            Action::Yank { motion, force } => {
+                yank_motion(buf, motion, count, force);
+            }
+            Action::YankLine => yank_lines(buf, repeat),
         }
     }
+
+    /// Whether the action changes the text and can therefore be repeated with `.`
+    pub fn changes(&self) -> bool {
+        !matches!(self, Action::Yank { .. } | Action::YankLine)
+    }
+
+    /// Whether a count repeats the text typed after this action rather than extending the range
+    pub fn repeats_insert(&self) -> bool {
+        matches!(
+            self,
+            Action::Insert
+                | Action::Append
+                | Action::InsertLineStart
+                | Action::AppendLineEnd
+                | Action::InsertColumnZero
+                | Action::InsertLast
+                | Action::OpenBelow
+                | Action::OpenAbove
+        )
+    }
 }
 
 #[cfg(test)]
@@ -54,7 +184,7 @@ mod tests {
     fn check_executed() {
         let mut buf = Buffer::test(String::from("test.txt"));
 
-        Action::new("d$").unwrap().execute(&mut buf);
+        Action::new("d$").unwrap().execute(&mut buf, None);
         assert_eq!(buf.content[0], String::new());
     }
 
@@ -63,4 +193,11 @@ mod tests {
         let action = Action::new("dd");
         assert_eq!(action, Some(Action::DeleteLine));
     }
+
+    #[test]
+    fn creates_insert_actions() {
+        assert_eq!(Action::new("cc"), Some(Action::ChangeLine));
+        assert_eq!(Action::new("gi"), Some(Action::InsertLast));
+        assert_eq!(Action::new("g"), None);
+    }
 }
@@ -1,57 +1,134 @@
-use std::cmp::{max, min};
+use std::cmp::min;
 
-use crate::{buffer::Buffer, motion::Motion};
+use crate::{
+    buffer::Buffer,
+    indent::indent_of,
+    motion::{Force, Motion, Region},
+    navigation::{clamp_col, first_not_whitespace},
+};
 
-pub fn delete_motion(buf: &mut Buffer, motion: Motion) {
-    let from = buf.cursor;
-    let end = motion.execute(buf);
+/// Deletes the text the motion moves over, returns false if the motion failed
+pub fn delete_motion(
+    buf: &mut Buffer,
+    motion: Motion,
+    count: Option<usize>,
+    force: Option<Force>,
+) -> bool {
+    match motion.region(buf, count, force) {
+        Some(Region::Lines { start, end }) => {
+            buf.cursor.row = start;
+            delete_lines(buf, end - start + 1);
+        }
+        Some(region) => {
+            delete_region(buf, &region);
+            // The start of the region can be past the end of what's left of its line
+            buf.cursor.col = clamp_col(buf, buf.cursor.row, buf.cursor.col);
+        }
+        None => return false,
+    }
 
-    if from.row == end.row {
-        let min = min(from.col, end.col);
-        let mut max = max(from.col, end.col);
+    true
+}
 
-        if motion.inclusive() {
-            max -= 1;
+/// Like `delete_motion`, but keeps an empty line to insert into when the motion is linewise
+pub fn change_motion(
+    buf: &mut Buffer,
+    motion: Motion,
+    count: Option<usize>,
+    force: Option<Force>,
+) -> bool {
+    match motion.change_region(buf, count, force) {
+        Some(Region::Lines { start, end }) => {
+            buf.cursor.row = start;
+            change_lines(buf, end - start + 1);
         }
+        Some(region) => delete_region(buf, &region),
+        None => return false,
+    }
 
-        let line = buf
-            .content
-            .get_mut(from.row)
-            .unwrap_or_else(|| panic!("row: {} not found", from.row));
-
-        buf.register.set_default(line[min..max + 1].to_string());
-        line.replace_range(min..max + 1, "");
-        buf.cursor.col = min;
-    } else {
-        let min = min(from.row, end.row);
-        let max = max(from.row, end.row);
+    true
+}
 
-        let mut data = String::from("\n");
+/// Deletes characterwise or blockwise text, joining the lines a characterwise region spans
+pub fn delete_region(buf: &mut Buffer, region: &Region) {
+    buf.register.set_default(region.text(buf));
+    buf.cursor = region.start(buf);
 
-        for _ in min..max + 1 {
-            data.push_str(&format!("{}\n", buf.content.remove(min)));
+    match region {
+        Region::Chars { start, end } => {
+            let tail = buf.row(end.row)[end.col..].to_string();
+            buf.content[start.row].replace_range(start.col.., &tail);
+            buf.remove_lines(start.row + 1..end.row + 1);
+        }
+        region => {
+            for (row, range) in region.ranges(buf) {
+                buf.content[row].replace_range(range, "");
+            }
         }
-        buf.register.set_default(data);
     }
 }
 
-pub fn delete_line(buf: &mut Buffer) {
-    let line = format!("\n{}\n", buf.content.remove(buf.cursor.row));
-    println!("{}", line);
-    buf.register.set_default(line);
+pub fn delete_lines(buf: &mut Buffer, count: usize) {
+    let end = min(buf.cursor.row + count, buf.content.len());
+    let lines = buf.remove_lines(buf.cursor.row..end);
+    buf.register
+        .set_default(format!("\n{}\n", lines.join("\n")));
+
+    if buf.content.is_empty() {
+        buf.content.push(String::new());
+    }
+
     buf.cursor.row = min(buf.cursor.row, buf.content.len() - 1);
+    buf.cursor.col = first_not_whitespace(buf.row(buf.cursor.row));
 }
 
-pub fn delete_end(buf: &mut Buffer) {
+pub fn change_lines(buf: &mut Buffer, count: usize) {
+    let end = min(buf.cursor.row + count, buf.content.len());
+    let lines = buf.remove_lines(buf.cursor.row..end);
+    buf.register
+        .set_default(format!("\n{}\n", lines.join("\n")));
+
+    let indent = match lines.first() {
+        Some(line) if buf.options.autoindent() => indent_of(line).to_string(),
+        _ => String::new(),
+    };
+    buf.cursor.col = indent.len();
+    buf.insert_lines(buf.cursor.row, [indent]);
+}
+
+pub fn delete_end(buf: &mut Buffer, count: usize) {
+    let end = min(buf.cursor.row + count, buf.content.len());
+    let mut lines = buf.remove_lines(buf.cursor.row + 1..end);
+
     let line = &mut buf.content[buf.cursor.row];
-    buf.register.set_default(line[buf.cursor.col..].to_string());
+    lines.insert(0, line[buf.cursor.col..].to_string());
+    buf.register.set_default(lines.join("\n"));
     line.replace_range(buf.cursor.col.., "");
-    buf.cursor.col = min(buf.cursor.col, max(line.len(), 1) - 1);
+    buf.cursor.col = clamp_col(buf, buf.cursor.row, buf.cursor.col);
+}
+
+pub fn delete_char(buf: &mut Buffer, count: usize) {
+    let line = &mut buf.content[buf.cursor.row];
+    if line.is_empty() {
+        return;
+    }
+
+    // The count is in characters, which can be more than a byte each
+    let end = line[buf.cursor.col..]
+        .char_indices()
+        .nth(count)
+        .map_or(line.len(), |(idx, _)| buf.cursor.col + idx);
+    buf.register
+        .set_default(line[buf.cursor.col..end].to_string());
+    line.replace_range(buf.cursor.col..end, "");
+    buf.cursor.col = clamp_col(buf, buf.cursor.row, buf.cursor.col);
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::buffer::Position;
+    use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use crate::{buffer::Position, mode::Mode};
 
     use super::*;
 
@@ -59,7 +136,7 @@ mod tests {
     fn test_delete_motion() {
         let mut buf = Buffer::test(String::from("test.txt"));
 
-        delete_motion(&mut buf, Motion::LineEnd);
+        delete_motion(&mut buf, Motion::LineEnd, None, None);
         assert_eq!(buf.content[0], String::new());
         assert_eq!(
             buf.register.get_default(),
@@ -67,12 +144,12 @@ mod tests {
         );
 
         buf.cursor = Position { row: 1, col: 0 };
-        delete_motion(&mut buf, Motion::StartWord);
+        delete_motion(&mut buf, Motion::StartWord, None, None);
         assert_eq!(buf.content[1], String::from("adipiscing elit. "));
         assert_eq!(buf.register.get_default(), String::from("consectetuer "));
 
         buf.cursor = Position { row: 3, col: 13 };
-        delete_motion(&mut buf, Motion::PrevWordStart);
+        delete_motion(&mut buf, Motion::PrevWordStart, None, None);
         assert_eq!(
             buf.content[3],
             String::from("Mauris semper varius eros morbi.")
@@ -85,7 +162,7 @@ mod tests {
         }
 
         buf.cursor = Position { row: 2, col: 0 };
-        delete_motion(&mut buf, Motion::Down);
+        delete_motion(&mut buf, Motion::Down, None, None);
         println!();
 
         for line in buf.content.iter() {
@@ -107,7 +184,7 @@ Mauris semper varius eros morbi.
     fn test_delete_line() {
         let mut buf = Buffer::test(String::from("test.txt"));
 
-        delete_line(&mut buf);
+        delete_lines(&mut buf, 1);
         assert_eq!(buf.content.len(), 6);
         assert_eq!(
             buf.content[0],
@@ -119,7 +196,7 @@ Mauris semper varius eros morbi.
         );
 
         buf.cursor.row = buf.content.len() - 1;
-        delete_line(&mut buf);
+        delete_lines(&mut buf, 1);
         assert_eq!(buf.content.len(), 5);
         assert_eq!(buf.cursor.row, 4);
         assert_eq!(buf.register.get_default(), String::from("\nnulla\n"));
@@ -129,15 +206,241 @@ Mauris semper varius eros morbi.
     fn test_delete_end() {
         let mut buf = Buffer::test(String::from("test.txt"));
 
-        delete_end(&mut buf);
+        delete_end(&mut buf, 1);
         assert_eq!(buf.content[0], String::new());
 
         buf.cursor = Position { row: 1, col: 13 };
-        delete_end(&mut buf);
+        delete_end(&mut buf, 1);
         assert_eq!(buf.content[1], String::from("consectetuer "));
         assert_eq!(
             buf.register.get_default(),
             String::from("adipiscing elit. ")
         )
     }
+
+    #[test]
+    fn test_delete_counts() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+
+        delete_lines(&mut buf, 2);
+        assert_eq!(buf.content.len(), 5);
+        assert_eq!(
+            buf.register.get_default(),
+            String::from("\nLorem ipsum odor amet, \nconsectetuer adipiscing elit. \n")
+        );
+
+        buf.cursor = Position { row: 2, col: 5 };
+        delete_end(&mut buf, 2);
+        assert_eq!(buf.content[2], String::from("Lorem"));
+        assert_eq!(buf.content[3], String::from("nulla"));
+        assert_eq!(buf.register.get_default(), String::from(".ipsum\n"));
+
+        buf.cursor = Position { row: 0, col: 0 };
+        delete_char(&mut buf, 3);
+        assert_eq!(
+            buf.content[0],
+            String::from("iculus nulla consectetur proin purus ad justo nullam. ")
+        );
+    }
+
+    #[test]
+    fn keeps_the_cursor_on_the_line() {
+        let mut buf = Buffer::test(String::new());
+        buf.content = vec![String::from("abc"), String::from("def")];
+        buf.input_keys("$de");
+        assert_eq!(buf.content, vec!["ab"]);
+        assert_eq!(buf.cursor, Position { row: 0, col: 1 });
+
+        for keys in ["0wd$", "0wdw"] {
+            buf.content = vec![String::from("abc def")];
+            buf.input_keys(keys);
+            assert_eq!(buf.content, vec!["abc "]);
+            assert_eq!(buf.cursor, Position { row: 0, col: 3 });
+        }
+
+        buf.content = vec![String::from("abc def")];
+        buf.input_keys("0wcwx");
+        assert_eq!(buf.content, vec!["abc x"]);
+    }
+
+    #[test]
+    fn deletes_multibyte_characters() {
+        let mut buf = Buffer::test(String::new());
+        buf.content[0] = String::from("é");
+        buf.input_keys("x");
+        assert_eq!(buf.content[0], "");
+
+        buf.content[0] = String::from("aéb");
+        buf.input_keys("03x");
+        assert_eq!(buf.content[0], "");
+
+        buf.content[0] = String::from("aé€");
+        buf.input_keys("0x$x");
+        assert_eq!(buf.content[0], "é");
+        assert_eq!(buf.register.get_default(), "€");
+        assert_eq!(buf.cursor.col, 0);
+
+        buf.content[0] = String::from("éa");
+        buf.input_keys("0sX");
+        assert_eq!(buf.content[0], "Xa");
+    }
+
+    #[test]
+    fn test_change_lines() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.cursor = Position { row: 1, col: 4 };
+
+        change_lines(&mut buf, 2);
+        assert_eq!(buf.content.len(), 6);
+        assert_eq!(buf.content[1], String::new());
+        assert_eq!(buf.cursor, Position { row: 1, col: 0 });
+    }
+
+    #[test]
+    fn deletes_to_character_finds() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.input_keys("dtm");
+        assert_eq!(buf.content[0], "m ipsum odor amet, ");
+
+        buf.input_keys("d;");
+        assert_eq!(buf.content[0], "m odor amet, ");
+
+        buf.input_keys("$dFo");
+        assert_eq!(buf.content[0], "m od ");
+
+        buf.input_keys("cfz");
+        assert_eq!(buf.content[0], "m od ");
+        assert_eq!(buf.mode, Mode::Normal);
+    }
+
+    #[test]
+    fn deletes_linewise_motions() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.input_keys("jjdG");
+        assert_eq!(buf.content.len(), 2);
+        assert_eq!(buf.cursor, Position { row: 1, col: 0 });
+
+        buf.input_keys("dgg");
+        assert_eq!(buf.content, vec![String::new()]);
+
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.input_keys("G2dgg");
+        assert_eq!(buf.content, vec!["Lorem ipsum odor amet, "]);
+    }
+
+    #[test]
+    fn deletes_last_word_of_line() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.cursor = Position { row: 4, col: 6 };
+
+        buf.input_keys("dw");
+        assert_eq!(buf.content[4], "Lorem.");
+        assert_eq!(buf.content.len(), 7);
+
+        buf.input_keys("Gdw");
+        assert_eq!(buf.content[6], "");
+    }
+
+    #[test]
+    fn deletes_to_matching_bracket() {
+        let mut buf = Buffer::test(String::new());
+        buf.content[0] = String::from("f(a, [b]) c");
+        buf.cursor.col = 8;
+
+        buf.input_keys("d%");
+        assert_eq!(buf.content[0], "f c");
+        assert_eq!(buf.cursor.col, 1);
+    }
+
+    #[test]
+    fn deletes_by_motion_kind() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.input_keys("de");
+        assert_eq!(buf.content[0], " ipsum odor amet, ");
+
+        buf.input_keys("df,");
+        assert_eq!(buf.content[0], " ");
+
+        buf.input_keys("jwd$");
+        assert_eq!(buf.content[1], "consectetuer ");
+        assert_eq!(buf.register.get_default(), "adipiscing elit. ");
+
+        buf.cursor = Position { row: 1, col: 5 };
+        buf.input_keys("d}");
+        assert_eq!(buf.content, vec![" ", "conse", "", "nulla"]);
+        assert_eq!(buf.cursor, Position { row: 1, col: 4 });
+
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.input_keys("wd/elit");
+        buf.handle_keys(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        assert_eq!(buf.content[0], "Lorem elit. ");
+        assert_eq!(
+            buf.register.get_default(),
+            "ipsum odor amet, \nconsectetuer adipiscing "
+        );
+    }
+
+    #[test]
+    fn forces_motion_kinds() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.cursor.col = 6;
+        buf.input_keys("dvj");
+        assert_eq!(buf.content[0], "Lorem tetuer adipiscing elit. ");
+        assert_eq!(buf.content.len(), 6);
+
+        buf.input_keys("dVw");
+        assert_eq!(buf.content.len(), 5);
+
+        buf.cursor.col = 0;
+        buf.input_keys("dve");
+        assert_eq!(
+            buf.content[0],
+            "s nulla consectetur proin purus ad justo nullam. "
+        );
+
+        buf.input_keys("ld");
+        buf.handle_keys(KeyEvent::new(KeyCode::Char('v'), KeyModifiers::CONTROL));
+        buf.input_keys("j");
+        assert_eq!(
+            buf.content[0],
+            "snulla consectetur proin purus ad justo nullam. "
+        );
+        assert_eq!(buf.content[1], "Muris vitae semper varius eros morbi.");
+        assert_eq!(buf.register.get_default(), " \na");
+    }
+
+    #[test]
+    fn deletes_blocks_past_short_lines() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.options.set("ve=block").unwrap();
+        buf.cursor = Position { row: 3, col: 20 };
+
+        buf.input_keys("d");
+        buf.handle_keys(KeyEvent::new(KeyCode::Char('v'), KeyModifiers::CONTROL));
+        buf.input_keys("j");
+        assert_eq!(buf.content[3], "Mauris vitae semper arius eros morbi.");
+        assert_eq!(buf.content[4], "Lorem.ipsum");
+    }
+
+    #[test]
+    fn changes_word_to_its_end() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.input_keys("cwfoo");
+        assert_eq!(buf.content[0], "foo ipsum odor amet, ");
+
+        buf.handle_keys(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        buf.cursor.col = 8;
+        buf.input_keys("cwX");
+        assert_eq!(buf.content[0], "foo ipsuX odor amet, ");
+
+        buf.handle_keys(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        buf.cursor.col = 3;
+        buf.input_keys("cwY");
+        assert_eq!(buf.content[0], "fooYipsuX odor amet, ");
+
+        buf.handle_keys(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        buf.cursor.col = 0;
+        buf.input_keys("c2wZ");
+        assert_eq!(buf.content[0], "Z amet, ");
+    }
 }
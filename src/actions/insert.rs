@@ -0,0 +1,120 @@
+use std::cmp::min;
+
+use crate::{
+    buffer::Buffer,
+    indent::{indent_after, indent_before, indent_of},
+    mode::Mode,
+    navigation::right,
+};
+
+pub fn append(buf: &mut Buffer) {
+    buf.change_mode(Mode::Insert);
+    buf.cursor = right(buf);
+}
+
+pub fn insert_line_start(buf: &mut Buffer) {
+    buf.change_mode(Mode::Insert);
+    buf.cursor.col = indent_of(buf.row(buf.cursor.row)).len();
+}
+
+pub fn append_line_end(buf: &mut Buffer) {
+    buf.change_mode(Mode::Insert);
+    buf.cursor.col = buf.row(buf.cursor.row).len();
+}
+
+pub fn insert_column_zero(buf: &mut Buffer) {
+    buf.change_mode(Mode::Insert);
+    buf.cursor.col = 0;
+}
+
+pub fn insert_last(buf: &mut Buffer) {
+    buf.change_mode(Mode::Insert);
+
+    if let Some(position) = buf.last_insert {
+        buf.cursor.row = min(position.row, buf.content.len() - 1);
+        buf.cursor.col = min(position.col, buf.row(buf.cursor.row).len());
+    }
+}
+
+pub fn open_line_below(buf: &mut Buffer) {
+    buf.change_mode(Mode::Insert);
+    let indent = indent_after(&buf.options, buf.row(buf.cursor.row), "");
+    buf.cursor.row += 1;
+    buf.cursor.col = indent.len();
+    buf.insert_lines(buf.cursor.row, [indent]);
+    buf.auto_indent = Some(buf.cursor);
+}
+
+pub fn open_line_above(buf: &mut Buffer) {
+    buf.change_mode(Mode::Insert);
+    let indent = indent_before(&buf.options, buf.row(buf.cursor.row));
+    buf.cursor.col = indent.len();
+    buf.insert_lines(buf.cursor.row, [indent]);
+    buf.auto_indent = Some(buf.cursor);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::buffer::Position;
+
+    use super::*;
+
+    #[test]
+    fn test_insert_line_start() {
+        let mut buf = Buffer::test(String::new());
+        buf.content[0] = String::from("    indented");
+        buf.cursor.col = 8;
+
+        insert_line_start(&mut buf);
+        assert_eq!(buf.cursor.col, 4);
+        assert_eq!(buf.mode, Mode::Insert);
+    }
+
+    #[test]
+    fn test_append_line_end() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+
+        append_line_end(&mut buf);
+        assert_eq!(buf.cursor, Position { row: 0, col: 23 });
+    }
+
+    #[test]
+    fn test_insert_last() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.last_insert = Some(Position { row: 5, col: 3 });
+
+        insert_last(&mut buf);
+        assert_eq!(buf.cursor, Position { row: 5, col: 0 });
+    }
+
+    #[test]
+    fn test_open_lines() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+
+        open_line_below(&mut buf);
+        assert_eq!(buf.content[1], String::new());
+        assert_eq!(buf.cursor, Position { row: 1, col: 0 });
+
+        buf.cursor = Position { row: 3, col: 5 };
+        open_line_above(&mut buf);
+        assert_eq!(buf.content[3], String::new());
+        assert_eq!(buf.cursor, Position { row: 3, col: 0 });
+        assert_eq!(buf.content.len(), 9);
+    }
+
+    #[test]
+    fn open_lines_indented() {
+        let mut buf = Buffer::test(String::new());
+        buf.options.set("ai si sw=2 et").unwrap();
+        buf.content = vec![String::from("  if x {"), String::from("  }")];
+
+        open_line_below(&mut buf);
+        assert_eq!(buf.content[1], "    ");
+        assert_eq!(buf.cursor, Position { row: 1, col: 4 });
+
+        buf.content.remove(1);
+        buf.cursor = Position { row: 1, col: 2 };
+        open_line_above(&mut buf);
+        assert_eq!(buf.content[1], "    ");
+    }
+}
@@ -0,0 +1,62 @@
+use std::cmp::min;
+
+use crate::{
+    buffer::Buffer,
+    motion::{Force, Motion, Region},
+};
+
+/// Copies the text the motion moves over into the default register and moves the cursor to its
+/// start, returns false if the motion failed
+pub fn yank_motion(
+    buf: &mut Buffer,
+    motion: Motion,
+    count: Option<usize>,
+    force: Option<Force>,
+) -> bool {
+    let Some(region) = motion.region(buf, count, force) else {
+        return false;
+    };
+
+    buf.register.set_default(region.text(buf));
+    match region {
+        Region::Lines { start, .. } if start < buf.cursor.row => {
+            buf.cursor = motion.apply(buf, count);
+        }
+        Region::Lines { .. } => {}
+        region => buf.cursor = region.start(buf),
+    }
+
+    true
+}
+
+pub fn yank_lines(buf: &mut Buffer, count: usize) {
+    let end = min(buf.cursor.row + count, buf.content.len());
+    let lines = buf.content[buf.cursor.row..end].join("\n");
+    buf.register.set_default(format!("\n{}\n", lines));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::buffer::Position;
+
+    use super::*;
+
+    #[test]
+    fn yanks_motions() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.input_keys("wyw");
+        assert_eq!(buf.register.get_default(), "ipsum ");
+        assert_eq!(buf.cursor, Position { row: 0, col: 6 });
+
+        buf.input_keys("jygg");
+        assert_eq!(
+            buf.register.get_default(),
+            "\nLorem ipsum odor amet, \nconsectetuer adipiscing elit. \n"
+        );
+        assert_eq!(buf.cursor, Position { row: 0, col: 0 });
+
+        buf.input_keys("2yy");
+        assert_eq!(buf.content.len(), 7);
+        assert_eq!(buf.last_change, None);
+    }
+}
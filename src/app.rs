@@ -1,5 +1,6 @@
 use std::{
     io::{self, Stdout},
+    rc::Rc,
     time::Duration,
 };
 
@@ -7,28 +8,55 @@ use ratatui::{
     crossterm::event::{self, Event, KeyEventKind},
     layout::{Constraint, Layout},
     prelude::{Buffer as TBuffer, CrosstermBackend, Rect},
+    style::Color,
     text::{Line, Span},
     widgets::{Paragraph, Widget},
     Frame, Terminal,
 };
 
-use crate::buffer::{Buffer, Register};
+use crate::{
+    abbreviation::Abbreviations,
+    buffer::{Buffer, Register},
+    completion::{extend_completion, render_menu},
+    file_commands::for_all_buffers,
+    history::{close_command_window, open_command_window, History},
+    mode::{visual::render_selection, Mode},
+    options::Options,
+    search::render_matches,
+    substitute::render_confirm,
+    view::{scroll_to_cursor, visible_lines},
+};
 
 #[allow(dead_code)]
 pub struct App {
     buffers: Vec<Buffer>,
     active_buffer: usize,
     register: Register,
+    abbreviations: Abbreviations,
+    options: Options,
+    history: History,
 }
 
 impl App {
     pub fn new(args: Vec<String>) -> Self {
         let filename = args.get(1).map_or(String::new(), |value| value.to_string());
         let register = Register::new();
+        let abbreviations = Abbreviations::new();
+        let options = Options::default();
+        let history = History::new();
 
         App {
-            buffers: vec![Buffer::new(filename, &register)],
+            buffers: vec![Buffer::new(
+                filename,
+                &register,
+                &abbreviations,
+                &options,
+                &history,
+            )],
             register,
+            abbreviations,
+            options,
+            history,
             active_buffer: 0,
         }
     }
@@ -43,14 +71,39 @@ impl App {
     }
 
     fn render_frame(&mut self, frame: &mut Frame) {
+        self.scroll_to_cursor(frame.area());
         self.render(frame.area(), frame.buffer_mut());
     }
 
+    /// Scrolls the active buffer so its cursor is visible in the text area of `area`
+    fn scroll_to_cursor(&mut self, area: Rect) {
+        let buffer = self.get_active_buffer();
+        let layout = Self::layout(area, buffer);
+        scroll_to_cursor(buffer, layout[0]);
+        buffer.view.message_height = layout[1].height as usize;
+    }
+
+    /// Splits the area into the text and the status line, which grows to fit a message of
+    /// several lines while leaving a line of text
+    fn layout(area: Rect, buffer: &Buffer) -> Rc<[Rect]> {
+        let lines = buffer.message_lines().len().max(1);
+        let height = lines.min(area.height.saturating_sub(1).max(1) as usize);
+
+        Layout::default()
+            .constraints(vec![Constraint::Min(1), Constraint::Length(height as u16)])
+            .split(area)
+    }
+
     fn handle_events(&mut self) -> io::Result<()> {
         if event::poll(Duration::from_millis(10))? {
             match event::read()? {
                 Event::Key(event) if event.kind == KeyEventKind::Press => {
-                    self.get_active_buffer().handle_keys(event)
+                    self.get_active_buffer().handle_keys(event);
+                    self.complete_from_other_buffers();
+                    self.run_for_all_buffers();
+                    self.active_buffer = open_command_window(&mut self.buffers, self.active_buffer);
+                    self.active_buffer =
+                        close_command_window(&mut self.buffers, self.active_buffer);
                 }
                 _ => {}
             }
@@ -59,6 +112,49 @@ impl App {
         Ok(())
     }
 
+    /// Carries out a command for every buffer given in the active buffer
+    fn run_for_all_buffers(&mut self) {
+        if let Some(request) = self.buffers[self.active_buffer].all_buffers.take() {
+            for_all_buffers(&mut self.buffers, self.active_buffer, request);
+        }
+    }
+
+    /// Adds the candidates of the other buffers to a completion started in the active buffer
+    fn complete_from_other_buffers(&mut self) {
+        let Some(completion) = self.buffers[self.active_buffer]
+            .completion
+            .as_ref()
+            .filter(|completion| completion.other_buffers)
+        else {
+            return;
+        };
+
+        let (kind, base, backward) = (
+            completion.kind,
+            completion.base.clone(),
+            completion.backward,
+        );
+
+        let mut candidates: Vec<_> = self
+            .buffers
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| *idx != self.active_buffer)
+            .flat_map(|(_, buffer)| {
+                kind.candidates_in(&buffer.content, buffer.options.iskeyword(), &base)
+            })
+            .collect();
+        if backward {
+            candidates.reverse();
+        }
+
+        let buffer = self.get_active_buffer();
+        if let Some(completion) = buffer.completion.as_mut() {
+            completion.other_buffers = false;
+        }
+        extend_completion(buffer, candidates);
+    }
+
     fn get_active_buffer(&mut self) -> &mut Buffer {
         &mut self.buffers[self.active_buffer]
     }
@@ -67,27 +163,66 @@ impl App {
 impl Widget for &App {
     fn render(self, area: Rect, buf: &mut TBuffer) {
         let active_buffer = &self.buffers[self.active_buffer];
-        let layout = Layout::default()
-            .constraints(vec![Constraint::Min(1), Constraint::Length(1)])
-            .split(area);
+        let layout = App::layout(area, active_buffer);
 
-        Paragraph::new(active_buffer.content.join("\n").to_string()).render(layout[0], buf);
+        Paragraph::new(visible_lines(active_buffer, layout[0]).join("\n"))
+            .scroll((0, active_buffer.view.left as u16))
+            .render(layout[0], buf);
 
-        Paragraph::new(Line::from(vec![
-            Span::raw(format!("-- {} --     ", active_buffer.mode)),
-            active_buffer.message(),
-        ]))
-        .render(layout[1], buf);
+        let prompt = match (&active_buffer.search_prompt, active_buffer.mode) {
+            (Some(prompt), _) => Some(prompt.delimiter()),
+            (None, Mode::Command) => Some(':'),
+            _ => None,
+        };
+        let status = match prompt {
+            Some(prompt) => Span::raw(format!("{}{}", prompt, active_buffer.keys)),
+            None => active_buffer.message(),
+        };
+        let mode = Span::raw(format!("-- {} --     ", active_buffer.mode));
+        let command_cursor = prompt.map(|prompt| {
+            let typed = format!(
+                "{}{}",
+                prompt,
+                &active_buffer.keys[..active_buffer.command_cursor]
+            );
+            layout[1].x + (mode.width() + Span::raw(typed).width()) as u16
+        });
+        if prompt.is_none() && active_buffer.message_lines().len() > 1 {
+            let page: Vec<Line> = active_buffer
+                .message_page()
+                .into_iter()
+                .map(Line::from)
+                .collect();
+            Paragraph::new(page).render(layout[1], buf);
+        } else {
+            Paragraph::new(Line::from(vec![mode, status])).render(layout[1], buf);
+        }
 
-        active_buffer.render_cursor(buf);
+        render_matches(active_buffer, layout[0], buf);
+        render_selection(active_buffer, layout[0], buf);
+        render_confirm(active_buffer, layout[0], buf);
+        render_menu(active_buffer, layout[0], buf);
+        active_buffer.render_match_pair(layout[0], buf);
+        match command_cursor {
+            Some(x) => {
+                if let Some(cell) = buf.cell_mut((x, layout[1].y)) {
+                    cell.set_bg(Color::White).set_fg(Color::Black);
+                }
+            }
+            None => active_buffer.render_cursor(layout[0], buf),
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use ratatui::style::{Style, Stylize};
+    use ratatui::{
+        crossterm::event::{KeyCode, KeyEvent, KeyModifiers},
+        style::{Color, Style, Stylize},
+    };
 
     use super::*;
+    use crate::mode::Mode;
 
     #[test]
     fn renders_blank_with_mode() {
@@ -107,4 +242,99 @@ mod tests {
 
         assert_eq!(buf, expected);
     }
+
+    #[test]
+    fn highlights_matching_bracket() {
+        let mut app = App::new(Vec::new());
+        app.get_active_buffer().content[0] = String::from("(a)");
+        let mut buf = TBuffer::empty(Rect::new(0, 0, 10, 3));
+
+        app.render(buf.area, &mut buf);
+
+        assert_eq!(buf[(2, 0)].bg, Color::Cyan);
+        assert_eq!(buf[(1, 0)].bg, Color::Reset);
+    }
+
+    #[test]
+    fn scrolls_to_cursor() {
+        let mut app = App::new(vec![String::new(), String::from("test.txt")]);
+        let mut buf = TBuffer::empty(Rect::new(0, 0, 12, 4));
+        app.get_active_buffer().input_keys("Gjj");
+
+        app.scroll_to_cursor(buf.area);
+        app.render(buf.area, &mut buf);
+
+        assert_eq!(buf[(0, 0)].symbol(), "L");
+        assert_eq!(buf[(0, 2)].symbol(), "n");
+        assert_eq!(buf[(0, 2)].bg, Color::White);
+    }
+
+    #[test]
+    fn renders_command_line_cursor() {
+        let mut app = App::new(Vec::new());
+        let mut buf = TBuffer::empty(Rect::new(0, 0, 25, 3));
+
+        let buffer = app.get_active_buffer();
+        buffer.input_keys(":ab");
+        buffer.handle_keys(KeyEvent::new(KeyCode::Left, KeyModifiers::NONE));
+        app.render(buf.area, &mut buf);
+
+        assert_eq!(buf[(18, 2)].symbol(), ":");
+        assert_eq!(buf[(20, 2)].symbol(), "b");
+        assert_eq!(buf[(20, 2)].bg, Color::White);
+        assert_eq!(buf[(0, 0)].bg, Color::Reset);
+    }
+
+    #[test]
+    fn pages_long_messages() {
+        let mut app = App::new(Vec::new());
+        let mut buf = TBuffer::empty(Rect::new(0, 0, 24, 5));
+        app.scroll_to_cursor(buf.area);
+
+        let buffer = app.get_active_buffer();
+        buffer.input_keys(":digraphs");
+        buffer.handle_keys(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        app.scroll_to_cursor(buf.area);
+        app.render(buf.area, &mut buf);
+
+        let row = |buf: &TBuffer, y: u16| -> String {
+            (0..buf.area.width).map(|x| buf[(x, y)].symbol()).collect()
+        };
+        assert_eq!(row(&buf, 1), "NS \u{a0} 160    !I ¡ 161    ");
+        assert_eq!(row(&buf, 3), "Cu ¤ 164    Ye ¥ 165    ");
+        assert_eq!(row(&buf, 4), "-- More --              ");
+
+        app.get_active_buffer().input_keys(" ");
+        app.scroll_to_cursor(buf.area);
+        app.render(buf.area, &mut buf);
+        assert_eq!(row(&buf, 1), "BB ¦ 166    SE § 167    ");
+
+        app.get_active_buffer().input_keys("q");
+        assert_eq!(app.get_active_buffer().mode, Mode::Normal);
+        app.scroll_to_cursor(buf.area);
+        buf.reset();
+        app.render(buf.area, &mut buf);
+        assert_eq!(row(&buf, 4), "-- Normal --            ");
+    }
+
+    #[test]
+    fn completes_from_other_buffers() {
+        let mut app = App::new(Vec::new());
+        let other = Buffer::new(
+            String::from("test.txt"),
+            &app.register,
+            &app.abbreviations,
+            &app.options,
+            &app.history,
+        );
+        app.buffers.push(other);
+
+        let buffer = app.get_active_buffer();
+        buffer.mode = Mode::Insert;
+        buffer.input_keys("Ridi");
+        buffer.handle_keys(KeyEvent::new(KeyCode::Char('n'), KeyModifiers::CONTROL));
+        app.complete_from_other_buffers();
+
+        assert_eq!(app.buffers[0].content[0], "Ridiculus");
+    }
 }
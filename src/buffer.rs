@@ -1,49 +1,147 @@
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    io,
+    ops::Range,
+    rc::Rc,
+};
 
 use ratatui::{
     buffer::Buffer as TBuffer,
-    crossterm::event::KeyEvent,
-    layout::Position as TuiPosition,
+    crossterm::event::{KeyCode, KeyEvent},
+    layout::Rect,
     style::{Color, Style},
     text::Span,
 };
 
 use crate::{
+    abbreviation::Abbreviations,
+    completion::Completion,
+    file_commands::AllBuffers,
     filesystem::{read_file, write_file},
+    history::{CommandWindow, History, Recall},
     mode::Mode,
+    motion::Motion,
+    navigation::matching_bracket,
+    options::Options,
+    repeat::Change,
+    search::{Search, SearchPrompt},
+    substitute::{Substitute, Substitution},
+    view::{screen_position, View},
 };
 
 pub struct Buffer {
     pub filename: String,
     pub content: Vec<String>,
+    /// The content as last read or written, to tell whether it has unsaved changes
+    pub written: Vec<String>,
+    /// Set when `:file` renamed the buffer, until it's written to the file of its new name
+    pub renamed: bool,
     pub cursor: Position,
     pub keys: String,
+    pub pending: String,
     pub mode: Mode,
     pub exit: bool,
     pub register: Register,
+    pub abbreviations: Abbreviations,
+    pub options: Options,
+    pub last_insert: Option<Position>,
+    pub last_change: Option<Change>,
+    pub last_find: Option<Motion>,
+    pub last_search: Option<Search>,
+    pub last_substitute: Option<Substitute>,
+    /// Set while `:global` runs its command, which can't be another `:global`
+    pub in_global: bool,
+    /// The rows of the lines `:global` has yet to run its command on, which `insert_lines` and
+    /// `remove_lines` keep on their lines
+    pub global_marks: VecDeque<usize>,
+    /// A substitution with the `c` flag waiting for an answer
+    pub substitution: Option<Substitution>,
+    /// Positions set with `m`, and `<` and `>` for the last visual selection
+    pub marks: HashMap<char, Position>,
+    pub search_prompt: Option<SearchPrompt>,
+    /// Where the selection started in visual mode
+    pub visual_start: Option<Position>,
+    /// Cleared by `:nohlsearch` to hide the `hlsearch` highlighting until the next search
+    pub highlight_search: bool,
+    pub recording: Option<Change>,
+    pub restart_insert: bool,
+    /// Where typing started in insert mode, which `backspace` may not delete before without `start`
+    pub insert_start: Option<Position>,
+    /// The end of the indent inserted automatically on a new line during insert mode
+    pub auto_indent: Option<Position>,
+    /// A command for every buffer, like `:wa`, for the app to carry out
+    pub all_buffers: Option<AllBuffers>,
+    /// Where typing goes in the command line, as a byte index into `keys`
+    pub command_cursor: usize,
+    pub history: History,
+    /// Set while Up and Down browse the history at a prompt
+    pub recall: Option<Recall>,
+    /// The prompt of a command-line window opened with `q:`, `q/` or `q?`, for the app to open
+    pub open_command_window: Option<char>,
+    /// Set when the buffer is a command-line window
+    pub command_window: Option<CommandWindow>,
+    pub completion: Option<Completion>,
+    pub view: View,
     message: Output,
 }
 
 impl Buffer {
-    pub fn new(filename: String, register: &Register) -> Self {
+    pub fn new(
+        filename: String,
+        register: &Register,
+        abbreviations: &Abbreviations,
+        options: &Options,
+        history: &History,
+    ) -> Self {
         let (content, error) = Self::get_content(&filename);
 
         let message = match error {
             Some(message) => Output {
                 message,
                 error: true,
+                ..Output::default()
             },
             None => Output::default(),
         };
 
         Buffer {
             filename,
+            written: content.clone(),
+            renamed: false,
             content,
             cursor: Position::default(),
             keys: String::new(),
+            pending: String::new(),
             mode: Mode::Normal,
             exit: false,
             register: Register::clone(register),
+            abbreviations: Abbreviations::clone(abbreviations),
+            options: options.for_buffer(),
+            history: History::clone(history),
+            last_insert: None,
+            last_change: None,
+            last_find: None,
+            last_search: None,
+            search_prompt: None,
+            last_substitute: None,
+            in_global: false,
+            global_marks: VecDeque::new(),
+            substitution: None,
+            marks: HashMap::new(),
+            highlight_search: true,
+            visual_start: None,
+            recording: None,
+            restart_insert: false,
+            insert_start: None,
+            auto_indent: None,
+            all_buffers: None,
+            command_cursor: 0,
+            recall: None,
+            open_command_window: None,
+            command_window: None,
+            completion: None,
+            view: View::default(),
             message,
         }
     }
@@ -54,12 +152,41 @@ impl Buffer {
 
         Buffer {
             filename,
+            written: content.clone(),
+            renamed: false,
             content,
             cursor: Position::default(),
             keys: String::new(),
+            pending: String::new(),
             mode: Mode::Normal,
             exit: false,
             register: Register::new(),
+            abbreviations: Abbreviations::new(),
+            options: Options::default(),
+            history: History::new(),
+            last_insert: None,
+            last_change: None,
+            last_find: None,
+            last_search: None,
+            search_prompt: None,
+            last_substitute: None,
+            in_global: false,
+            global_marks: VecDeque::new(),
+            substitution: None,
+            marks: HashMap::new(),
+            highlight_search: true,
+            visual_start: None,
+            recording: None,
+            restart_insert: false,
+            insert_start: None,
+            auto_indent: None,
+            all_buffers: None,
+            command_cursor: 0,
+            recall: None,
+            open_command_window: None,
+            command_window: None,
+            completion: None,
+            view: View::default(),
             message: Output::default(),
         }
     }
@@ -79,25 +206,124 @@ impl Buffer {
         }
     }
 
-    pub fn render_cursor(&self, buf: &mut TBuffer) {
-        let cell = buf.cell_mut(self.cursor).unwrap();
-        cell.set_bg(Color::White).set_fg(Color::Black);
+    pub fn render_cursor(&self, area: Rect, buf: &mut TBuffer) {
+        if let Some(cell) =
+            screen_position(self, self.cursor, area).and_then(|pos| buf.cell_mut(pos))
+        {
+            cell.set_bg(Color::White).set_fg(Color::Black);
+        }
+    }
+
+    /// Highlights the bracket matching the one under the cursor
+    pub fn render_match_pair(&self, area: Rect, buf: &mut TBuffer) {
+        if matches!(self.mode, Mode::Command | Mode::Search) {
+            return;
+        }
+
+        if let Some(cell) = matching_bracket(self, self.cursor)
+            .and_then(|pos| screen_position(self, pos, area))
+            .and_then(|pos| buf.cell_mut(pos))
+        {
+            cell.set_bg(Color::Cyan);
+        }
     }
 
     pub fn handle_keys(&mut self, event: KeyEvent) {
+        if self.page_message(event) {
+            return;
+        }
         self.mode.clone().handle_keys(self, event);
     }
 
-    pub fn write(&mut self) {
-        if let Err(msg) = write_file(&self.filename, &self.content.join("\n")) {
-            self.message.message = msg.to_string();
-            self.message.error = true;
+    /// Shows the next page of a message too long for the message area, or drops it on `q` or
+    /// Esc. A key after the last page of a message of several lines clears it and is handled as
+    /// usual. Returns whether the key was used up
+    fn page_message(&mut self, event: KeyEvent) -> bool {
+        let lines = self.message_lines().len();
+        if lines <= 1 {
+            return false;
+        }
+
+        let rows = self.message_rows();
+        if lines <= rows {
+            self.show_message("");
+            return false;
+        }
+
+        match event.code {
+            KeyCode::Char('q') | KeyCode::Esc => self.show_message(""),
+            _ => self.message.top += rows - 1,
         }
+        true
+    }
+
+    pub fn write(&mut self) -> io::Result<()> {
+        write_file(&self.filename, &self.content.join("\n"))?;
+        self.written = self.content.clone();
+        Ok(())
+    }
+
+    /// Whether the content changed since it was last read or written
+    pub fn modified(&self) -> bool {
+        self.content != self.written
+    }
+
+    pub fn show_message(&mut self, message: impl ToString) {
+        self.message = Output {
+            message: message.to_string(),
+            ..Output::default()
+        };
+    }
+
+    pub fn show_error(&mut self, message: impl ToString) {
+        self.message = Output {
+            message: message.to_string(),
+            error: true,
+            ..Output::default()
+        };
     }
 
     pub fn change_mode(&mut self, mode: Mode) {
         self.mode = mode;
         self.keys = String::new();
+        self.pending = String::new();
+        self.command_cursor = 0;
+        self.recall = None;
+        if mode != Mode::Insert {
+            self.insert_start = None;
+            self.auto_indent = None;
+        }
+    }
+
+    /// Inserts lines before `row`, moving the lines marked by `:global` below them along
+    pub fn insert_lines(&mut self, row: usize, lines: impl IntoIterator<Item = String>) {
+        let len = self.content.len();
+        self.content.splice(row..row, lines);
+        let added = self.content.len() - len;
+
+        for marked in self
+            .global_marks
+            .iter_mut()
+            .filter(|marked| **marked >= row)
+        {
+            *marked += added;
+        }
+    }
+
+    /// Removes the lines in `range`, unmarking them for `:global` and moving the marked lines
+    /// below them up
+    pub fn remove_lines(&mut self, range: Range<usize>) -> Vec<String> {
+        let lines: Vec<String> = self.content.drain(range.clone()).collect();
+
+        self.global_marks.retain_mut(|marked| {
+            if *marked >= range.end {
+                *marked -= range.len();
+                true
+            } else {
+                *marked < range.start
+            }
+        });
+        lines
     }
 
     pub fn row(&self, row: usize) -> &String {
@@ -106,17 +332,59 @@ impl Buffer {
             .unwrap_or_else(|| panic!("row: {} doesn't exist", row))
     }
 
-    pub fn message(&self) -> Span {
+    pub fn message(&self) -> Span<'_> {
+        self.styled(&self.message.message)
+    }
+
+    fn styled<'a>(&self, text: &'a str) -> Span<'a> {
         if self.message.error {
-            Span::styled(&self.message.message, Style::default().fg(Color::Red))
+            Span::styled(text, Style::default().fg(Color::Red))
         } else {
-            Span::raw(&self.message.message)
+            Span::raw(text)
+        }
+    }
+
+    /// The lines of the message from the first one not paged past yet
+    pub fn message_lines(&self) -> Vec<&str> {
+        self.message
+            .message
+            .lines()
+            .skip(self.message.top)
+            .collect()
+    }
+
+    /// The lines of the message that fit in the message area, with a prompt in the last one when
+    /// the rest doesn't
+    pub fn message_page(&self) -> Vec<Span<'_>> {
+        let lines = self.message_lines();
+        let rows = self.message_rows();
+        if lines.len() <= rows {
+            return lines.into_iter().map(|line| self.styled(line)).collect();
+        }
+
+        let mut page: Vec<Span> = lines[..rows - 1]
+            .iter()
+            .map(|line| self.styled(line))
+            .collect();
+        page.push(Span::styled(
+            "-- More --",
+            Style::default().fg(Color::Green),
+        ));
+        page
+    }
+
+    /// The number of lines of the message area, all the lines of the message before the first
+    /// frame sized it
+    fn message_rows(&self) -> usize {
+        match self.view.message_height {
+            0 => self.message_lines().len(),
+            height => height.max(2),
         }
     }
 
     #[cfg(test)]
     pub fn input_keys(&mut self, text: &str) {
-        use ratatui::crossterm::event::{KeyCode, KeyModifiers};
+        use ratatui::crossterm::event::KeyModifiers;
 
         for char in text.chars() {
             self.handle_keys(KeyEvent::new(KeyCode::Char(char), KeyModifiers::NONE));
@@ -128,6 +396,8 @@ impl Buffer {
 struct Output {
     message: String,
     error: bool,
+    /// The first line shown of a message paged through with `-- More --`
+    top: usize,
 }
 
 #[derive(Default, Clone, Copy, Debug, PartialEq)]
@@ -136,15 +406,6 @@ pub struct Position {
     pub row: usize,
 }
 
-impl From<Position> for TuiPosition {
-    fn from(value: Position) -> Self {
-        TuiPosition {
-            x: value.col as u16,
-            y: value.row as u16,
-        }
-    }
-}
-
 pub struct Register(Rc<RefCell<HashMap<char, String>>>);
 
 #[allow(dead_code)]
@@ -158,14 +419,22 @@ impl Register {
     }
 
     pub fn get(&self, char: char) -> String {
-        match (*self.0).borrow().get(&char) {
+        match (*self.0).borrow().get(&Self::name(char)) {
             Some(value) => value.to_string(),
             None => String::new(),
         }
     }
 
     pub fn set(&self, char: char, value: String) -> Option<String> {
-        (*self.0).borrow_mut().insert(char, value)
+        (*self.0).borrow_mut().insert(Self::name(char), value)
+    }
+
+    /// Maps `"`, the name the unnamed register has in Vim, onto the default register
+    fn name(char: char) -> char {
+        match char {
+            '"' => '*',
+            char => char,
+        }
     }
 
     pub fn get_default(&self) -> String {
@@ -196,7 +465,7 @@ mod tests {
         let filename = String::from("test");
         let mut buf = Buffer::test(filename.clone());
         buf.content[0] = String::from("test");
-        buf.write();
+        buf.write().unwrap();
 
         assert_eq!(read_file(&filename).unwrap(), "test");
         fs::remove_file(filename).unwrap();
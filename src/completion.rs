@@ -0,0 +1,594 @@
+use std::{
+    cmp::{max, min},
+    fs,
+};
+
+use ratatui::{
+    buffer::Buffer as TBuffer,
+    crossterm::event::{KeyCode, KeyEvent, KeyModifiers},
+    layout::Rect,
+    style::{Color, Style},
+    text::Line,
+    widgets::{Clear, Paragraph, Widget},
+};
+
+use crate::{
+    buffer::{Buffer, Position},
+    indent::indent_of,
+    options::{KeywordChars, Options},
+    repeat::record_key,
+    view::screen_position,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CompletionKind {
+    Keyword,
+    Line,
+    File,
+    Dictionary,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Candidate {
+    pub word: String,
+    /// Extra information shown when `completeopt` contains `preview`
+    pub info: String,
+}
+
+/// A running insert-mode completion, replacing the text from `start` to the cursor
+#[derive(Clone, Debug, PartialEq)]
+pub struct Completion {
+    pub kind: CompletionKind,
+    pub start: usize,
+    pub base: String,
+    pub candidates: Vec<Candidate>,
+    pub selected: Option<usize>,
+    pub backward: bool,
+    /// Whether the candidates of the other buffers still have to be added by the app
+    pub other_buffers: bool,
+}
+
+impl CompletionKind {
+    /// Candidates for `base` found in the lines of another buffer
+    pub fn candidates_in(
+        self,
+        lines: &[String],
+        iskeyword: &KeywordChars,
+        base: &str,
+    ) -> Vec<Candidate> {
+        match self {
+            CompletionKind::Keyword => unique(
+                lines
+                    .iter()
+                    .flat_map(|line| {
+                        keywords(line, iskeyword).map(|(_, word)| Candidate {
+                            word: word.to_string(),
+                            info: line.trim().to_string(),
+                        })
+                    })
+                    .filter(|candidate| candidate.word.starts_with(base) && candidate.word != base)
+                    .collect(),
+            ),
+            CompletionKind::Line => unique(
+                lines
+                    .iter()
+                    .map(|line| line.trim_start())
+                    .filter(|line| line.starts_with(base) && *line != base)
+                    .map(|line| Candidate {
+                        word: line.to_string(),
+                        info: String::new(),
+                    })
+                    .collect(),
+            ),
+            CompletionKind::File | CompletionKind::Dictionary => Vec::new(),
+        }
+    }
+}
+
+pub fn start_completion(buf: &mut Buffer, kind: CompletionKind, backward: bool) {
+    let line = buf.row(buf.cursor.row);
+    let before = &line[..buf.cursor.col];
+
+    let start = match kind {
+        CompletionKind::Keyword | CompletionKind::Dictionary => before
+            .char_indices()
+            .rev()
+            .take_while(|(_, char)| buf.options.iskeyword().contains(*char))
+            .last()
+            .map_or(before.len(), |(idx, _)| idx),
+        CompletionKind::Line => min(indent_of(line).len(), buf.cursor.col),
+        CompletionKind::File => before.rfind(char::is_whitespace).map_or(0, |idx| {
+            idx + before[idx..].chars().next().unwrap().len_utf8()
+        }),
+    };
+    let base = before[start..].to_string();
+
+    let mut candidates = match kind {
+        CompletionKind::Keyword => buffer_keywords(buf, start, &base),
+        CompletionKind::Line => buffer_lines(buf, &base),
+        CompletionKind::File => files(&base),
+        CompletionKind::Dictionary => dictionary(&buf.options.dictionary(), &base),
+    };
+    if backward {
+        candidates.reverse();
+    }
+
+    buf.completion = Some(Completion {
+        kind,
+        start,
+        base,
+        candidates: Vec::new(),
+        selected: None,
+        backward,
+        other_buffers: matches!(kind, CompletionKind::Keyword | CompletionKind::Line),
+    });
+    extend_completion(buf, candidates);
+}
+
+/// Adds candidates to the running completion, selecting the first one when it is the first batch
+pub fn extend_completion(buf: &mut Buffer, candidates: Vec<Candidate>) {
+    let noselect = has_option(&buf.options, "noselect");
+    let Some(completion) = buf.completion.as_mut() else {
+        return;
+    };
+
+    let was_empty = completion.candidates.is_empty();
+    for candidate in candidates {
+        if completion
+            .candidates
+            .iter()
+            .all(|existing| existing.word != candidate.word)
+        {
+            completion.candidates.push(candidate);
+        }
+    }
+
+    if completion.candidates.is_empty() {
+        if !completion.other_buffers {
+            buf.completion = None;
+            buf.show_error("Pattern not found");
+        }
+    } else if was_empty && !noselect {
+        let first = if completion.backward {
+            completion.candidates.len() - 1
+        } else {
+            0
+        };
+        select(buf, Some(first));
+    } else {
+        show_status(buf);
+    }
+}
+
+/// Handles a key while a completion is running, returns false if the key still has to be inserted
+pub fn handle_completion_keys(buf: &mut Buffer, event: KeyEvent) -> bool {
+    let Some(completion) = buf.completion.as_ref() else {
+        return false;
+    };
+
+    let (len, selected) = (completion.candidates.len(), completion.selected);
+    let next = |selected: Option<usize>| match selected {
+        None if len > 0 => Some(0),
+        Some(idx) if idx + 1 < len => Some(idx + 1),
+        _ => None,
+    };
+    let prev = |selected: Option<usize>| match selected {
+        None if len > 0 => Some(len - 1),
+        Some(idx) if idx > 0 => Some(idx - 1),
+        _ => None,
+    };
+
+    let ctrl = event.modifiers.contains(KeyModifiers::CONTROL);
+    match event.code {
+        KeyCode::Char('n') if ctrl => select(buf, next(selected)),
+        KeyCode::Down => select(buf, next(selected)),
+        KeyCode::Char('p') if ctrl => select(buf, prev(selected)),
+        KeyCode::Up => select(buf, prev(selected)),
+        KeyCode::Char('e') if ctrl => {
+            select(buf, None);
+            buf.completion = None;
+        }
+        KeyCode::Char('y') if ctrl => accept(buf),
+        _ => {
+            accept(buf);
+            return false;
+        }
+    }
+
+    true
+}
+
+/// Keeps the selected candidate and records it as typed, so that `.` inserts the same text
+fn accept(buf: &mut Buffer) {
+    let Some(completion) = buf.completion.take() else {
+        return;
+    };
+
+    if let Some(selected) = completion.selected {
+        let word = &completion.candidates[selected].word;
+        for char in word[completion.base.len()..].chars() {
+            record_key(buf, KeyEvent::new(KeyCode::Char(char), KeyModifiers::NONE));
+        }
+    }
+}
+
+fn select(buf: &mut Buffer, selected: Option<usize>) {
+    let Some(completion) = buf.completion.as_mut() else {
+        return;
+    };
+
+    completion.selected = selected;
+    let text = match selected {
+        Some(idx) => &completion.candidates[idx].word,
+        None => &completion.base,
+    };
+
+    buf.content[buf.cursor.row].replace_range(completion.start..buf.cursor.col, text);
+    buf.cursor.col = completion.start + text.len();
+    show_status(buf);
+}
+
+fn show_status(buf: &mut Buffer) {
+    let Some(completion) = buf.completion.as_ref() else {
+        return;
+    };
+
+    let status = match completion.selected {
+        Some(idx)
+            if has_option(&buf.options, "preview")
+                && !completion.candidates[idx].info.is_empty() =>
+        {
+            format!(
+                "match {} of {}: {}",
+                idx + 1,
+                completion.candidates.len(),
+                completion.candidates[idx].info
+            )
+        }
+        Some(idx) => format!("match {} of {}", idx + 1, completion.candidates.len()),
+        None => String::from("Back at original"),
+    };
+    buf.show_message(status);
+}
+
+fn has_option(options: &Options, value: &str) -> bool {
+    options.completeopt().iter().any(|option| option == value)
+}
+
+/// Keyword candidates of the buffer, starting after the cursor and wrapping around
+fn buffer_keywords(buf: &Buffer, start: usize, base: &str) -> Vec<Candidate> {
+    let current = Position {
+        row: buf.cursor.row,
+        col: start,
+    };
+    let (before, after): (Vec<_>, Vec<_>) = buf
+        .content
+        .iter()
+        .enumerate()
+        .flat_map(|(row, line)| {
+            keywords(line, buf.options.iskeyword())
+                .map(move |(col, word)| (Position { row, col }, word, line))
+        })
+        .filter(|(position, word, _)| {
+            *position != current && word.starts_with(base) && *word != base
+        })
+        .partition(|(position, _, _)| (position.row, position.col) < (current.row, current.col));
+
+    unique(
+        after
+            .into_iter()
+            .chain(before)
+            .map(|(_, word, line)| Candidate {
+                word: word.to_string(),
+                info: line.trim().to_string(),
+            })
+            .collect(),
+    )
+}
+
+/// Line candidates of the buffer, starting after the cursor line and wrapping around
+fn buffer_lines(buf: &Buffer, base: &str) -> Vec<Candidate> {
+    let rows = buf.content.len();
+    let lines: Vec<String> = (1..rows)
+        .map(|offset| buf.content[(buf.cursor.row + offset) % rows].clone())
+        .collect();
+
+    CompletionKind::Line.candidates_in(&lines, buf.options.iskeyword(), base)
+}
+
+fn files(base: &str) -> Vec<Candidate> {
+    let (dir, prefix) = match base.rfind('/') {
+        Some(idx) => base.split_at(idx + 1),
+        None => ("", base),
+    };
+    let Ok(entries) = fs::read_dir(if dir.is_empty() { "." } else { dir }) else {
+        return Vec::new();
+    };
+
+    let mut candidates: Vec<Candidate> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+
+            let is_dir = entry.file_type().is_ok_and(|kind| kind.is_dir());
+            Some(Candidate {
+                word: format!("{}{}{}", dir, name, if is_dir { "/" } else { "" }),
+                info: String::from(if is_dir { "directory" } else { "file" }),
+            })
+        })
+        .collect();
+
+    candidates.sort_by(|a, b| a.word.cmp(&b.word));
+    candidates
+}
+
+fn dictionary(files: &[String], base: &str) -> Vec<Candidate> {
+    unique(
+        files
+            .iter()
+            .filter_map(|file| Some((file, fs::read_to_string(file).ok()?)))
+            .flat_map(|(file, content)| {
+                content
+                    .split_whitespace()
+                    .filter(|word| word.starts_with(base) && *word != base)
+                    .map(|word| Candidate {
+                        word: word.to_string(),
+                        info: file.to_string(),
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect(),
+    )
+}
+
+/// The keywords of a line with their starting column
+fn keywords<'a>(
+    line: &'a str,
+    iskeyword: &'a KeywordChars,
+) -> impl Iterator<Item = (usize, &'a str)> {
+    let is_keyword = |char| iskeyword.contains(char);
+
+    line.char_indices()
+        .filter(move |(idx, char)| {
+            is_keyword(*char) && !line[..*idx].chars().next_back().is_some_and(is_keyword)
+        })
+        .map(move |(idx, _)| {
+            let end = line[idx..]
+                .find(|char: char| !is_keyword(char))
+                .map_or(line.len(), |len| idx + len);
+            (idx, &line[idx..end])
+        })
+}
+
+fn unique(candidates: Vec<Candidate>) -> Vec<Candidate> {
+    let mut unique: Vec<Candidate> = Vec::new();
+
+    for candidate in candidates {
+        if unique
+            .iter()
+            .all(|existing| existing.word != candidate.word)
+        {
+            unique.push(candidate);
+        }
+    }
+
+    unique
+}
+
+/// Draws the popup menu of the running completion below or above the cursor
+pub fn render_menu(buffer: &Buffer, area: Rect, buf: &mut TBuffer) {
+    let Some(completion) = buffer.completion.as_ref() else {
+        return;
+    };
+
+    let min_len = if has_option(&buffer.options, "menuone") {
+        1
+    } else if has_option(&buffer.options, "menu") {
+        2
+    } else {
+        return;
+    };
+    if completion.candidates.len() < min_len {
+        return;
+    }
+
+    let width = completion
+        .candidates
+        .iter()
+        .map(|candidate| candidate.word.chars().count())
+        .max()
+        .unwrap_or_default()
+        + 2;
+    let start = Position {
+        row: buffer.cursor.row,
+        col: completion.start,
+    };
+    let Some(start) = screen_position(buffer, start, area) else {
+        return;
+    };
+    let (start_row, start_col) = ((start.y - area.y) as usize, (start.x - area.x) as usize);
+
+    let below = start_row + 1;
+    // Without `pumheight` the menu takes as many lines as fit above or below the cursor
+    let limit = match buffer.options.pumheight() {
+        0 => max((area.height as usize).saturating_sub(below), start_row),
+        height => height,
+    };
+    let height = min(completion.candidates.len(), limit);
+    let first = completion
+        .selected
+        .map_or(0, |selected| max(selected + 1, height) - height);
+    let row = if below + height <= area.height as usize || start_row < height {
+        below
+    } else {
+        start_row - height
+    };
+    let col = min(start_col, max(area.width as usize, width) - width);
+
+    let lines: Vec<Line> = completion
+        .candidates
+        .iter()
+        .enumerate()
+        .skip(first)
+        .take(height)
+        .map(|(idx, candidate)| {
+            let style = if Some(idx) == completion.selected {
+                Style::default().bg(Color::Gray).fg(Color::Black)
+            } else {
+                Style::default().bg(Color::DarkGray).fg(Color::White)
+            };
+            Line::styled(format!(" {:<1$} ", candidate.word, width - 2), style)
+        })
+        .collect();
+
+    let menu = Rect::new(
+        area.x + col as u16,
+        area.y + row as u16,
+        width as u16,
+        height as u16,
+    )
+    .intersection(area);
+
+    Clear.render(menu, buf);
+    Paragraph::new(lines).render(menu, buf);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{filesystem::TempDir, mode::Mode};
+
+    fn ctrl(char: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(char), KeyModifiers::CONTROL)
+    }
+
+    #[test]
+    fn completes_keywords() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.cursor.row = 5;
+        buf.mode = Mode::Insert;
+
+        buf.input_keys("co");
+        buf.handle_keys(ctrl('n'));
+        assert_eq!(buf.content[5], "consectetuer");
+
+        buf.handle_keys(ctrl('n'));
+        assert_eq!(buf.content[5], "consectetur");
+
+        buf.handle_keys(ctrl('n'));
+        assert_eq!(buf.content[5], "co");
+
+        buf.handle_keys(ctrl('p'));
+        buf.input_keys(" ");
+        assert_eq!(buf.content[5], "consectetur ");
+        assert_eq!(buf.completion, None);
+    }
+
+    #[test]
+    fn completes_backward() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.cursor = Position { row: 4, col: 11 };
+        buf.mode = Mode::Insert;
+
+        buf.input_keys(" m");
+        buf.handle_keys(ctrl('p'));
+        assert_eq!(buf.content[4], "Lorem.ipsum morbi");
+    }
+
+    #[test]
+    fn completes_lines() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.cursor.row = 5;
+        buf.mode = Mode::Insert;
+
+        buf.input_keys("  Lorem.");
+        buf.handle_keys(ctrl('x'));
+        buf.handle_keys(ctrl('l'));
+        assert_eq!(buf.content[5], "  Lorem.ipsum");
+    }
+
+    #[test]
+    fn completes_files() {
+        let dir = TempDir::new("completes_files");
+        for file in ["mode.rs", "motion.rs", "view.rs"] {
+            fs::write(dir.file(file), "").unwrap();
+        }
+        fs::create_dir(dir.file("mode")).unwrap();
+        let mut buf = Buffer::test(String::new());
+        buf.mode = Mode::Insert;
+
+        buf.input_keys(&format!("open {}", dir.file("mo")));
+        buf.handle_keys(ctrl('x'));
+        buf.handle_keys(ctrl('f'));
+        assert_eq!(buf.content[0], format!("open {}", dir.file("mode.rs")));
+
+        buf.handle_keys(ctrl('n'));
+        assert_eq!(buf.content[0], format!("open {}/", dir.file("mode")));
+        assert_eq!(buf.completion.as_ref().unwrap().candidates.len(), 3);
+    }
+
+    #[test]
+    fn cancels_and_reports_missing() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.cursor.row = 5;
+        buf.mode = Mode::Insert;
+
+        buf.input_keys("Lo");
+        buf.handle_keys(ctrl('n'));
+        assert_eq!(buf.content[5], "Lorem");
+        buf.handle_keys(ctrl('e'));
+        assert_eq!(buf.content[5], "Lo");
+        assert_eq!(buf.completion, None);
+
+        buf.handle_keys(ctrl('x'));
+        buf.handle_keys(ctrl('k'));
+        assert_eq!(buf.completion, None);
+        assert_eq!(buf.message().content, "Pattern not found");
+    }
+
+    #[test]
+    fn noselect_keeps_text() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.options.set("cot=menu,noselect").unwrap();
+        buf.cursor.row = 5;
+        buf.mode = Mode::Insert;
+
+        buf.input_keys("co");
+        buf.handle_keys(ctrl('n'));
+        assert_eq!(buf.content[5], "co");
+        assert_eq!(buf.completion.as_ref().unwrap().candidates.len(), 2);
+    }
+
+    #[test]
+    fn repeats_completed_text() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.cursor.row = 5;
+
+        buf.input_keys("ico");
+        buf.handle_keys(ctrl('n'));
+        buf.handle_keys(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        buf.cursor = Position { row: 6, col: 4 };
+        buf.input_keys(".");
+
+        assert_eq!(buf.content[6], "nullconsectetuera");
+    }
+
+    #[test]
+    fn renders_menu() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.cursor.row = 5;
+        buf.mode = Mode::Insert;
+        buf.input_keys("co");
+        buf.handle_keys(ctrl('n'));
+
+        let mut screen = TBuffer::empty(Rect::new(0, 0, 20, 10));
+        render_menu(&buf, screen.area, &mut screen);
+
+        assert_eq!(screen.cell((1, 6)).unwrap().symbol(), "c");
+        assert_eq!(screen.cell((1, 6)).unwrap().bg, Color::Gray);
+        assert_eq!(screen.cell((1, 7)).unwrap().bg, Color::DarkGray);
+    }
+}
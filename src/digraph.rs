@@ -0,0 +1,428 @@
+/// Digraphs from RFC 1345 that can be entered with `Ctrl-k {char1}{char2}`
+const DIGRAPHS: &[(&str, char)] = &[
+    ("NS", '\u{a0}'),
+    ("!I", '¡'),
+    ("Ct", '¢'),
+    ("Pd", '£'),
+    ("Cu", '¤'),
+    ("Ye", '¥'),
+    ("BB", '¦'),
+    ("SE", '§'),
+    ("':", '¨'),
+    ("Co", '©'),
+    ("-a", 'ª'),
+    ("<<", '«'),
+    ("NO", '¬'),
+    ("--", '\u{ad}'),
+    ("Rg", '®'),
+    ("'m", '¯'),
+    ("DG", '°'),
+    ("+-", '±'),
+    ("2S", '²'),
+    ("3S", '³'),
+    ("''", '´'),
+    ("My", 'µ'),
+    ("PI", '¶'),
+    (".M", '·'),
+    ("',", '¸'),
+    ("1S", '¹'),
+    ("-o", 'º'),
+    (">>", '»'),
+    ("14", '¼'),
+    ("12", '½'),
+    ("34", '¾'),
+    ("?I", '¿'),
+    ("A!", 'À'),
+    ("A'", 'Á'),
+    ("A>", 'Â'),
+    ("A?", 'Ã'),
+    ("A:", 'Ä'),
+    ("AA", 'Å'),
+    ("AE", 'Æ'),
+    ("C,", 'Ç'),
+    ("E!", 'È'),
+    ("E'", 'É'),
+    ("E>", 'Ê'),
+    ("E:", 'Ë'),
+    ("I!", 'Ì'),
+    ("I'", 'Í'),
+    ("I>", 'Î'),
+    ("I:", 'Ï'),
+    ("D-", 'Ð'),
+    ("N?", 'Ñ'),
+    ("O!", 'Ò'),
+    ("O'", 'Ó'),
+    ("O>", 'Ô'),
+    ("O?", 'Õ'),
+    ("O:", 'Ö'),
+    ("*X", '×'),
+    ("O/", 'Ø'),
+    ("U!", 'Ù'),
+    ("U'", 'Ú'),
+    ("U>", 'Û'),
+    ("U:", 'Ü'),
+    ("Y'", 'Ý'),
+    ("TH", 'Þ'),
+    ("ss", 'ß'),
+    ("a!", 'à'),
+    ("a'", 'á'),
+    ("a>", 'â'),
+    ("a?", 'ã'),
+    ("a:", 'ä'),
+    ("aa", 'å'),
+    ("ae", 'æ'),
+    ("c,", 'ç'),
+    ("e!", 'è'),
+    ("e'", 'é'),
+    ("e>", 'ê'),
+    ("e:", 'ë'),
+    ("i!", 'ì'),
+    ("i'", 'í'),
+    ("i>", 'î'),
+    ("i:", 'ï'),
+    ("d-", 'ð'),
+    ("n?", 'ñ'),
+    ("o!", 'ò'),
+    ("o'", 'ó'),
+    ("o>", 'ô'),
+    ("o?", 'õ'),
+    ("o:", 'ö'),
+    ("-:", '÷'),
+    ("o/", 'ø'),
+    ("u!", 'ù'),
+    ("u'", 'ú'),
+    ("u>", 'û'),
+    ("u:", 'ü'),
+    ("y'", 'ý'),
+    ("th", 'þ'),
+    ("y:", 'ÿ'),
+    ("A-", 'Ā'),
+    ("a-", 'ā'),
+    ("A(", 'Ă'),
+    ("a(", 'ă'),
+    ("A;", 'Ą'),
+    ("a;", 'ą'),
+    ("C'", 'Ć'),
+    ("c'", 'ć'),
+    ("C<", 'Č'),
+    ("c<", 'č'),
+    ("D<", 'Ď'),
+    ("d<", 'ď'),
+    ("D/", 'Đ'),
+    ("d/", 'đ'),
+    ("E-", 'Ē'),
+    ("e-", 'ē'),
+    ("E;", 'Ę'),
+    ("e;", 'ę'),
+    ("E<", 'Ě'),
+    ("e<", 'ě'),
+    ("G(", 'Ğ'),
+    ("g(", 'ğ'),
+    ("I-", 'Ī'),
+    ("i-", 'ī'),
+    ("I.", 'İ'),
+    ("i.", 'ı'),
+    ("L/", 'Ł'),
+    ("l/", 'ł'),
+    ("N'", 'Ń'),
+    ("n'", 'ń'),
+    ("N<", 'Ň'),
+    ("n<", 'ň'),
+    ("O-", 'Ō'),
+    ("o-", 'ō'),
+    ("O\"", 'Ő'),
+    ("o\"", 'ő'),
+    ("OE", 'Œ'),
+    ("oe", 'œ'),
+    ("R<", 'Ř'),
+    ("r<", 'ř'),
+    ("S'", 'Ś'),
+    ("s'", 'ś'),
+    ("S,", 'Ş'),
+    ("s,", 'ş'),
+    ("S<", 'Š'),
+    ("s<", 'š'),
+    ("T<", 'Ť'),
+    ("t<", 'ť'),
+    ("U-", 'Ū'),
+    ("u-", 'ū'),
+    ("U0", 'Ů'),
+    ("u0", 'ů'),
+    ("U\"", 'Ű'),
+    ("u\"", 'ű'),
+    ("Y:", 'Ÿ'),
+    ("Z'", 'Ź'),
+    ("z'", 'ź'),
+    ("Z.", 'Ż'),
+    ("z.", 'ż'),
+    ("Z<", 'Ž'),
+    ("z<", 'ž'),
+    ("A*", 'Α'),
+    ("B*", 'Β'),
+    ("G*", 'Γ'),
+    ("D*", 'Δ'),
+    ("E*", 'Ε'),
+    ("Z*", 'Ζ'),
+    ("Y*", 'Η'),
+    ("H*", 'Θ'),
+    ("I*", 'Ι'),
+    ("K*", 'Κ'),
+    ("L*", 'Λ'),
+    ("M*", 'Μ'),
+    ("N*", 'Ν'),
+    ("C*", 'Ξ'),
+    ("O*", 'Ο'),
+    ("P*", 'Π'),
+    ("R*", 'Ρ'),
+    ("S*", 'Σ'),
+    ("T*", 'Τ'),
+    ("U*", 'Υ'),
+    ("F*", 'Φ'),
+    ("X*", 'Χ'),
+    ("Q*", 'Ψ'),
+    ("W*", 'Ω'),
+    ("a*", 'α'),
+    ("b*", 'β'),
+    ("g*", 'γ'),
+    ("d*", 'δ'),
+    ("e*", 'ε'),
+    ("z*", 'ζ'),
+    ("y*", 'η'),
+    ("h*", 'θ'),
+    ("i*", 'ι'),
+    ("k*", 'κ'),
+    ("l*", 'λ'),
+    ("m*", 'μ'),
+    ("n*", 'ν'),
+    ("c*", 'ξ'),
+    ("o*", 'ο'),
+    ("p*", 'π'),
+    ("r*", 'ρ'),
+    ("*s", 'ς'),
+    ("s*", 'σ'),
+    ("t*", 'τ'),
+    ("u*", 'υ'),
+    ("f*", 'φ'),
+    ("x*", 'χ'),
+    ("q*", 'ψ'),
+    ("w*", 'ω'),
+    ("-1", '‐'),
+    ("-N", '–'),
+    ("-M", '—'),
+    ("-3", '―'),
+    ("!2", '‖'),
+    ("'6", '‘'),
+    ("'9", '’'),
+    (".9", '‚'),
+    ("\"6", '“'),
+    ("\"9", '”'),
+    (":9", '„'),
+    ("/-", '†'),
+    ("/=", '‡'),
+    ("..", '‥'),
+    (",.", '…'),
+    ("%0", '‰'),
+    ("1'", '′'),
+    ("2'", '″'),
+    ("<1", '‹'),
+    (">1", '›'),
+    ("Eu", '€'),
+    ("=e", '€'),
+    ("oC", '℃'),
+    ("co", '℅'),
+    ("No", '№'),
+    ("TM", '™'),
+    ("13", '⅓'),
+    ("23", '⅔'),
+    ("18", '⅛'),
+    ("38", '⅜'),
+    ("58", '⅝'),
+    ("78", '⅞'),
+    ("0S", '⁰'),
+    ("4S", '⁴'),
+    ("5S", '⁵'),
+    ("6S", '⁶'),
+    ("7S", '⁷'),
+    ("8S", '⁸'),
+    ("9S", '⁹'),
+    ("0s", '₀'),
+    ("1s", '₁'),
+    ("2s", '₂'),
+    ("3s", '₃'),
+    ("4s", '₄'),
+    ("5s", '₅'),
+    ("6s", '₆'),
+    ("7s", '₇'),
+    ("8s", '₈'),
+    ("9s", '₉'),
+    ("<-", '←'),
+    ("-!", '↑'),
+    ("->", '→'),
+    ("-v", '↓'),
+    ("<>", '↔'),
+    ("UD", '↕'),
+    ("<=", '⇐'),
+    ("=>", '⇒'),
+    ("==", '⇔'),
+    ("FA", '∀'),
+    ("dP", '∂'),
+    ("TE", '∃'),
+    ("/0", '∅'),
+    ("DE", '∆'),
+    ("NB", '∇'),
+    ("(-", '∈'),
+    ("-)", '∋'),
+    ("*P", '∏'),
+    ("+Z", '∑'),
+    ("-2", '−'),
+    ("-+", '∓'),
+    ("*-", '∗'),
+    ("Ob", '∘'),
+    ("Sb", '∙'),
+    ("RT", '√'),
+    ("0(", '∝'),
+    ("00", '∞'),
+    ("-L", '∟'),
+    ("-V", '∠'),
+    ("PP", '∥'),
+    ("AN", '∧'),
+    ("OR", '∨'),
+    ("(U", '∩'),
+    (")U", '∪'),
+    ("In", '∫'),
+    ("DI", '∬'),
+    ("Io", '∮'),
+    (".:", '∴'),
+    (":.", '∵'),
+    (":R", '∶'),
+    ("::", '∷'),
+    ("?1", '∼'),
+    ("CG", '∾'),
+    ("?-", '≃'),
+    ("?=", '≅'),
+    ("?2", '≈'),
+    ("=?", '≌'),
+    ("HI", '≓'),
+    ("!=", '≠'),
+    ("=3", '≡'),
+    ("=<", '≤'),
+    (">=", '≥'),
+    ("<*", '≪'),
+    ("*>", '≫'),
+    ("!<", '≮'),
+    ("!>", '≯'),
+    ("(C", '⊂'),
+    (")C", '⊃'),
+    ("(_", '⊆'),
+    (")_", '⊇'),
+    ("0.", '⊙'),
+    ("02", '⊚'),
+    ("-T", '⊥'),
+    (".P", '⋅'),
+    (":3", '⋮'),
+    (".3", '⋯'),
+    ("hh", '─'),
+    ("HH", '━'),
+    ("vv", '│'),
+    ("VV", '┃'),
+    ("dr", '┌'),
+    ("dl", '┐'),
+    ("ur", '└'),
+    ("ul", '┘'),
+    ("vr", '├'),
+    ("vl", '┤'),
+    ("dh", '┬'),
+    ("uh", '┴'),
+    ("vh", '┼'),
+    ("FB", '█'),
+    ("fS", '■'),
+    ("OS", '□'),
+    ("Db", '◆'),
+    ("0m", '○'),
+    ("0M", '●'),
+    ("*2", '★'),
+    ("*1", '☆'),
+    ("cH", '♥'),
+    ("cS", '♠'),
+    ("cD", '♦'),
+    ("cC", '♣'),
+    ("Md", '♩'),
+    ("M8", '♪'),
+    ("M2", '♫'),
+    ("Mb", '♭'),
+    ("Mx", '♮'),
+    ("MX", '♯'),
+    ("OK", '✓'),
+    ("XX", '✗'),
+];
+
+/// The character for a digraph, trying the reversed order as well. Like Vim the second character
+/// is used when there is no such digraph
+pub fn digraph(first: char, second: char) -> char {
+    let find = |first: char, second: char| {
+        DIGRAPHS
+            .iter()
+            .find(|(keys, _)| keys.chars().eq([first, second]))
+            .map(|(_, char)| *char)
+    };
+
+    find(first, second)
+        .or_else(|| find(second, first))
+        .unwrap_or(second)
+}
+
+/// The digraph that produces a character
+pub fn digraph_for(char: char) -> Option<&'static str> {
+    DIGRAPHS
+        .iter()
+        .find(|(_, value)| *value == char)
+        .map(|(keys, _)| *keys)
+}
+
+/// The width of a digraph in the output of `:digraphs`, with the space before the next one
+const LISTED_WIDTH: usize = 12;
+
+/// All digraphs formatted like the output of `:digraphs`, in lines as many columns wide as fit in
+/// `width`
+pub fn list_digraphs(width: usize) -> String {
+    let entries: Vec<String> = DIGRAPHS
+        .iter()
+        .map(|(keys, char)| format!("{} {} {:<5}  ", keys, char, *char as u32))
+        .collect();
+
+    entries
+        .chunks((width / LISTED_WIDTH).max(1))
+        .map(|row| row.concat().trim_end().to_string())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_digraphs() {
+        assert_eq!(digraph('e', ':'), 'ë');
+        assert_eq!(digraph(':', 'e'), 'ë');
+        assert_eq!(digraph('a', '*'), 'α');
+        assert_eq!(digraph('q', 'z'), 'z');
+    }
+
+    #[test]
+    fn finds_digraph_for_char() {
+        assert_eq!(digraph_for('€'), Some("Eu"));
+        assert_eq!(digraph_for('a'), None);
+    }
+
+    #[test]
+    fn lists_digraphs() {
+        let list = list_digraphs(30);
+        let mut lines = list.lines();
+
+        assert_eq!(lines.next(), Some("NS \u{a0} 160    !I ¡ 161"));
+        assert_eq!(lines.next(), Some("Ct ¢ 162    Pd £ 163"));
+        assert_eq!(list.lines().count(), DIGRAPHS.len().div_ceil(2));
+        assert_eq!(list_digraphs(0).lines().count(), DIGRAPHS.len());
+    }
+}
@@ -0,0 +1,551 @@
+use std::cmp::min;
+
+use crate::{
+    buffer::{Buffer, Position},
+    search::{compile, find_match, Offset, Search},
+    utils::split_count,
+};
+
+/// Accepts a line range
+const RANGE: u8 = 1;
+/// Accepts a `!` after the name
+const BANG: u8 = 1 << 1;
+/// Accepts a count after the name, making the range that many lines from its end
+const COUNT: u8 = 1 << 2;
+/// Accepts a register name before the count
+const REGISTER: u8 = 1 << 3;
+/// Takes the rest of the line as its argument, so `|` doesn't start another command
+const TAKES_BAR: u8 = 1 << 4;
+/// Its argument can contain `"`, so it doesn't start a comment
+const TAKES_QUOTE: u8 = 1 << 5;
+/// Its argument starts with a pattern, where `|` doesn't start another command
+const PATTERN: u8 = 1 << 6;
+
+/// The commands as `(shortest abbreviation, full name, flags)`. Commands sharing a prefix are
+/// ordered so the one Vim picks for the shorter name comes first
+const COMMANDS: &[(&str, &str, u8)] = &[
+    ("&", "&", RANGE | TAKES_QUOTE),
+    ("ab", "abbreviate", TAKES_QUOTE),
+    ("abc", "abclear", 0),
+    ("ca", "cabbrev", TAKES_QUOTE),
+    ("cabc", "cabclear", 0),
+    ("cuna", "cunabbrev", TAKES_QUOTE),
+    ("d", "delete", RANGE | COUNT | REGISTER),
+    ("dig", "digraphs", 0),
+    ("e", "edit", BANG),
+    ("ene", "enew", BANG),
+    ("f", "file", 0),
+    ("g", "global", RANGE | BANG | TAKES_BAR | TAKES_QUOTE),
+    ("ia", "iabbrev", TAKES_QUOTE),
+    ("iabc", "iabclear", 0),
+    ("iuna", "iunabbrev", TAKES_QUOTE),
+    ("noh", "nohlsearch", 0),
+    ("norm", "normal", RANGE | BANG | TAKES_BAR | TAKES_QUOTE),
+    ("p", "print", RANGE | COUNT),
+    ("q", "quit", BANG),
+    ("qa", "qall", BANG),
+    ("r", "read", RANGE),
+    ("s", "substitute", RANGE | TAKES_QUOTE | PATTERN),
+    ("sav", "saveas", BANG),
+    ("se", "set", 0),
+    ("setg", "setglobal", 0),
+    ("setl", "setlocal", 0),
+    ("una", "unabbreviate", TAKES_QUOTE),
+    ("v", "vglobal", RANGE | TAKES_BAR | TAKES_QUOTE),
+    ("w", "write", RANGE | BANG),
+    ("wa", "wall", BANG),
+    ("wq", "wq", RANGE | BANG),
+    ("wqa", "wqall", BANG),
+    ("x", "xit", RANGE | BANG),
+    ("y", "yank", RANGE | COUNT | REGISTER),
+];
+
+/// Lines a command acts on, both ends included
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LineRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// One command of a command line, like `1,$d a` or `q!`
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExCommand {
+    pub range: Option<LineRange>,
+    /// Set when the range ends at line 0, before the first line, as in `:0r`. The range then
+    /// holds the first line for the commands that take 0 as 1
+    pub zero: bool,
+    /// The full name of the command, empty for a line range alone
+    pub name: &'static str,
+    pub bang: bool,
+    pub register: Option<char>,
+    pub args: String,
+}
+
+impl ExCommand {
+    /// The given range, or the cursor line
+    pub fn lines(&self, buf: &Buffer) -> LineRange {
+        self.range.unwrap_or(LineRange {
+            start: buf.cursor.row,
+            end: buf.cursor.row,
+        })
+    }
+}
+
+/// Parses the first command of a command line, returning it with the commands chained after it
+/// with `|`. Addresses are resolved against the buffer as it is now, so a chained command has to
+/// be parsed after the ones before it ran. `None` for an empty line or a comment
+pub fn parse_command<'a>(
+    buf: &mut Buffer,
+    line: &'a str,
+) -> Result<(Option<ExCommand>, Option<&'a str>), String> {
+    let line = line.trim_start_matches([':', ' ', '\t']);
+    if line.is_empty() || line.starts_with('"') {
+        return Ok((None, None));
+    }
+
+    let (range, zero, rest) = parse_range(buf, line)?;
+    let rest = rest.trim_start();
+
+    let name_len = match rest.starts_with('&') {
+        true => 1,
+        false => rest
+            .find(|char: char| !char.is_ascii_alphabetic())
+            .unwrap_or(rest.len()),
+    };
+    let (typed, rest) = rest.split_at(name_len);
+
+    let (name, flags) = match typed {
+        // Anything after a range alone has to be another command or a comment
+        "" if !rest.is_empty() && !rest.starts_with(['|', '"']) => {
+            return Err(format!("Not an editor command: {}", line))
+        }
+        "" => ("", RANGE),
+        typed => COMMANDS
+            .iter()
+            .find(|(short, full, _)| typed.len() >= short.len() && full.starts_with(typed))
+            .map(|(_, full, flags)| (*full, *flags))
+            .ok_or_else(|| format!("Not an editor command: {}", line))?,
+    };
+
+    let (bang, rest) = match rest.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, rest),
+    };
+    if bang && flags & BANG == 0 {
+        return Err(String::from("No ! allowed"));
+    }
+    if range.is_some() && flags & RANGE == 0 {
+        return Err(String::from("No range allowed"));
+    }
+    // Going to a line past the end goes to the last line, but a command can't act on it
+    let last = buf.content.len() - 1;
+    let range = match range {
+        Some(range) if range.end > last && name.is_empty() => Some(LineRange {
+            start: min(range.start, last),
+            end: last,
+        }),
+        Some(range) if range.end > last => return Err(String::from("Invalid range")),
+        range => range,
+    };
+
+    let skipped = match flags & PATTERN {
+        0 => 0,
+        _ => pattern_len(rest),
+    };
+    let (args, next) = match flags & TAKES_BAR {
+        0 => {
+            let (args, next) = split_bar(&rest[skipped..], flags & TAKES_QUOTE == 0);
+            (format!("{}{}", &rest[..skipped], args), next)
+        }
+        _ => (rest.to_string(), None),
+    };
+    let mut command = ExCommand {
+        range,
+        zero,
+        name,
+        bang,
+        register: None,
+        // Trailing blanks can matter to a command taking the rest of the line, like `:normal`
+        args: match flags & TAKES_BAR {
+            0 => args.trim(),
+            _ => args.trim_start(),
+        }
+        .to_string(),
+    };
+
+    if flags & REGISTER != 0 {
+        if let Some(register) = command
+            .args
+            .chars()
+            .next()
+            .filter(|char| !char.is_ascii_digit())
+        {
+            command.register = Some(register);
+            command.args = command.args[register.len_utf8()..].trim_start().to_string();
+        }
+    }
+
+    if flags & COUNT != 0 {
+        if let (Some(count), rest) = split_count(&command.args) {
+            let end = command.lines(buf).end;
+            command.range = Some(LineRange {
+                start: end,
+                end: min(end + count - 1, buf.content.len() - 1),
+            });
+            command.args = rest.trim_start().to_string();
+        }
+    }
+
+    Ok((Some(command), next))
+}
+
+/// Splits the arguments at the first `|` that isn't escaped with a backslash, dropping a `"`
+/// comment when `comments` is set
+fn split_bar(rest: &str, comments: bool) -> (String, Option<&str>) {
+    let mut args = String::new();
+    let mut chars = rest.char_indices().peekable();
+
+    while let Some((idx, char)) = chars.next() {
+        match char {
+            '\\' if chars.peek().is_some_and(|(_, next)| *next == '|') => {
+                args.push('|');
+                chars.next();
+            }
+            '|' => return (args, Some(&rest[idx + 1..])),
+            // A comment runs to the end of the line, `|` included
+            '"' if comments => return (args, None),
+            char => args.push(char),
+        }
+    }
+
+    (args, None)
+}
+
+/// The length of a `/pat/` at the start of the arguments, up to and including its closing
+/// delimiter
+fn pattern_len(args: &str) -> usize {
+    let Some(delim) = args.chars().next().filter(|char| is_delimiter(*char)) else {
+        return 0;
+    };
+
+    let mut chars = args.char_indices().skip(1);
+    while let Some((idx, char)) = chars.next() {
+        match char {
+            '\\' => {
+                chars.next();
+            }
+            char if char == delim => return idx + char.len_utf8(),
+            _ => {}
+        }
+    }
+
+    args.len()
+}
+
+/// Whether the character can delimit a pattern in a command like `:s#a#b#`
+pub fn is_delimiter(char: char) -> bool {
+    !char.is_alphanumeric() && !char.is_whitespace() && !matches!(char, '\\' | '"' | '|')
+}
+
+/// Parses the addresses at the start of a command line, like `.,$`, `%` or `'a;/foo/+1`, and
+/// whether the range ends at line 0
+fn parse_range<'a>(
+    buf: &mut Buffer,
+    line: &'a str,
+) -> Result<(Option<LineRange>, bool, &'a str), String> {
+    if let Some(rest) = line.strip_prefix('%') {
+        let range = LineRange {
+            start: 0,
+            end: buf.content.len() - 1,
+        };
+        return Ok((Some(range), false, rest));
+    }
+
+    let mut current = buf.cursor.row;
+    let mut addresses = Vec::new();
+    let mut rest = line;
+
+    loop {
+        let (address, after) = parse_address(buf, rest.trim_start(), current)?;
+        rest = after.trim_start();
+
+        // A missing address next to a separator stands for the cursor line
+        let separated = rest.starts_with([',', ';']);
+        match address {
+            Some(number) => addresses.push(number),
+            None if separated || !addresses.is_empty() => addresses.push(buf.cursor.row + 1),
+            None => break,
+        }
+
+        match rest.chars().next() {
+            Some(',') => rest = &rest[1..],
+            // With `;` the following addresses are relative to this one
+            Some(';') => {
+                current = addresses.last().unwrap().max(&1) - 1;
+                if current >= buf.content.len() {
+                    return Err(String::from("Invalid range"));
+                }
+                rest = &rest[1..];
+            }
+            _ => break,
+        }
+    }
+
+    let zero = addresses.last() == Some(&0);
+    let row = |number: usize| number.max(1) - 1;
+    let range = match addresses[..] {
+        [] => None,
+        [number] => Some(LineRange {
+            start: row(number),
+            end: row(number),
+        }),
+        [.., start, end] if start > end => return Err(String::from("Backwards range given")),
+        [.., start, end] => Some(LineRange {
+            start: row(start),
+            end: row(end),
+        }),
+    };
+
+    Ok((range, zero, rest))
+}
+
+/// Parses a single address with its `+N` and `-N` offsets, returning its line number, which is 0
+/// before the first line
+fn parse_address<'a>(
+    buf: &mut Buffer,
+    line: &'a str,
+    current: usize,
+) -> Result<(Option<usize>, &'a str), String> {
+    let mut chars = line.chars();
+    let (base, mut rest) = match chars.next() {
+        Some('.') => (Some(current as isize + 1), chars.as_str()),
+        Some('$') => (Some(buf.content.len() as isize), chars.as_str()),
+        Some('\'') => {
+            let mark = chars.next().ok_or("Mark not set")?;
+            let pos = buf.marks.get(&mark).ok_or("Mark not set")?;
+            (Some(pos.row as isize + 1), chars.as_str())
+        }
+        Some(delim @ ('/' | '?')) => {
+            let (pattern, rest) = split_pattern(chars.as_str(), delim);
+            let row = search_line(buf, &pattern, delim == '?', current)?;
+            (Some(row as isize + 1), rest)
+        }
+        Some(char) if char.is_ascii_digit() => {
+            let idx = line
+                .find(|char: char| !char.is_ascii_digit())
+                .unwrap_or(line.len());
+            let number: isize = line[..idx].parse().map_err(|_| "Invalid range")?;
+            (Some(number), &line[idx..])
+        }
+        _ => (None, line),
+    };
+
+    let mut number = base;
+    while let Some(sign @ ('+' | '-')) = rest.chars().next() {
+        let (count, after) = split_count(&rest[1..]);
+        let count = count.unwrap_or(1) as isize;
+        let offset = if sign == '+' { count } else { -count };
+        number = Some(number.unwrap_or(current as isize + 1) + offset);
+        rest = after;
+    }
+
+    // A line past the end is only an error once it's known whether a command acts on it
+    match number {
+        Some(number) if number < 0 => Err(String::from("Invalid range")),
+        number => Ok((number.map(|number| number as usize), rest)),
+    }
+}
+
+/// Splits a `/pat/` address at its closing delimiter, which a backslash escapes
+pub fn split_pattern(line: &str, delim: char) -> (String, &str) {
+    let mut pattern = String::new();
+    let mut chars = line.char_indices();
+
+    while let Some((idx, char)) = chars.next() {
+        match char {
+            '\\' => match chars.next() {
+                Some((_, next)) if next == delim => pattern.push(next),
+                Some((_, next)) => {
+                    pattern.push('\\');
+                    pattern.push(next);
+                }
+                None => pattern.push('\\'),
+            },
+            char if char == delim => return (pattern, &line[idx + 1..]),
+            char => pattern.push(char),
+        }
+    }
+
+    (pattern, "")
+}
+
+/// The next line after `current` with a match for the pattern, or the one before it going
+/// backward. An empty pattern uses the last search
+fn search_line(
+    buf: &mut Buffer,
+    pattern: &str,
+    backward: bool,
+    current: usize,
+) -> Result<usize, String> {
+    let pattern = match pattern {
+        "" => buf
+            .last_search
+            .as_ref()
+            .map(|search| search.pattern.clone())
+            .ok_or("No previous regular expression")?,
+        pattern => pattern.to_string(),
+    };
+    let regex = compile(&pattern, &buf.options)?;
+
+    let from = Position {
+        row: current,
+        col: if backward { 0 } else { buf.row(current).len() },
+    };
+    let (found, _, _) = find_match(buf, &regex, from, backward, 1)
+        .ok_or_else(|| format!("Pattern not found: {}", pattern))?;
+
+    buf.register.set('/', pattern.clone());
+    buf.last_search = Some(Search {
+        pattern,
+        backward,
+        offset: Offset::None,
+    });
+
+    Ok(found.row)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(buf: &mut Buffer, line: &str) -> ExCommand {
+        parse_command(buf, line).unwrap().0.unwrap()
+    }
+
+    #[test]
+    fn parses_names() {
+        let mut buf = Buffer::test(String::new());
+
+        let command = parse(&mut buf, "q!");
+        assert_eq!((command.name, command.bang), ("quit", true));
+        assert_eq!(parse(&mut buf, "se ai").args, "ai");
+        assert_eq!(parse(&mut buf, "iabc").name, "iabclear");
+        assert_eq!(parse(&mut buf, "y").name, "yank");
+
+        assert_eq!(
+            parse_command(&mut buf, "foo"),
+            Err(String::from("Not an editor command: foo"))
+        );
+        assert_eq!(
+            parse_command(&mut buf, "noh!"),
+            Err(String::from("No ! allowed"))
+        );
+        assert_eq!(parse_command(&mut buf, "\" note"), Ok((None, None)));
+        assert_eq!(
+            parse_command(&mut buf, "1€"),
+            Err(String::from("Not an editor command: 1€"))
+        );
+        assert_eq!(
+            parse_command(&mut buf, "1 #"),
+            Err(String::from("Not an editor command: 1 #"))
+        );
+        assert_eq!(parse(&mut buf, "1 \" note").name, "");
+    }
+
+    #[test]
+    fn parses_ranges() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.cursor.row = 2;
+        let range = |buf: &mut Buffer, line: &str| parse(buf, line).range;
+
+        assert_eq!(range(&mut buf, "3"), Some(LineRange { start: 2, end: 2 }));
+        assert_eq!(range(&mut buf, ".,$"), Some(LineRange { start: 2, end: 6 }));
+        assert_eq!(range(&mut buf, "%"), Some(LineRange { start: 0, end: 6 }));
+        assert_eq!(
+            range(&mut buf, "-,+2"),
+            Some(LineRange { start: 1, end: 4 })
+        );
+        assert_eq!(range(&mut buf, ",+"), Some(LineRange { start: 2, end: 3 }));
+        assert_eq!(
+            range(&mut buf, "/nulla/"),
+            Some(LineRange { start: 6, end: 6 })
+        );
+        assert_eq!(
+            range(&mut buf, "?Lorem?"),
+            Some(LineRange { start: 0, end: 0 })
+        );
+        assert_eq!(
+            range(&mut buf, "1;/Lorem/+1"),
+            Some(LineRange { start: 0, end: 5 })
+        );
+        assert_eq!(
+            range(&mut buf, "1,/Lorem/"),
+            Some(LineRange { start: 0, end: 4 })
+        );
+
+        buf.marks.insert('a', Position { row: 5, col: 0 });
+        assert_eq!(
+            range(&mut buf, "'a,$"),
+            Some(LineRange { start: 5, end: 6 })
+        );
+        assert_eq!(
+            parse_command(&mut buf, "'<,'>d"),
+            Err(String::from("Mark not set"))
+        );
+        buf.marks.insert('<', Position { row: 1, col: 0 });
+        buf.marks.insert('>', Position { row: 3, col: 4 });
+        assert_eq!(
+            range(&mut buf, "'<,'>"),
+            Some(LineRange { start: 1, end: 3 })
+        );
+        assert_eq!(range(&mut buf, "9"), Some(LineRange { start: 6, end: 6 }));
+        assert_eq!(range(&mut buf, "0"), Some(LineRange { start: 0, end: 0 }));
+        assert!(parse(&mut buf, "0r").zero);
+        assert!(!parse(&mut buf, "0+1r").zero);
+        assert_eq!(range(&mut buf, ".+5"), Some(LineRange { start: 6, end: 6 }));
+        assert_eq!(
+            parse_command(&mut buf, "9d"),
+            Err(String::from("Invalid range"))
+        );
+        assert_eq!(
+            parse_command(&mut buf, ".-5"),
+            Err(String::from("Invalid range"))
+        );
+        assert_eq!(
+            parse_command(&mut buf, "3,2d"),
+            Err(String::from("Backwards range given"))
+        );
+        assert_eq!(
+            parse_command(&mut buf, "2,3q"),
+            Err(String::from("No range allowed"))
+        );
+    }
+
+    #[test]
+    fn parses_counts_and_chains() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+
+        let command = parse(&mut buf, "2d a 3");
+        assert_eq!(command.range, Some(LineRange { start: 1, end: 3 }));
+        assert_eq!(command.register, Some('a'));
+        assert_eq!(
+            parse(&mut buf, "y 9").range,
+            Some(LineRange { start: 0, end: 6 })
+        );
+
+        let (command, next) = parse_command(&mut buf, "set ai | q").unwrap();
+        assert_eq!(command.unwrap().args, "ai");
+        assert_eq!(next, Some(" q"));
+        let (command, next) = parse_command(&mut buf, "set ai \" indent | q").unwrap();
+        assert_eq!(command.unwrap().args, "ai");
+        assert_eq!(next, None);
+
+        let (command, next) = parse_command(&mut buf, "s/a\\|b/c/|q").unwrap();
+        assert_eq!(command.unwrap().args, "/a\\|b/c/");
+        assert_eq!(next, Some("q"));
+
+        let (command, next) = parse_command(&mut buf, "ab a b\\|c|q").unwrap();
+        assert_eq!(command.unwrap().args, "a b|c");
+        assert_eq!(next, Some("q"));
+    }
+}
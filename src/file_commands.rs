@@ -0,0 +1,385 @@
+use std::{io::ErrorKind, path::Path};
+
+use crate::{
+    buffer::{Buffer, Position},
+    ex::ExCommand,
+    filesystem::{append_file, read_file, write_file},
+    navigation::first_not_whitespace,
+    view::View,
+};
+
+/// A command the app carries out for every buffer
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AllBuffers {
+    Write,
+    Quit { force: bool },
+    WriteQuit,
+}
+
+const NOT_WRITTEN: &str = "No write since last change (add ! to override)";
+
+/// Runs `:e file`, or reloads the current file without a name. Unsaved changes need `!`
+pub fn edit(buf: &mut Buffer, command: &ExCommand) -> Result<(), String> {
+    if buf.modified() && !command.bang {
+        return Err(String::from(NOT_WRITTEN));
+    }
+
+    let filename = match command.args.as_str() {
+        "" if buf.filename.is_empty() => return Err(String::from("No file name")),
+        "" => buf.filename.clone(),
+        filename => filename.to_string(),
+    };
+
+    let (content, message) = match read_file(&filename) {
+        Ok(text) => {
+            let message = format!(
+                "\"{}\" {}L, {}B",
+                filename,
+                text.lines().count(),
+                text.len()
+            );
+            (text.lines().map(String::from).collect(), message)
+        }
+        Err(error) if error.kind() == ErrorKind::NotFound => {
+            (Vec::new(), format!("\"{}\" [New]", filename))
+        }
+        Err(error) => return Err(format!("Can't open file {}: {}", filename, error)),
+    };
+
+    load(buf, filename, content);
+    buf.show_message(message);
+    Ok(())
+}
+
+/// Runs `:enew`, starting an unnamed empty buffer
+pub fn edit_new(buf: &mut Buffer, command: &ExCommand) -> Result<(), String> {
+    if buf.modified() && !command.bang {
+        return Err(String::from(NOT_WRITTEN));
+    }
+
+    load(buf, String::new(), Vec::new());
+    Ok(())
+}
+
+/// Replaces the buffer with the content of another file, forgetting what belonged to the old one
+fn load(buf: &mut Buffer, filename: String, mut content: Vec<String>) {
+    if content.is_empty() {
+        content.push(String::new());
+    }
+
+    buf.filename = filename;
+    buf.written = content.clone();
+    buf.renamed = false;
+    buf.content = content;
+    buf.cursor = Position::default();
+    buf.view = View {
+        height: buf.view.height,
+        width: buf.view.width,
+        message_height: buf.view.message_height,
+        ..View::default()
+    };
+    buf.marks.clear();
+    buf.global_marks.clear();
+    buf.last_insert = None;
+}
+
+/// Runs `:[range]w[!] [>>] [file]`. Writing to another existing file or only part of the
+/// buffer to its own file needs `!`, and an unnamed buffer takes the name it's written to
+pub fn write(buf: &mut Buffer, command: &ExCommand) -> Result<(), String> {
+    let (append, filename) = match command.args.strip_prefix(">>") {
+        Some(filename) => (true, filename.trim_start()),
+        None => (false, command.args.as_str()),
+    };
+    let filename = match filename {
+        "" if buf.filename.is_empty() => return Err(String::from("No file name")),
+        "" => buf.filename.clone(),
+        filename => filename.to_string(),
+    };
+
+    let own_file = filename == buf.filename;
+    let whole = command
+        .range
+        .is_none_or(|range| range.start == 0 && range.end == buf.content.len() - 1);
+    if !append && !command.bang {
+        if own_file && !whole {
+            return Err(String::from("Use ! to write partial buffer"));
+        }
+        // A renamed buffer doesn't own the file of its new name until it's written to it
+        if (!own_file || buf.renamed) && Path::new(&filename).exists() {
+            return Err(String::from("File exists (add ! to override)"));
+        }
+    }
+
+    let lines = command.range.map_or(&buf.content[..], |range| {
+        &buf.content[range.start..=range.end]
+    });
+    let text = lines.join("\n");
+    let result = match append {
+        true => append_file(&filename, &text),
+        false => write_file(&filename, &text),
+    };
+    result.map_err(|error| format!("Can't open file {} for writing: {}", filename, error))?;
+
+    buf.show_message(format!(
+        "\"{}\" {}L, {}B {}",
+        filename,
+        lines.len(),
+        text.len(),
+        if append { "appended" } else { "written" }
+    ));
+    if buf.filename.is_empty() {
+        buf.filename = filename.clone();
+    }
+    if filename == buf.filename && whole && !append {
+        buf.written = buf.content.clone();
+        buf.renamed = false;
+    }
+
+    Ok(())
+}
+
+/// Runs `:wq`, or `:x` which only writes when there are changes
+pub fn write_quit(buf: &mut Buffer, command: &ExCommand, always: bool) -> Result<(), String> {
+    if always || buf.modified() || !command.args.is_empty() {
+        write(buf, command)?;
+    }
+
+    buf.exit = true;
+    Ok(())
+}
+
+/// Runs `:q`, which needs `!` to throw away unsaved changes. A command-line window only holds
+/// history and closes regardless
+pub fn quit(buf: &mut Buffer, command: &ExCommand) -> Result<(), String> {
+    if buf.modified() && !command.bang && buf.command_window.is_none() {
+        return Err(String::from(NOT_WRITTEN));
+    }
+
+    buf.exit = true;
+    Ok(())
+}
+
+/// Runs `:saveas file`, writing the buffer to the file and naming it after it
+pub fn save_as(buf: &mut Buffer, command: &ExCommand) -> Result<(), String> {
+    if command.args.is_empty() {
+        return Err(String::from("Argument required"));
+    }
+
+    let previous = std::mem::take(&mut buf.filename);
+    if let Err(error) = write(buf, command) {
+        buf.filename = previous;
+        return Err(error);
+    }
+
+    Ok(())
+}
+
+/// Runs `:file`, showing the file name and position, or renaming the buffer with a name
+pub fn file(buf: &mut Buffer, command: &ExCommand) -> Result<(), String> {
+    if !command.args.is_empty() {
+        buf.filename = command.args.clone();
+        buf.renamed = true;
+    }
+
+    let name = match buf.filename.as_str() {
+        "" => "[No Name]",
+        filename => filename,
+    };
+    let modified = if buf.modified() { " [Modified]" } else { "" };
+    let renamed = if buf.renamed { " [Not edited]" } else { "" };
+    let lines = buf.content.len();
+    buf.show_message(format!(
+        "\"{}\"{}{} {} line{} --{}%--",
+        name,
+        renamed,
+        modified,
+        lines,
+        if lines == 1 { "" } else { "s" },
+        (buf.cursor.row + 1) * 100 / lines
+    ));
+
+    Ok(())
+}
+
+/// Runs `:r file`, putting the lines of the file below the cursor line or the end of the range,
+/// or above the first line with `:0r`
+pub fn read(buf: &mut Buffer, command: &ExCommand) -> Result<(), String> {
+    let filename = match command.args.as_str() {
+        "" if buf.filename.is_empty() => return Err(String::from("No file name")),
+        "" => buf.filename.clone(),
+        filename => filename.to_string(),
+    };
+    let text =
+        read_file(&filename).map_err(|error| format!("Can't open file {}: {}", filename, error))?;
+    if text.is_empty() {
+        return Ok(());
+    }
+
+    let row = match command.zero {
+        true => 0,
+        false => command.lines(buf).end + 1,
+    };
+    buf.insert_lines(row, text.lines().map(String::from));
+    buf.cursor = Position {
+        row,
+        col: first_not_whitespace(buf.row(row)),
+    };
+
+    Ok(())
+}
+
+/// Carries out a `:wa`, `:qa` or `:wqa` given in the active buffer
+pub fn for_all_buffers(buffers: &mut [Buffer], active: usize, request: AllBuffers) {
+    let result = match request {
+        AllBuffers::Write => write_all(buffers),
+        AllBuffers::WriteQuit => write_all(buffers).map(|_| buffers[active].exit = true),
+        AllBuffers::Quit { force } => match buffers.iter().find(|buf| buf.modified()) {
+            Some(buf) if !force => Err(format!(
+                "No write since last change for buffer \"{}\"",
+                if buf.filename.is_empty() {
+                    "[No Name]"
+                } else {
+                    &buf.filename
+                }
+            )),
+            _ => {
+                buffers[active].exit = true;
+                Ok(())
+            }
+        },
+    };
+
+    if let Err(message) = result {
+        buffers[active].show_error(message);
+    }
+}
+
+/// Writes every buffer with changes, stopping at one without a file name
+fn write_all(buffers: &mut [Buffer]) -> Result<(), String> {
+    for buf in buffers.iter_mut().filter(|buf| buf.modified()) {
+        if buf.filename.is_empty() {
+            return Err(String::from("No file name for buffer"));
+        }
+        buf.write()
+            .map_err(|error| format!("Can't open file {} for writing: {}", buf.filename, error))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use super::*;
+    use crate::filesystem::TempDir;
+
+    fn run(buf: &mut Buffer, command: &str) {
+        buf.input_keys(command);
+        buf.handle_keys(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+    }
+
+    #[test]
+    fn edits_files() {
+        let mut buf = Buffer::test(String::new());
+        buf.content[0] = String::from("changed");
+
+        run(&mut buf, ":e test.txt");
+        assert_eq!(buf.message().content, NOT_WRITTEN);
+        run(&mut buf, ":e! test.txt");
+        assert_eq!(buf.filename, "test.txt");
+        assert_eq!(buf.content.len(), 7);
+        assert!(!buf.modified());
+
+        run(&mut buf, ":e missing_file");
+        assert_eq!(buf.message().content, "\"missing_file\" [New]");
+        assert_eq!(buf.content, vec![String::new()]);
+
+        run(&mut buf, ":enew");
+        assert_eq!(buf.filename, "");
+        run(&mut buf, ":e");
+        assert_eq!(buf.message().content, "No file name");
+        run(&mut buf, ":w");
+        assert_eq!(buf.message().content, "No file name");
+    }
+
+    #[test]
+    fn writes_files() {
+        let dir = TempDir::new("writes_files");
+        let (existing, range, saved) = (dir.file("existing"), dir.file("range"), dir.file("saved"));
+        write_file(&existing, "first\nsecond").unwrap();
+        let mut buf = Buffer::test(String::from("test.txt"));
+
+        run(&mut buf, &format!(":w {}", existing));
+        assert_eq!(buf.message().content, "File exists (add ! to override)");
+        run(&mut buf, ":2,3w");
+        assert_eq!(buf.message().content, "Use ! to write partial buffer");
+
+        run(&mut buf, &format!(":2,3w {}", range));
+        assert_eq!(read_file(&range).unwrap(), buf.content[1..3].join("\n"));
+        run(&mut buf, &format!(":$w >> {}", range));
+        assert_eq!(
+            buf.message().content,
+            format!("\"{}\" 1L, 5B appended", range)
+        );
+        assert_eq!(
+            read_file(&range).unwrap(),
+            format!("{}\nnulla", buf.content[1..3].join("\n"))
+        );
+
+        run(&mut buf, &format!(":r {}", existing));
+        assert_eq!(buf.cursor.row, 1);
+        assert_eq!(buf.content[1..3], ["first", "second"]);
+        run(&mut buf, &format!(":0r {}", existing));
+        assert_eq!(buf.cursor.row, 0);
+        assert_eq!(
+            buf.content[..3],
+            ["first", "second", "Lorem ipsum odor amet, "]
+        );
+        run(&mut buf, &format!(":r {}", dir.file("missing")));
+        assert!(buf
+            .message()
+            .content
+            .starts_with(&format!("Can't open file {}: ", dir.file("missing"))));
+        run(&mut buf, &format!(":saveas {}", saved));
+        assert_eq!(buf.filename, saved);
+        assert!(!buf.modified());
+        run(&mut buf, &format!(":file {}", existing));
+        assert!(buf
+            .message()
+            .content
+            .starts_with(&format!("\"{}\" [Not edited] 11 lines", existing)));
+        assert!(!buf.modified());
+        run(&mut buf, ":w");
+        assert_eq!(buf.message().content, "File exists (add ! to override)");
+        run(&mut buf, ":w!");
+        assert_eq!(read_file(&existing).unwrap(), buf.content.join("\n"));
+        assert!(!buf.renamed);
+
+        run(&mut buf, ":file renamed");
+        run(&mut buf, ":q");
+        assert!(buf.exit);
+    }
+
+    #[test]
+    fn quits() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.content[0] = String::from("changed");
+
+        run(&mut buf, ":q");
+        assert_eq!(buf.message().content, NOT_WRITTEN);
+        assert!(!buf.exit);
+
+        let mut buffers = vec![buf, Buffer::test(String::new())];
+        for_all_buffers(&mut buffers, 1, AllBuffers::Quit { force: false });
+        assert_eq!(
+            buffers[1].message().content,
+            "No write since last change for buffer \"test.txt\""
+        );
+        for_all_buffers(&mut buffers, 1, AllBuffers::Quit { force: true });
+        assert!(buffers[1].exit);
+
+        run(&mut buffers[0], ":q!");
+        assert!(buffers[0].exit);
+    }
+}
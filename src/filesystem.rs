@@ -1,4 +1,7 @@
-use std::{fs, io};
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+};
 
 pub fn read_file(filename: &str) -> io::Result<String> {
     if filename.is_empty() {
@@ -11,3 +14,41 @@ pub fn read_file(filename: &str) -> io::Result<String> {
 pub fn write_file(filename: &str, contents: &str) -> io::Result<()> {
     fs::write(filename, contents)
 }
+
+pub fn append_file(filename: &str, contents: &str) -> io::Result<()> {
+    let mut file = OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(filename)?;
+    if file.metadata()?.len() > 0 {
+        file.write_all(b"\n")?;
+    }
+    file.write_all(contents.as_bytes())
+}
+
+/// A directory for a test to put files in, removed with everything in it when dropped, even if
+/// the test fails
+#[cfg(test)]
+pub struct TempDir(std::path::PathBuf);
+
+#[cfg(test)]
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("vimrs-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    /// The path of a file in the directory
+    pub fn file(&self, name: &str) -> String {
+        self.0.join(name).to_string_lossy().into_owned()
+    }
+}
+
+#[cfg(test)]
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,174 @@
+use crate::{
+    buffer::{Buffer, Position},
+    ex::{is_delimiter, split_pattern, ExCommand},
+    mode::command::run_line,
+    search::{compile, Offset, Search},
+};
+
+/// Runs `:g/pattern/command` on each line in the range matching the pattern, or with `:v` and
+/// `:g!` on each line that doesn't. The lines are marked first and the marks move with the
+/// lines as lines are added and removed, so a command changing lines doesn't make it skip or
+/// repeat any
+pub fn global(buf: &mut Buffer, command: &ExCommand) -> Result<(), String> {
+    if buf.in_global {
+        return Err(String::from("Cannot do :global recursive"));
+    }
+
+    let inverse = command.name == "vglobal" || command.bang;
+    let args = command.args.as_str();
+    let delim = args
+        .chars()
+        .next()
+        .filter(|char| is_delimiter(*char))
+        .ok_or("Regular expression missing from :global")?;
+    let (pattern, line) = split_pattern(&args[delim.len_utf8()..], delim);
+
+    let pattern = match pattern.as_str() {
+        "" => buf
+            .last_search
+            .as_ref()
+            .map(|search| search.pattern.clone())
+            .ok_or("No previous regular expression")?,
+        _ => pattern,
+    };
+    let regex = compile(&pattern, &buf.options)?;
+    buf.register.set('/', pattern.clone());
+    buf.last_search = Some(Search {
+        pattern: pattern.clone(),
+        backward: false,
+        offset: Offset::None,
+    });
+
+    let (start, end) = match command.range {
+        Some(range) => (range.start, range.end),
+        None => (0, buf.content.len() - 1),
+    };
+    buf.global_marks = (start..=end)
+        .filter(|row| regex.is_match(buf.row(*row)) != inverse)
+        .collect();
+    if buf.global_marks.is_empty() {
+        return Err(match inverse {
+            true => format!("Pattern found in every line: {}", pattern),
+            false => format!("Pattern not found: {}", pattern),
+        });
+    }
+
+    let line = match line.trim() {
+        "" => "p",
+        _ => line,
+    };
+    buf.in_global = true;
+    let mut output = Vec::new();
+    let mut result = Ok(());
+
+    while let Some(row) = buf.global_marks.pop_front() {
+        buf.cursor = Position { row, col: 0 };
+        buf.show_message("");
+
+        result = run_line(buf, line);
+        let message = buf.message().content.to_string();
+        if !message.is_empty() {
+            output.push(message);
+        }
+        if result.is_err() {
+            break;
+        }
+    }
+
+    buf.global_marks.clear();
+    buf.in_global = false;
+    if !output.is_empty() {
+        buf.show_message(output.join("\n"));
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use super::*;
+    use crate::mode::Mode;
+
+    fn run(buf: &mut Buffer, command: &str) {
+        buf.input_keys(command);
+        buf.handle_keys(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+    }
+
+    #[test]
+    fn runs_on_matching_lines() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+
+        run(&mut buf, ":g/Lorem/");
+        assert_eq!(
+            buf.message().content,
+            "Lorem ipsum odor amet, \nLorem.ipsum"
+        );
+        assert_eq!(buf.cursor.row, 4);
+
+        run(&mut buf, ":g/a/d");
+        assert_eq!(buf.content, vec!["Lorem.ipsum", ""]);
+
+        run(&mut buf, ":g/zzz/d");
+        assert_eq!(buf.message().content, "Pattern not found: zzz");
+        run(&mut buf, ":g/./g/x/d");
+        assert_eq!(buf.message().content, "Cannot do :global recursive");
+    }
+
+    #[test]
+    fn runs_on_other_lines() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+
+        run(&mut buf, ":v/Lorem/s/^/- /|s/$/ -/");
+        assert_eq!(buf.content[0], "Lorem ipsum odor amet, ");
+        assert_eq!(buf.content[1], "- consectetuer adipiscing elit.  -");
+        assert_eq!(buf.content[5], "-  -");
+
+        run(&mut buf, ":2,4g!/Ridiculus/d");
+        assert_eq!(buf.content.len(), 5);
+        assert_eq!(
+            buf.content[1],
+            "- Ridiculus nulla consectetur proin purus ad justo nullam.  -"
+        );
+        run(&mut buf, ":v/./d");
+        assert_eq!(buf.message().content, "Pattern found in every line: .");
+    }
+
+    #[test]
+    fn runs_normal_commands() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+
+        run(&mut buf, ":g/ipsum/normal Ax ");
+        assert_eq!(buf.content[0], "Lorem ipsum odor amet, x ");
+        assert_eq!(buf.content[4], "Lorem.ipsumx ");
+        assert_eq!(buf.mode, Mode::Normal);
+
+        run(&mut buf, ":g/^L/normal jdd");
+        assert_eq!(buf.content.len(), 5);
+        assert_eq!(
+            buf.content[1],
+            "Ridiculus nulla consectetur proin purus ad justo nullam. "
+        );
+        assert_eq!(buf.content[3], "Lorem.ipsumx ");
+        assert_eq!(buf.content[4], "nulla");
+    }
+
+    #[test]
+    fn keeps_marks_on_their_lines() {
+        let mut buf = Buffer::test(String::new());
+        buf.content = vec!["a1", "b", "a2", "c"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+
+        run(&mut buf, ":g/a/normal ddGo");
+        assert_eq!(buf.content, vec!["b", "c", "", ""]);
+
+        buf.content = vec!["a1", "b", "a2", "c"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        run(&mut buf, ":g/a/s/^/x/|$s/$/\\ry/");
+        assert_eq!(buf.content, vec!["xa1", "b", "xa2", "c", "y", "y"]);
+    }
+}
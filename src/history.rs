@@ -0,0 +1,261 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    buffer::{Buffer, Position},
+    mode::{command::execute_command, Mode},
+    search::{execute_search, start_search},
+};
+
+/// Which prompt a history belongs to. Both search directions share one
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HistoryKind {
+    Command,
+    Search,
+}
+
+impl HistoryKind {
+    /// The history of a prompt, `:`, `/` or `?`
+    pub fn of(prompt: char) -> HistoryKind {
+        match prompt {
+            ':' => HistoryKind::Command,
+            _ => HistoryKind::Search,
+        }
+    }
+}
+
+/// The lines entered at the `:` and search prompts, oldest first, shared between all buffers
+pub struct History(Rc<RefCell<[Vec<String>; 2]>>);
+
+impl History {
+    pub fn new() -> Self {
+        History(Rc::new(RefCell::new([Vec::new(), Vec::new()])))
+    }
+
+    pub fn clone(&self) -> History {
+        History(Rc::clone(&self.0))
+    }
+
+    pub fn entries(&self, kind: HistoryKind) -> Vec<String> {
+        self.0.borrow()[kind as usize].clone()
+    }
+
+    /// Adds a line as the newest entry, dropping an older copy of it and the oldest entries
+    /// beyond `limit`
+    pub fn add(&self, kind: HistoryKind, line: &str, limit: usize) {
+        if line.is_empty() {
+            return;
+        }
+
+        let entries = &mut self.0.borrow_mut()[kind as usize];
+        entries.retain(|entry| entry != line);
+        entries.push(line.to_string());
+        let excess = entries.len().saturating_sub(limit);
+        entries.drain(..excess);
+    }
+}
+
+/// Browsing the history at a prompt, with the line as typed before it started
+#[derive(Clone, Debug, PartialEq)]
+pub struct Recall {
+    typed: String,
+    index: usize,
+}
+
+/// Replaces the command line with an older or newer history entry. With `prefix` only entries
+/// starting with the line as typed are recalled, and going past the newest brings it back
+pub fn recall(buf: &mut Buffer, older: bool, prefix: bool) {
+    let kind = match buf.mode {
+        Mode::Search => HistoryKind::Search,
+        _ => HistoryKind::Command,
+    };
+    let entries = buf.history.entries(kind);
+    let Recall { typed, index } = buf.recall.take().unwrap_or(Recall {
+        typed: buf.keys.clone(),
+        index: entries.len(),
+    });
+
+    let matches = |idx: &usize| !prefix || entries[*idx].starts_with(&typed);
+    let found = match older {
+        true => (0..index).rev().find(matches),
+        false => (index + 1..entries.len()).find(matches),
+    };
+    let index = match (found, older) {
+        (Some(idx), _) => idx,
+        (None, true) => index,
+        (None, false) => entries.len(),
+    };
+
+    buf.keys = entries.get(index).unwrap_or(&typed).clone();
+    buf.command_cursor = buf.keys.len();
+    buf.recall = Some(Recall { typed, index });
+}
+
+/// The buffer `q:`, `q/` and `q?` open to edit a history in, which runs the line entered with
+/// Enter at the prompt of the buffer it was opened from
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommandWindow {
+    pub prompt: char,
+    pub origin: usize,
+    pub run: Option<String>,
+}
+
+/// Picks the line under the cursor to run once the command-line window closes
+pub fn run_command_window(buf: &mut Buffer) {
+    if let Some(window) = buf.command_window.as_mut() {
+        window.run = Some(buf.content[buf.cursor.row].clone());
+        buf.exit = true;
+    }
+}
+
+/// Opens the command-line window the active buffer asked for, returning its index
+pub fn open_command_window(buffers: &mut Vec<Buffer>, active: usize) -> usize {
+    let origin = &mut buffers[active];
+    let Some(prompt) = origin.open_command_window.take() else {
+        return active;
+    };
+
+    let mut window = Buffer::new(
+        String::new(),
+        &origin.register,
+        &origin.abbreviations,
+        &origin.options,
+        &origin.history,
+    );
+    window.content = origin.history.entries(HistoryKind::of(prompt));
+    window.content.push(String::new());
+    window.written = window.content.clone();
+    window.cursor = Position {
+        row: window.content.len() - 1,
+        col: 0,
+    };
+    window.command_window = Some(CommandWindow {
+        prompt,
+        origin: active,
+        run: None,
+    });
+
+    buffers.push(window);
+    buffers.len() - 1
+}
+
+/// Closes the active command-line window once it exits and runs its line in the buffer it was
+/// opened from, returning the index of that buffer
+pub fn close_command_window(buffers: &mut Vec<Buffer>, active: usize) -> usize {
+    let buf = &buffers[active];
+    if !buf.exit || buf.command_window.is_none() {
+        return active;
+    }
+
+    let window = buffers.remove(active).command_window.unwrap();
+    let origin = &mut buffers[window.origin];
+    if let Some(line) = window.run {
+        match window.prompt {
+            ':' => origin.change_mode(Mode::Command),
+            prompt => start_search(origin, prompt == '?', ""),
+        }
+        origin.keys = line;
+        match window.prompt {
+            ':' => execute_command(origin),
+            _ => execute_search(origin),
+        }
+    }
+
+    window.origin
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn run(buf: &mut Buffer, command: &str) {
+        buf.input_keys(command);
+        buf.handle_keys(key(KeyCode::Enter));
+    }
+
+    #[test]
+    fn keeps_separate_histories() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.options.set("history=3").unwrap();
+
+        for command in [":p", ":2", ":1p", ":2", ":3"] {
+            run(&mut buf, command);
+        }
+        run(&mut buf, "/ipsum");
+        assert_eq!(
+            buf.history.entries(HistoryKind::Command),
+            vec!["1p", "2", "3"]
+        );
+        assert_eq!(buf.history.entries(HistoryKind::Search), vec!["ipsum"]);
+
+        buf.input_keys(":");
+        buf.handle_keys(key(KeyCode::Up));
+        assert_eq!(buf.keys, "3");
+        buf.handle_keys(key(KeyCode::Up));
+        buf.handle_keys(key(KeyCode::Up));
+        buf.handle_keys(key(KeyCode::Up));
+        assert_eq!(buf.keys, "1p");
+        buf.handle_keys(key(KeyCode::Down));
+        assert_eq!(buf.keys, "2");
+    }
+
+    #[test]
+    fn recalls_by_prefix() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        for command in [":s/a/b/", ":p", ":s/c/d/", ":2"] {
+            run(&mut buf, command);
+        }
+
+        buf.input_keys(":s");
+        buf.handle_keys(key(KeyCode::Up));
+        assert_eq!(buf.keys, "s/c/d/");
+        buf.handle_keys(key(KeyCode::Up));
+        assert_eq!(buf.keys, "s/a/b/");
+        buf.handle_keys(key(KeyCode::Up));
+        assert_eq!(buf.keys, "s/a/b/");
+        buf.handle_keys(key(KeyCode::Down));
+        buf.handle_keys(key(KeyCode::Down));
+        assert_eq!(buf.keys, "s");
+
+        buf.handle_keys(KeyEvent::new(KeyCode::Char('p'), KeyModifiers::CONTROL));
+        assert_eq!(buf.keys, "2");
+    }
+
+    #[test]
+    fn runs_lines_from_the_command_window() {
+        let mut buffers = vec![Buffer::test(String::from("test.txt"))];
+        run(&mut buffers[0], ":3");
+        run(&mut buffers[0], ":5");
+
+        buffers[0].input_keys("q:");
+        let active = open_command_window(&mut buffers, 0);
+        assert_eq!(active, 1);
+        assert_eq!(buffers[1].content, vec!["3", "5", ""]);
+
+        buffers[1].input_keys("kk");
+        buffers[1].handle_keys(key(KeyCode::Enter));
+        assert_eq!(close_command_window(&mut buffers, active), 0);
+        assert_eq!(buffers.len(), 1);
+        assert_eq!(buffers[0].cursor.row, 2);
+        assert_eq!(
+            buffers[0].history.entries(HistoryKind::Command),
+            vec!["5", "3"]
+        );
+
+        buffers[0].input_keys("q/");
+        let active = open_command_window(&mut buffers, 0);
+        buffers[1].input_keys("inulla");
+        buffers[1].handle_keys(key(KeyCode::Enter));
+        close_command_window(&mut buffers, active);
+        assert_eq!(buffers[0].cursor, Position { row: 2, col: 10 });
+        assert_eq!(
+            buffers[0].history.entries(HistoryKind::Search),
+            vec!["nulla"]
+        );
+    }
+}
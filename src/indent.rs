@@ -0,0 +1,153 @@
+use crate::{
+    buffer::{Buffer, Position},
+    navigation::matching_bracket,
+    options::Options,
+};
+
+/// The leading whitespace of a line
+pub fn indent_of(line: &str) -> &str {
+    &line[..line.len() - line.trim_start().len()]
+}
+
+/// The whitespace inserted for one level of indentation
+pub fn indent_unit(options: &Options) -> String {
+    if options.expandtab() {
+        " ".repeat(options.shiftwidth())
+    } else {
+        String::from("\t")
+    }
+}
+
+/// Removes one level of indentation from the end of an indent
+pub fn dedent(indent: &str, options: &Options) -> String {
+    if let Some(indent) = indent.strip_suffix('\t') {
+        return indent.to_string();
+    }
+
+    let spaces = indent.len() - indent.trim_end_matches(' ').len();
+    indent[..indent.len() - spaces.min(options.shiftwidth())].to_string()
+}
+
+fn smart(options: &Options) -> bool {
+    options.smartindent() || options.cindent()
+}
+
+/// The indent for a line opened after `prev`, which starts with `next`
+pub fn indent_after(options: &Options, prev: &str, next: &str) -> String {
+    if !options.autoindent() && !smart(options) {
+        return String::new();
+    }
+
+    let mut indent = indent_of(prev).to_string();
+    if smart(options) {
+        if prev.trim_end().ends_with(['{', ':']) {
+            indent.push_str(&indent_unit(options));
+        }
+        if next.trim_start().starts_with('}') {
+            indent = dedent(&indent, options);
+        }
+    }
+
+    indent
+}
+
+/// The indent for a line opened above `next`
+pub fn indent_before(options: &Options, next: &str) -> String {
+    if !options.autoindent() && !smart(options) {
+        return String::new();
+    }
+
+    let mut indent = indent_of(next).to_string();
+    if smart(options) && next.trim_start().starts_with('}') {
+        indent.push_str(&indent_unit(options));
+    }
+
+    indent
+}
+
+/// Lines up a `}` just typed as the first character of a line with the line of the `{` it
+/// closes, or removes a level of indentation when there is none
+pub fn dedent_closing(buf: &mut Buffer) {
+    if !smart(&buf.options) || buf.cursor.col == 0 {
+        return;
+    }
+
+    let brace = Position {
+        row: buf.cursor.row,
+        col: buf.cursor.col - 1,
+    };
+    let line = buf.row(brace.row);
+    if !line[..brace.col].trim().is_empty() {
+        return;
+    }
+
+    let indent = indent_of(line).len();
+    let wanted = match matching_bracket(buf, brace) {
+        Some(open) => indent_of(buf.row(open.row)).to_string(),
+        None => dedent(indent_of(line), &buf.options),
+    };
+
+    buf.content[brace.row].replace_range(..indent, &wanted);
+    buf.cursor.col = buf.cursor.col + wanted.len() - indent;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(args: &str) -> Options {
+        let mut options = Options::default();
+        options.set(args).unwrap();
+        options
+    }
+
+    #[test]
+    fn copies_indent() {
+        let options = options("ai");
+
+        assert_eq!(indent_after(&options, "    foo {", "bar"), "    ");
+        assert_eq!(indent_after(&Options::default(), "    foo", ""), "");
+    }
+
+    #[test]
+    fn indents_blocks() {
+        let options = options("si sw=4 et");
+
+        assert_eq!(indent_after(&options, "    fn foo() {", ""), "        ");
+        assert_eq!(indent_after(&options, "    fn foo() {", "}"), "    ");
+        assert_eq!(indent_after(&options, "match:", ""), "    ");
+        assert_eq!(indent_before(&options, "    }"), "        ");
+    }
+
+    #[test]
+    fn dedents() {
+        let options = options("sw=4");
+
+        assert_eq!(dedent("\t\t", &options), "\t");
+        assert_eq!(dedent("      ", &options), "  ");
+        assert_eq!(dedent("  ", &options), "");
+    }
+
+    #[test]
+    fn lines_up_closing_braces() {
+        let mut buf = Buffer::test(String::new());
+        buf.options.set("si sw=4").unwrap();
+        buf.content = vec![
+            String::from("fn main() {"),
+            String::from("            x"),
+            String::from("            }"),
+        ];
+        buf.cursor = Position { row: 2, col: 13 };
+
+        dedent_closing(&mut buf);
+        assert_eq!(buf.content[2], "}");
+        assert_eq!(buf.cursor.col, 1);
+
+        buf.content = vec![String::from("    {"), String::from("  }")];
+        buf.cursor = Position { row: 1, col: 3 };
+
+        dedent_closing(&mut buf);
+        assert_eq!(buf.content[1], "    }");
+        assert_eq!(buf.cursor.col, 5);
+    }
+}
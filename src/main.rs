@@ -1,11 +1,25 @@
+mod abbreviation;
 mod actions;
 mod app;
 mod buffer;
+mod completion;
+mod digraph;
+mod ex;
+mod file_commands;
 mod filesystem;
+mod global;
+mod history;
+mod indent;
 mod mode;
 mod motion;
 mod navigation;
+mod options;
+mod repeat;
+mod search;
+mod substitute;
+mod syntax;
 mod utils;
+mod view;
 
 use app::App;
 use std::{env, io};
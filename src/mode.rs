@@ -4,18 +4,24 @@ use command::handle_command_keys;
 use insert::handle_insert_keys;
 use normal::handle_normal_keys;
 use ratatui::crossterm::event::KeyEvent;
+use visual::handle_visual_keys;
 
 use crate::buffer::Buffer;
 
 pub mod command;
 pub mod insert;
+pub mod literal;
 pub mod normal;
+pub mod visual;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Mode {
     Normal,
     Insert,
     Command,
+    Search,
+    Visual,
+    VisualLine,
 }
 
 impl Display for Mode {
@@ -27,6 +33,9 @@ impl Display for Mode {
                 Mode::Normal => "Normal",
                 Mode::Insert => "Insert",
                 Mode::Command => "Command",
+                Mode::Search => "Search",
+                Mode::Visual => "Visual",
+                Mode::VisualLine => "Visual Line",
             }
         )
     }
@@ -37,7 +46,8 @@ impl Mode {
         match self {
             Mode::Insert => handle_insert_keys(buf, event),
             Mode::Normal => handle_normal_keys(buf, event),
-            Mode::Command => handle_command_keys(buf, event),
+            Mode::Command | Mode::Search => handle_command_keys(buf, event),
+            Mode::Visual | Mode::VisualLine => handle_visual_keys(buf, event),
         }
     }
 }
@@ -1,45 +1,375 @@
-use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
-use crate::buffer::Buffer;
+use crate::{
+    abbreviation::{parse_abbreviation, AbbreviationMode},
+    actions::{delete::delete_lines, yank::yank_lines},
+    buffer::{Buffer, Position},
+    digraph::list_digraphs,
+    ex::{parse_command, ExCommand},
+    file_commands::{edit, edit_new, file, quit, read, save_as, write, write_quit, AllBuffers},
+    global::global,
+    history::{recall, HistoryKind},
+    navigation::{first_not_whitespace, goto_line, word_under_cursor},
+    options::SetScope,
+    search::{cancel_search, execute_search, preview_search},
+    substitute::{repeat_command, substitute},
+    utils::split_first_char,
+};
 
-use super::Mode;
+use super::{
+    literal::{digraph_key, literal, Special},
+    Mode,
+};
+
+const CTRL_K: char = '\x0b';
+const CTRL_R: char = '\x12';
+const CTRL_V: char = '\x16';
 
 pub fn handle_command_keys(buf: &mut Buffer, event: KeyEvent) {
+    let ctrl = event.modifiers.contains(KeyModifiers::CONTROL);
+
+    if !buf.pending.is_empty() {
+        return handle_pending(buf, event);
+    }
+
+    // Any key but the ones browsing the history starts browsing anew from the line it leaves
+    let recalls = match event.code {
+        KeyCode::Up | KeyCode::Down => true,
+        KeyCode::Char('p' | 'n') => ctrl,
+        _ => false,
+    };
+    if !recalls {
+        buf.recall = None;
+    }
+
     match event.code {
-        KeyCode::Char(key) => buf.keys.push(key),
+        KeyCode::Char('v') if ctrl => buf.pending.push(CTRL_V),
+        KeyCode::Char('k') if ctrl => buf.pending.push(CTRL_K),
+        KeyCode::Char('r') if ctrl => buf.pending.push(CTRL_R),
+        KeyCode::Char(']') if ctrl => expand_abbreviation(buf),
+        KeyCode::Char('b') if ctrl => buf.command_cursor = 0,
+        KeyCode::Char('e') if ctrl => buf.command_cursor = buf.keys.len(),
+        KeyCode::Char('h') if ctrl => pop_char(buf),
+        KeyCode::Char('w') if ctrl => delete_word(buf),
+        KeyCode::Char('u') if ctrl => {
+            buf.keys.replace_range(..buf.command_cursor, "");
+            buf.command_cursor = 0;
+        }
+        KeyCode::Char('p') if ctrl => recall(buf, true, false),
+        KeyCode::Char('n') if ctrl => recall(buf, false, false),
+        KeyCode::Char(_) if ctrl => {}
+        KeyCode::Char(key) => {
+            if !buf.options.iskeyword().contains(key) {
+                expand_abbreviation(buf);
+            }
+            insert_text(buf, &key.to_string());
+        }
         KeyCode::Backspace => pop_char(buf),
-        KeyCode::Enter => execute_command(buf),
+        KeyCode::Delete => {
+            if buf.command_cursor == buf.keys.len() {
+                buf.command_cursor = prev_char(buf);
+            }
+            if buf.command_cursor < buf.keys.len() {
+                buf.keys.remove(buf.command_cursor);
+            }
+        }
+        KeyCode::Left => buf.command_cursor = prev_char(buf),
+        KeyCode::Right => buf.command_cursor = next_char(buf),
+        KeyCode::Home => buf.command_cursor = 0,
+        KeyCode::End => buf.command_cursor = buf.keys.len(),
+        KeyCode::Up => recall(buf, true, true),
+        KeyCode::Down => recall(buf, false, true),
+        KeyCode::Enter => {
+            expand_abbreviation(buf);
+            match buf.mode {
+                Mode::Search => return execute_search(buf),
+                _ => execute_command(buf),
+            }
+        }
+        KeyCode::Esc if buf.mode == Mode::Search => return cancel_search(buf),
         KeyCode::Esc => buf.change_mode(Mode::Normal),
         _ => {}
     }
+
+    if buf.mode == Mode::Search {
+        preview_search(buf);
+    }
+}
+
+/// Continues a `Ctrl-v`, `Ctrl-k` or `Ctrl-r` sequence with the key typed after it
+fn handle_pending(buf: &mut Buffer, event: KeyEvent) {
+    let (prefix, typed) = split_first_char(&std::mem::take(&mut buf.pending));
+
+    let special = match prefix {
+        CTRL_V => literal(&typed, event),
+        CTRL_K => digraph_key(&typed, event),
+        CTRL_R => return insert_register(buf, event),
+        _ => return,
+    };
+
+    match special {
+        Special::Pending(char) => buf.pending = format!("{}{}{}", prefix, typed, char),
+        Special::Insert(char) => insert_text(buf, &char.to_string()),
+        Special::InsertThen(char, event) => {
+            if let Some(char) = char {
+                insert_text(buf, &char.to_string());
+            }
+            return handle_command_keys(buf, event);
+        }
+    }
+
+    if buf.mode == Mode::Search {
+        preview_search(buf);
+    }
+}
+
+/// Inserts the register named by the key typed after `Ctrl-r`, or the word under the cursor for
+/// `Ctrl-w`. Lines of a register are joined with `\r`, as Vim shows them
+fn insert_register(buf: &mut Buffer, event: KeyEvent) {
+    let text = match event.code {
+        KeyCode::Char('w') if event.modifiers.contains(KeyModifiers::CONTROL) => {
+            match word_under_cursor(buf) {
+                Some((range, _)) => buf.row(buf.cursor.row)[range].to_string(),
+                None => return,
+            }
+        }
+        KeyCode::Char(_) if event.modifiers.contains(KeyModifiers::CONTROL) => return,
+        KeyCode::Char(name) => buf
+            .register
+            .get(name)
+            .trim_end_matches('\n')
+            .replace('\n', "\r"),
+        _ => return,
+    };
+
+    insert_text(buf, &text);
+    if buf.mode == Mode::Search {
+        preview_search(buf);
+    }
+}
+
+fn insert_text(buf: &mut Buffer, text: &str) {
+    buf.keys.insert_str(buf.command_cursor, text);
+    buf.command_cursor += text.len();
+}
+
+fn prev_char(buf: &Buffer) -> usize {
+    buf.keys[..buf.command_cursor]
+        .char_indices()
+        .next_back()
+        .map_or(0, |(idx, _)| idx)
+}
+
+fn next_char(buf: &Buffer) -> usize {
+    buf.keys[buf.command_cursor..]
+        .chars()
+        .next()
+        .map_or(buf.command_cursor, |char| {
+            buf.command_cursor + char.len_utf8()
+        })
 }
 
-fn execute_command(buf: &mut Buffer) {
-    match buf.keys.as_str() {
-        "q" => buf.exit = true,
-        "w" => buf.write(),
-        "wq" => {
-            buf.exit = true;
-            buf.write();
+/// Deletes the word before the command-line cursor, along with the blanks after it
+fn delete_word(buf: &mut Buffer) {
+    let before = &buf.keys[..buf.command_cursor];
+    let trimmed = before.trim_end();
+    let keyword = |char: char| buf.options.iskeyword().contains(char);
+    let start = match trimmed.chars().next_back() {
+        Some(last) => {
+            let same = |char: char| !char.is_whitespace() && keyword(char) == keyword(last);
+            trimmed.trim_end_matches(same).len()
+        }
+        None => 0,
+    };
+
+    buf.keys.replace_range(start..buf.command_cursor, "");
+    buf.command_cursor = start;
+}
+
+/// Runs the command line, after adding it to the history and the `:` register
+pub fn execute_command(buf: &mut Buffer) {
+    let line = std::mem::take(&mut buf.keys);
+    buf.change_mode(Mode::Normal);
+    buf.history
+        .add(HistoryKind::Command, &line, buf.options.history());
+    if !line.is_empty() {
+        buf.register.set(':', line.clone());
+    }
+
+    if let Err(message) = run_line(buf, &line) {
+        buf.show_error(message);
+    }
+}
+
+/// Runs the `|` separated commands of a command line, stopping at the first error
+pub fn run_line(buf: &mut Buffer, line: &str) -> Result<(), String> {
+    let mut rest = Some(line);
+    while let Some(line) = rest {
+        let (command, next) = parse_command(buf, line)?;
+        rest = next;
+        if let Some(command) = command {
+            run_command(buf, command)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn run_command(buf: &mut Buffer, command: ExCommand) -> Result<(), String> {
+    let args = command.args.as_str();
+    let lines = command.lines(buf);
+
+    match command.name {
+        "" => buf.cursor = goto_line(buf, lines.end + 1),
+        "set" | "setlocal" | "setglobal" => {
+            let shown = match command.name {
+                "setlocal" => buf.options.set_scoped(args, SetScope::Local)?,
+                "setglobal" => buf.options.set_scoped(args, SetScope::Global)?,
+                _ => buf.options.set(args)?,
+            };
+            if !shown.is_empty() {
+                buf.show_message(shown);
+            }
+        }
+        "abbreviate" => abbreviate(
+            buf,
+            &[AbbreviationMode::Insert, AbbreviationMode::Command],
+            args,
+        ),
+        "iabbrev" => abbreviate(buf, &[AbbreviationMode::Insert], args),
+        "cabbrev" => abbreviate(buf, &[AbbreviationMode::Command], args),
+        "unabbreviate" => unabbreviate(
+            buf,
+            &[AbbreviationMode::Insert, AbbreviationMode::Command],
+            args,
+        )?,
+        "iunabbrev" => unabbreviate(buf, &[AbbreviationMode::Insert], args)?,
+        "cunabbrev" => unabbreviate(buf, &[AbbreviationMode::Command], args)?,
+        "abclear" => buf
+            .abbreviations
+            .clear(&[AbbreviationMode::Insert, AbbreviationMode::Command]),
+        "iabclear" => buf.abbreviations.clear(&[AbbreviationMode::Insert]),
+        "cabclear" => buf.abbreviations.clear(&[AbbreviationMode::Command]),
+        "digraphs" => buf.show_message(list_digraphs(buf.view.width)),
+        "nohlsearch" => buf.highlight_search = false,
+        "delete" | "yank" => {
+            buf.cursor.row = lines.start;
+            let count = lines.end - lines.start + 1;
+            match command.name {
+                "delete" => delete_lines(buf, count),
+                _ => yank_lines(buf, count),
+            }
+            if let Some(register) = command.register {
+                buf.register.set(register, buf.register.get_default());
+            }
+        }
+        "print" => {
+            let text = buf.content[lines.start..=lines.end].join("\n");
+            buf.show_message(text);
+            buf.cursor = Position {
+                row: lines.end,
+                col: first_not_whitespace(buf.row(lines.end)),
+            };
+        }
+        "normal" => match command.range {
+            Some(lines) => {
+                for row in lines.start..=lines.end {
+                    if row < buf.content.len() {
+                        buf.cursor = Position { row, col: 0 };
+                        execute_normal(buf, args);
+                    }
+                }
+            }
+            None => execute_normal(buf, args),
+        },
+        "global" | "vglobal" => global(buf, &command)?,
+        "substitute" => substitute(buf, &command)?,
+        "&" => repeat_command(buf, &command)?,
+        "edit" => edit(buf, &command)?,
+        "enew" => edit_new(buf, &command)?,
+        "file" => file(buf, &command)?,
+        "read" => read(buf, &command)?,
+        "saveas" => save_as(buf, &command)?,
+        "write" => write(buf, &command)?,
+        "wq" => write_quit(buf, &command, true)?,
+        "xit" => write_quit(buf, &command, false)?,
+        "quit" => quit(buf, &command)?,
+        "wall" => buf.all_buffers = Some(AllBuffers::Write),
+        "wqall" => buf.all_buffers = Some(AllBuffers::WriteQuit),
+        "qall" => {
+            buf.all_buffers = Some(AllBuffers::Quit {
+                force: command.bang,
+            })
         }
         _ => {}
     }
 
-    buf.keys = String::new();
+    Ok(())
+}
+
+/// Types the keys in normal mode, as `:normal` does, abandoning a command they leave unfinished
+fn execute_normal(buf: &mut Buffer, keys: &str) {
+    for char in keys.chars() {
+        buf.handle_keys(KeyEvent::new(KeyCode::Char(char), KeyModifiers::NONE));
+    }
+
+    if buf.mode != Mode::Normal || !buf.keys.is_empty() || buf.substitution.is_some() {
+        buf.handle_keys(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+    }
 }
 
+fn abbreviate(buf: &mut Buffer, modes: &[AbbreviationMode], args: &str) {
+    match parse_abbreviation(args) {
+        (lhs, "") => {
+            let list = buf.abbreviations.list(modes, lhs);
+            if list.is_empty() {
+                buf.show_message("No abbreviation found");
+            } else {
+                buf.show_message(list.join(", "));
+            }
+        }
+        (lhs, rhs) => buf.abbreviations.add(modes, lhs, rhs),
+    }
+}
+
+fn unabbreviate(buf: &mut Buffer, modes: &[AbbreviationMode], args: &str) -> Result<(), String> {
+    match buf.abbreviations.remove(modes, args.trim()) {
+        true => Ok(()),
+        false => Err(String::from("No such abbreviation")),
+    }
+}
+
+/// Replaces an abbreviation just before the command-line cursor with its expansion
+fn expand_abbreviation(buf: &mut Buffer) {
+    let cursor = buf.command_cursor;
+    if let Some((len, rhs)) = buf.abbreviations.find(
+        AbbreviationMode::Command,
+        buf.options.iskeyword(),
+        &buf.keys[..cursor],
+    ) {
+        buf.keys.replace_range(cursor - len..cursor, &rhs);
+        buf.command_cursor = cursor - len + rhs.len();
+    }
+}
+
+/// Deletes the character before the cursor, leaving the prompt when the line is empty
 fn pop_char(buf: &mut Buffer) {
-    if buf.keys.pop().is_none() {
-        buf.change_mode(Mode::Normal);
+    if buf.keys.is_empty() {
+        return match buf.mode {
+            Mode::Search => cancel_search(buf),
+            _ => buf.change_mode(Mode::Normal),
+        };
     }
+
+    let start = prev_char(buf);
+    buf.keys.replace_range(start..buf.command_cursor, "");
+    buf.command_cursor = start;
 }
 
 #[cfg(test)]
 mod tests {
     use std::fs;
 
-    use ratatui::crossterm::event::KeyModifiers;
-
     use crate::filesystem::read_file;
 
     use super::*;
@@ -86,4 +416,151 @@ mod tests {
         assert_written(&filename, "test");
         assert!(buf.exit);
     }
+
+    #[test]
+    fn defines_abbreviations() {
+        let mut buf = Buffer::test(String::new());
+        buf.mode = Mode::Command;
+
+        buf.input_keys("iab teh the");
+        buf.handle_keys(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        buf.input_keys(":cabbrev W w");
+        buf.handle_keys(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+
+        assert_eq!(
+            buf.abbreviations
+                .find(AbbreviationMode::Insert, buf.options.iskeyword(), "teh"),
+            Some((3, String::from("the")))
+        );
+        assert_eq!(
+            buf.abbreviations
+                .find(AbbreviationMode::Insert, buf.options.iskeyword(), "W"),
+            None
+        );
+
+        buf.input_keys(":una W");
+        buf.handle_keys(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        assert_eq!(
+            buf.abbreviations
+                .find(AbbreviationMode::Command, buf.options.iskeyword(), "W"),
+            None
+        );
+
+        buf.input_keys(":una W");
+        buf.handle_keys(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        assert_eq!(buf.message().content, "No such abbreviation");
+
+        buf.input_keys(":abc");
+        buf.handle_keys(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        assert_eq!(
+            buf.abbreviations
+                .find(AbbreviationMode::Insert, buf.options.iskeyword(), "teh"),
+            None
+        );
+    }
+
+    #[test]
+    fn expands_abbreviations() {
+        let mut buf = Buffer::test(String::new());
+        buf.abbreviations
+            .add(&[AbbreviationMode::Command], "ff", "foo");
+        buf.mode = Mode::Command;
+
+        buf.input_keys("ab ff ");
+        assert_eq!(buf.keys, "ab foo ");
+
+        buf.input_keys("ff");
+        buf.handle_keys(KeyEvent::new(KeyCode::Char('v'), KeyModifiers::CONTROL));
+        buf.input_keys(" ff");
+        buf.handle_keys(KeyEvent::new(KeyCode::Char(']'), KeyModifiers::CONTROL));
+        assert_eq!(buf.keys, "ab foo ff foo");
+    }
+
+    #[test]
+    fn inserts_special_characters() {
+        let mut buf = Buffer::test(String::new());
+        buf.mode = Mode::Command;
+
+        buf.handle_keys(KeyEvent::new(KeyCode::Char('k'), KeyModifiers::CONTROL));
+        buf.input_keys("a*");
+        buf.handle_keys(KeyEvent::new(KeyCode::Char('v'), KeyModifiers::CONTROL));
+        buf.input_keys("065 ");
+        buf.handle_keys(KeyEvent::new(KeyCode::Char('v'), KeyModifiers::CONTROL));
+        buf.input_keys("u3b2x");
+
+        assert_eq!(buf.keys, "αA βx");
+    }
+
+    #[test]
+    fn jumps_to_line() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.mode = Mode::Command;
+
+        buf.input_keys("3");
+        buf.handle_keys(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+
+        assert_eq!(buf.cursor.row, 2);
+    }
+
+    #[test]
+    fn runs_ranges_and_chains() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+
+        buf.input_keys(":2,3d|$");
+        buf.handle_keys(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        assert_eq!(buf.mode, Mode::Normal);
+        assert_eq!(buf.content.len(), 5);
+        assert_eq!(buf.content[1], "Mauris vitae semper varius eros morbi.");
+        assert_eq!(buf.cursor.row, 4);
+
+        buf.input_keys("ggma:'a,/Lorem/y b");
+        buf.handle_keys(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        assert_eq!(
+            buf.register.get('b'),
+            "\nLorem ipsum odor amet, \nMauris vitae semper varius eros morbi.\nLorem.ipsum\n"
+        );
+
+        buf.input_keys(":foo | q");
+        buf.handle_keys(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        assert_eq!(buf.message().content, "Not an editor command: foo | q");
+        assert!(!buf.exit);
+    }
+
+    #[test]
+    fn set_option() {
+        let mut buf = Buffer::test(String::new());
+        buf.mode = Mode::Command;
+
+        buf.input_keys("set ai");
+        buf.handle_keys(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+
+        assert!(buf.options.autoindent());
+    }
+
+    #[test]
+    fn edits_the_command_line() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        let ctrl = |char| KeyEvent::new(KeyCode::Char(char), KeyModifiers::CONTROL);
+
+        buf.input_keys(":s/foo/bar");
+        buf.handle_keys(KeyEvent::new(KeyCode::Left, KeyModifiers::NONE));
+        buf.handle_keys(ctrl('w'));
+        assert_eq!((buf.keys.as_str(), buf.command_cursor), ("s/foo/r", 6));
+        buf.handle_keys(ctrl('u'));
+        buf.input_keys("a");
+        assert_eq!(buf.keys, "ar");
+
+        buf.handle_keys(ctrl('b'));
+        buf.handle_keys(KeyEvent::new(KeyCode::Delete, KeyModifiers::NONE));
+        buf.handle_keys(ctrl('e'));
+        buf.handle_keys(ctrl('r'));
+        buf.handle_keys(ctrl('w'));
+        assert_eq!(buf.keys, "rLorem");
+
+        buf.register.set('a', String::from("line\n"));
+        buf.handle_keys(ctrl('b'));
+        buf.handle_keys(ctrl('r'));
+        buf.input_keys("a");
+        assert_eq!((buf.keys.as_str(), buf.command_cursor), ("linerLorem", 4));
+    }
 }
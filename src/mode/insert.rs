@@ -1,32 +1,317 @@
-use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
-use crate::{buffer::Buffer, mode::Mode, navigation::left};
+use crate::{
+    abbreviation::AbbreviationMode,
+    actions::{
+        insert::{open_line_above, open_line_below},
+        Action,
+    },
+    buffer::Buffer,
+    completion::{handle_completion_keys, start_completion, CompletionKind},
+    history::run_command_window,
+    indent::{dedent_closing, indent_after, indent_of},
+    mode::{
+        literal::{digraph_key, literal, Special},
+        Mode,
+    },
+    navigation::{down, left, left_wrap, right_wrap, up},
+    repeat::{finish_change, record_key},
+    utils::split_first_char,
+    view::{desired_col, remember_col},
+};
+
+const CTRL_K: char = '\x0b';
+const CTRL_R: char = '\x12';
+const CTRL_V: char = '\x16';
+const CTRL_X: char = '\x18';
 
 pub fn handle_insert_keys(buf: &mut Buffer, event: KeyEvent) {
+    if !buf.pending.is_empty() {
+        return handle_pending(buf, event);
+    }
+
+    if handle_completion_keys(buf, event) {
+        return;
+    }
+
+    let moves = matches!(
+        event.code,
+        KeyCode::Left | KeyCode::Right | KeyCode::Up | KeyCode::Down | KeyCode::Home | KeyCode::End
+    );
+    if !moves && event.code != KeyCode::Esc {
+        // Typing at a virtual column fills the line up to it with spaces
+        let line = &mut buf.content[buf.cursor.row];
+        if buf.cursor.col > line.len() {
+            let padding = buf.cursor.col - line.len();
+            line.push_str(&" ".repeat(padding));
+        }
+        buf.insert_start.get_or_insert(buf.cursor);
+    }
+
+    let ctrl = event.modifiers.contains(KeyModifiers::CONTROL);
     match event.code {
+        KeyCode::Char(key) if !ctrl && !buf.options.iskeyword().contains(key) => {
+            expand_abbreviation(buf)
+        }
+        KeyCode::Enter | KeyCode::Tab | KeyCode::Esc => expand_abbreviation(buf),
+        _ => {}
+    }
+
+    match event.code {
+        KeyCode::Esc => {}
+        KeyCode::Char('c' | 'o' | 'r' | 'a' | 'n' | 'p' | 'x') if ctrl => {}
+        _ => record_key(buf, event),
+    }
+
+    match event.code {
+        KeyCode::Char('c') if ctrl => leave_insert(buf, false),
+        KeyCode::Char(']') if ctrl => expand_abbreviation(buf),
+        KeyCode::Char('h') if ctrl => pop_char(buf),
+        KeyCode::Char('w') if ctrl => delete_word_before(buf),
+        KeyCode::Char('u') if ctrl => delete_line_before(buf),
+        KeyCode::Char('r') if ctrl => buf.pending.push(CTRL_R),
+        KeyCode::Char('v') if ctrl => buf.pending.push(CTRL_V),
+        KeyCode::Char('x') if ctrl => buf.pending.push(CTRL_X),
+        KeyCode::Char('k') if ctrl => buf.pending.push(CTRL_K),
+        KeyCode::Char('n') if ctrl => start_completion(buf, CompletionKind::Keyword, false),
+        KeyCode::Char('p') if ctrl => start_completion(buf, CompletionKind::Keyword, true),
+        KeyCode::Char('a') if ctrl => insert_text(buf, &buf.register.get('.')),
+        KeyCode::Char('o') if ctrl => {
+            finish_change(buf);
+            buf.change_mode(Mode::Normal);
+            buf.restart_insert = true;
+        }
+        KeyCode::Char(_) if ctrl => {}
+        KeyCode::Char('}') => {
+            insert_char(buf, '}');
+            dedent_closing(buf);
+        }
         KeyCode::Char(key) => insert_char(buf, key),
+        KeyCode::Tab => insert_char(buf, '\t'),
         KeyCode::Backspace => pop_char(buf),
-        KeyCode::Enter => new_line(buf),
-        KeyCode::Esc => {
-            buf.change_mode(Mode::Normal);
-            buf.cursor = left(buf);
+        KeyCode::Delete => delete_char(buf),
+        KeyCode::Enter if buf.command_window.is_some() => {
+            leave_insert(buf, false);
+            run_command_window(buf);
         }
+        KeyCode::Enter => new_line(buf),
+        KeyCode::Left => buf.cursor = left_wrap(buf, '['),
+        KeyCode::Right => buf.cursor = right_wrap(buf, ']'),
+        KeyCode::Up => move_vertically(buf, false),
+        KeyCode::Down => move_vertically(buf, true),
+        KeyCode::Home => buf.cursor.col = 0,
+        KeyCode::End => buf.cursor.col = buf.row(buf.cursor.row).len(),
+        KeyCode::Esc => leave_insert(buf, true),
         _ => {}
     }
+
+    // Moving the cursor starts a new insert for `backspace`
+    if moves {
+        buf.insert_start = None;
+        buf.auto_indent = None;
+    }
+}
+
+/// Continues a `Ctrl-r`, `Ctrl-v`, `Ctrl-k` or `Ctrl-x` sequence with the key typed after it
+fn handle_pending(buf: &mut Buffer, event: KeyEvent) {
+    let ctrl = event.modifiers.contains(KeyModifiers::CONTROL);
+    let (prefix, typed) = split_first_char(&std::mem::take(&mut buf.pending));
+
+    let special = match (prefix, event.code) {
+        (CTRL_X, KeyCode::Char('l')) if ctrl => {
+            return start_completion(buf, CompletionKind::Line, false)
+        }
+        (CTRL_X, KeyCode::Char('f')) if ctrl => {
+            return start_completion(buf, CompletionKind::File, false)
+        }
+        (CTRL_X, KeyCode::Char('k')) if ctrl => {
+            return start_completion(buf, CompletionKind::Dictionary, false)
+        }
+        (CTRL_X, KeyCode::Char('n')) if ctrl => {
+            return start_completion(buf, CompletionKind::Keyword, false)
+        }
+        (CTRL_X, KeyCode::Char('p')) if ctrl => {
+            return start_completion(buf, CompletionKind::Keyword, true)
+        }
+        (CTRL_X, _) => return handle_insert_keys(buf, event),
+        (CTRL_R, KeyCode::Char(name)) => {
+            let text = buf.register.get(name);
+            let text = match text.strip_prefix('\n') {
+                Some(lines) if text.ends_with('\n') => lines.to_string(),
+                _ => text,
+            };
+            return insert_text(buf, &text);
+        }
+        (CTRL_V, _) => literal(&typed, event),
+        (CTRL_K, _) => digraph_key(&typed, event),
+        _ => return,
+    };
+
+    match special {
+        Special::Pending(char) => {
+            record_key(buf, event);
+            buf.pending = format!("{}{}{}", prefix, typed, char);
+        }
+        Special::Insert(char) => {
+            record_key(buf, event);
+            insert_char(buf, char);
+        }
+        Special::InsertThen(char, event) => {
+            if let Some(char) = char {
+                insert_char(buf, char);
+            }
+            handle_insert_keys(buf, event);
+        }
+    }
+}
+
+/// Replaces an abbreviation before the cursor with its expansion
+fn expand_abbreviation(buf: &mut Buffer) {
+    let before = &buf.row(buf.cursor.row)[..buf.cursor.col];
+    let Some((len, rhs)) =
+        buf.abbreviations
+            .find(AbbreviationMode::Insert, buf.options.iskeyword(), before)
+    else {
+        return;
+    };
+
+    buf.content[buf.cursor.row].replace_range(buf.cursor.col - len..buf.cursor.col, "");
+    buf.cursor.col -= len;
+
+    for char in rhs.chars() {
+        match char {
+            '\n' => new_line(buf),
+            char => insert_char(buf, char),
+        }
+    }
+}
+
+/// Returns to normal mode, first typing the text again for the count of the command that
+/// started insert mode with `repeat`
+fn leave_insert(buf: &mut Buffer, repeat: bool) {
+    if buf.recording.is_some() {
+        buf.register.set('.', inserted_text(buf));
+    }
+    if repeat {
+        repeat_insert(buf);
+    }
+
+    buf.last_insert = Some(buf.cursor);
+    buf.change_mode(Mode::Normal);
+    buf.cursor = left(buf);
+    finish_change(buf);
+}
+
+/// The text typed since the insert started, as it ended up in the buffer. The indent added on
+/// new lines is left out, as inserting the text again adds it
+fn inserted_text(buf: &Buffer) -> String {
+    let (Some(start), end) = (buf.insert_start, buf.cursor) else {
+        return String::new();
+    };
+    if (end.row, end.col) <= (start.row, start.col) {
+        return String::new();
+    }
+
+    let mut text = String::new();
+    for row in start.row..=end.row {
+        let line = buf.row(row);
+        let line = match row == end.row {
+            true => &line[..end.col.min(line.len())],
+            false => line,
+        };
+
+        if row == start.row {
+            text.push_str(&line[start.col.min(line.len())..]);
+        } else {
+            let indent = indent_after(&buf.options, buf.row(row - 1), line);
+            text.push('\n');
+            text.push_str(line.strip_prefix(indent.as_str()).unwrap_or(line));
+        }
+    }
+
+    text
+}
+
+/// Inserts text as if it was typed, so that it is repeated with `.`
+fn insert_text(buf: &mut Buffer, text: &str) {
+    for char in text.chars() {
+        let event = match char {
+            '\n' => KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE),
+            '\t' => KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE),
+            char => KeyEvent::new(KeyCode::Char(char), KeyModifiers::NONE),
+        };
+        handle_insert_keys(buf, event);
+    }
+}
+
+/// Inserts the typed text again for the remaining count of the command that started insert mode
+fn repeat_insert(buf: &mut Buffer) {
+    let Some(change) = buf.recording.take() else {
+        return;
+    };
+
+    if let Some(action) = Action::new(&change.keys).filter(Action::repeats_insert) {
+        for _ in 1..change.count.unwrap_or(1) {
+            match action {
+                Action::OpenBelow => open_line_below(buf),
+                Action::OpenAbove => open_line_above(buf),
+                _ => {}
+            }
+
+            for event in change.inserted.iter() {
+                handle_insert_keys(buf, *event);
+            }
+        }
+    }
+
+    buf.recording = Some(change);
 }
 
 fn insert_char(buf: &mut Buffer, key: char) {
     if let Some(line) = buf.content.get_mut(buf.cursor.row) {
         line.insert(buf.cursor.col, key);
-        buf.cursor.col += 1;
+        buf.cursor.col += key.len_utf8();
     }
 }
 
+/// Whether the `backspace` option allows deleting the character before the cursor
+fn can_backspace(buf: &Buffer) -> bool {
+    let cursor = buf.cursor;
+    let options = &buf.options;
+
+    if cursor.col == 0 && !options.backspaces_over("eol") {
+        return false;
+    }
+    if let Some(start) = buf.insert_start {
+        if !options.backspaces_over("start") && (cursor.row, cursor.col) <= (start.row, start.col) {
+            return false;
+        }
+    }
+    if let Some(indent) = buf.auto_indent {
+        if !options.backspaces_over("indent")
+            && cursor.row == indent.row
+            && cursor.col > 0
+            && cursor.col <= indent.col
+            && buf.row(cursor.row)[..cursor.col].trim().is_empty()
+        {
+            return false;
+        }
+    }
+
+    true
+}
+
 fn pop_char(buf: &mut Buffer) {
+    if !can_backspace(buf) {
+        return;
+    }
+
     if let Some(line) = buf.content.get_mut(buf.cursor.row) {
         if buf.cursor.col == 0 {
             if buf.cursor.row != 0 {
-                let content = buf.content.remove(buf.cursor.row);
+                let content = buf
+                    .remove_lines(buf.cursor.row..buf.cursor.row + 1)
+                    .remove(0);
                 let prev_row = buf
                     .content
                     .get_mut(buf.cursor.row - 1)
@@ -40,20 +325,96 @@ fn pop_char(buf: &mut Buffer) {
             return;
         }
 
-        line.remove(buf.cursor.col - 1);
-        buf.cursor.col -= 1;
+        let prev = line[..buf.cursor.col].chars().next_back().unwrap();
+        buf.cursor.col -= prev.len_utf8();
+        line.remove(buf.cursor.col);
+    }
+}
+
+fn delete_char(buf: &mut Buffer) {
+    let row = buf.cursor.row;
+
+    if buf.cursor.col < buf.row(row).len() {
+        buf.content[row].remove(buf.cursor.col);
+    } else if row + 1 < buf.content.len() {
+        let next = buf.remove_lines(row + 1..row + 2).remove(0);
+        buf.content[row].push_str(&next);
+    }
+}
+
+fn delete_word_before(buf: &mut Buffer) {
+    if !can_backspace(buf) {
+        return;
+    }
+    if buf.cursor.col == 0 {
+        return pop_char(buf);
+    }
+
+    let line = &mut buf.content[buf.cursor.row];
+    let before: Vec<char> = line[..buf.cursor.col].chars().collect();
+    let mut start = before.len();
+
+    while start > 0 && before[start - 1].is_whitespace() {
+        start -= 1;
+    }
+
+    if let Some(&last) = before[..start].last() {
+        let keyword = buf.options.iskeyword().contains(last);
+        while start > 0
+            && !before[start - 1].is_whitespace()
+            && buf.options.iskeyword().contains(before[start - 1]) == keyword
+        {
+            start -= 1;
+        }
+    }
+
+    let start = before[..start].iter().map(|char| char.len_utf8()).sum();
+    line.replace_range(start..buf.cursor.col, "");
+    buf.cursor.col = start;
+}
+
+fn delete_line_before(buf: &mut Buffer) {
+    if !can_backspace(buf) {
+        return;
     }
+    if buf.cursor.col == 0 {
+        return pop_char(buf);
+    }
+
+    let indent = indent_of(buf.row(buf.cursor.row)).len();
+    let start = if buf.cursor.col > indent { indent } else { 0 };
+
+    buf.content[buf.cursor.row].replace_range(start..buf.cursor.col, "");
+    buf.cursor.col = start;
 }
 
 fn new_line(buf: &mut Buffer) {
     if let Some(line) = buf.content.get_mut(buf.cursor.row) {
-        let content = line.drain(buf.cursor.col..).collect();
-        buf.content.insert(buf.cursor.row + 1, content);
+        let content: String = line.drain(buf.cursor.col..).collect();
+        let indent = indent_after(&buf.options, line, &content);
+        let content = if indent.is_empty() {
+            content
+        } else {
+            format!("{}{}", indent, content.trim_start())
+        };
+
+        buf.insert_lines(buf.cursor.row + 1, [content]);
         buf.cursor.row += 1;
-        buf.cursor.col = 0;
+        buf.cursor.col = indent.len();
+        buf.auto_indent = Some(buf.cursor);
     }
 }
 
+/// Moves the cursor a line up or down with the arrow keys, keeping the desired column
+fn move_vertically(buf: &mut Buffer, downward: bool) {
+    let desired = desired_col(buf);
+    buf.cursor = match downward {
+        true => down(buf),
+        false => up(buf),
+    };
+    remember_col(buf, desired);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -94,6 +455,36 @@ mod tests {
         assert_eq!(buf.content[0], "testest2");
     }
 
+    #[test]
+    fn backspace_option() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.options.set("bs= ai").unwrap();
+        buf.content[0] = String::from("    indented");
+        buf.cursor.col = 12;
+        buf.mode = Mode::Insert;
+
+        buf.input_keys("x");
+        buf.handle_keys(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE));
+        buf.handle_keys(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE));
+        assert_eq!(buf.content[0], "    indented");
+
+        buf.handle_keys(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        buf.handle_keys(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE));
+        assert_eq!(buf.content[1], "    ");
+
+        buf.options.set("bs=indent").unwrap();
+        buf.handle_keys(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE));
+        assert_eq!(buf.content[1], "   ");
+
+        buf.cursor.col = 0;
+        buf.handle_keys(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE));
+        assert_eq!(buf.content[1], "   ");
+
+        buf.options.set("bs=indent,eol,start").unwrap();
+        buf.handle_keys(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE));
+        assert_eq!(buf.content[0], "    indented   ");
+    }
+
     #[test]
     fn new_line() {
         let mut buf = Buffer::test(String::new());
@@ -105,4 +496,222 @@ mod tests {
 
         assert_eq!(buf.content, vec!["test", "test2"]);
     }
+
+    #[test]
+    fn indents_new_lines() {
+        let mut buf = Buffer::test(String::new());
+        buf.options.set("si sw=4 et").unwrap();
+        buf.mode = Mode::Insert;
+
+        buf.input_keys("fn main() {");
+        buf.handle_keys(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        buf.input_keys("if x {");
+        buf.handle_keys(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        buf.input_keys("y");
+        buf.handle_keys(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        buf.input_keys("}");
+        buf.handle_keys(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        buf.input_keys("}");
+
+        assert_eq!(
+            buf.content,
+            vec!["fn main() {", "    if x {", "        y", "    }", "}"]
+        );
+    }
+
+    #[test]
+    fn splits_between_braces() {
+        let mut buf = Buffer::test(String::new());
+        buf.options.set("cindent").unwrap();
+        buf.content[0] = String::from("{}");
+        buf.cursor.col = 1;
+        buf.mode = Mode::Insert;
+
+        buf.handle_keys(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        assert_eq!(buf.content, vec!["{", "}"]);
+    }
+
+    #[test]
+    fn expands_abbreviations() {
+        let mut buf = Buffer::test(String::new());
+        buf.abbreviations
+            .add(&[AbbreviationMode::Insert], "teh", "the");
+        buf.abbreviations
+            .add(&[AbbreviationMode::Insert], "lic", "MIT<CR>License");
+        buf.mode = Mode::Insert;
+
+        buf.input_keys("steh teh,");
+        assert_eq!(buf.content[0], "steh the,");
+
+        buf.input_keys(" teh");
+        buf.handle_keys(KeyEvent::new(KeyCode::Char(']'), KeyModifiers::CONTROL));
+        assert_eq!(buf.content[0], "steh the, the");
+
+        buf.input_keys(" teh");
+        buf.handle_keys(KeyEvent::new(KeyCode::Char('v'), KeyModifiers::CONTROL));
+        buf.input_keys(" lic");
+        buf.handle_keys(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        assert_eq!(buf.content, vec!["steh the, the teh MIT", "License"]);
+    }
+
+    #[test]
+    fn inserts_special_characters() {
+        let mut buf = Buffer::test(String::new());
+        buf.mode = Mode::Insert;
+
+        buf.handle_keys(KeyEvent::new(KeyCode::Char('k'), KeyModifiers::CONTROL));
+        buf.input_keys("e:");
+        buf.handle_keys(KeyEvent::new(KeyCode::Char('v'), KeyModifiers::CONTROL));
+        buf.input_keys("u20ac!");
+        buf.handle_keys(KeyEvent::new(KeyCode::Char('v'), KeyModifiers::CONTROL));
+        buf.input_keys("U0001F600");
+        assert_eq!(buf.content[0], "ë€!😀");
+        assert_eq!(buf.cursor.col, 10);
+
+        buf.handle_keys(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE));
+        buf.handle_keys(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE));
+        assert_eq!(buf.content[0], "ë€");
+    }
+
+    #[test]
+    fn remembers_special_characters() {
+        let mut buf = Buffer::test(String::new());
+        buf.input_keys("Ox");
+        buf.handle_keys(KeyEvent::new(KeyCode::Char('k'), KeyModifiers::CONTROL));
+        buf.input_keys("e:");
+        buf.handle_keys(KeyEvent::new(KeyCode::Char('v'), KeyModifiers::CONTROL));
+        buf.input_keys("u00e9");
+        buf.handle_keys(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        assert_eq!(buf.register.get('.'), "xëé");
+
+        buf.input_keys("o");
+        buf.handle_keys(KeyEvent::new(KeyCode::Char('a'), KeyModifiers::CONTROL));
+        assert_eq!(buf.content, vec!["xëé", "xëé", ""]);
+    }
+
+    #[test]
+    fn ctrl_c_exits() {
+        let mut buf = Buffer::test(String::new());
+        buf.mode = Mode::Insert;
+
+        buf.handle_keys(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL));
+        assert_eq!(buf.mode, Mode::Normal);
+        assert_eq!(buf.content[0], "");
+    }
+
+    #[test]
+    fn deletes_words_and_lines() {
+        let mut buf = Buffer::test(String::new());
+        buf.mode = Mode::Insert;
+        buf.input_keys("  foo::bar baz  ");
+
+        buf.handle_keys(KeyEvent::new(KeyCode::Char('w'), KeyModifiers::CONTROL));
+        assert_eq!(buf.content[0], "  foo::bar ");
+        buf.handle_keys(KeyEvent::new(KeyCode::Char('w'), KeyModifiers::CONTROL));
+        assert_eq!(buf.content[0], "  foo::");
+        buf.handle_keys(KeyEvent::new(KeyCode::Char('w'), KeyModifiers::CONTROL));
+        assert_eq!(buf.content[0], "  foo");
+
+        buf.handle_keys(KeyEvent::new(KeyCode::Char('u'), KeyModifiers::CONTROL));
+        assert_eq!(buf.content[0], "  ");
+        assert_eq!(buf.cursor.col, 2);
+    }
+
+    #[test]
+    fn moves_and_deletes() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.mode = Mode::Insert;
+
+        buf.handle_keys(KeyEvent::new(KeyCode::End, KeyModifiers::NONE));
+        assert_eq!(buf.cursor.col, 23);
+        buf.handle_keys(KeyEvent::new(KeyCode::Delete, KeyModifiers::NONE));
+        assert_eq!(
+            buf.content[0],
+            "Lorem ipsum odor amet, consectetuer adipiscing elit. "
+        );
+
+        buf.handle_keys(KeyEvent::new(KeyCode::Home, KeyModifiers::NONE));
+        buf.handle_keys(KeyEvent::new(KeyCode::Right, KeyModifiers::NONE));
+        buf.handle_keys(KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE));
+        assert_eq!(
+            buf.content[0],
+            "L\torem ipsum odor amet, consectetuer adipiscing elit. "
+        );
+    }
+
+    #[test]
+    fn inserts_register() {
+        let mut buf = Buffer::test(String::new());
+        buf.mode = Mode::Insert;
+        buf.register.set_default(String::from("\nline\n"));
+        buf.register.set('a', String::from("word"));
+
+        buf.handle_keys(KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL));
+        buf.input_keys("a");
+        buf.handle_keys(KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL));
+        buf.input_keys("\"");
+        assert_eq!(buf.content, vec!["wordline", ""]);
+    }
+
+    #[test]
+    fn inserts_last_inserted_text() {
+        let mut buf = Buffer::test(String::new());
+        buf.input_keys("ifoo");
+        buf.handle_keys(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        buf.input_keys("A");
+        buf.handle_keys(KeyEvent::new(KeyCode::Char('a'), KeyModifiers::CONTROL));
+
+        assert_eq!(buf.content[0], "foofoo");
+        buf.handle_keys(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+
+        buf.input_keys("Ofoo bar");
+        buf.handle_keys(KeyEvent::new(KeyCode::Char('w'), KeyModifiers::CONTROL));
+        buf.input_keys("baz");
+        buf.handle_keys(KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL));
+        buf.input_keys("/");
+        buf.handle_keys(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        assert_eq!(buf.register.get('.'), "foo baz");
+
+        buf.input_keys("3ix");
+        buf.handle_keys(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        assert_eq!(buf.register.get('.'), "x");
+        buf.input_keys("o");
+        buf.handle_keys(KeyEvent::new(KeyCode::Char('a'), KeyModifiers::CONTROL));
+        assert_eq!(buf.content, vec!["foo baxxxz", "x", "foofoo"]);
+    }
+
+    #[test]
+    fn inserts_literally() {
+        let mut buf = Buffer::test(String::new());
+        buf.mode = Mode::Insert;
+
+        buf.handle_keys(KeyEvent::new(KeyCode::Char('v'), KeyModifiers::CONTROL));
+        buf.handle_keys(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        buf.handle_keys(KeyEvent::new(KeyCode::Char('v'), KeyModifiers::CONTROL));
+        buf.handle_keys(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL));
+        assert_eq!(buf.content, vec!["\r\x03"]);
+    }
+
+    #[test]
+    fn runs_one_normal_command() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.mode = Mode::Insert;
+
+        buf.handle_keys(KeyEvent::new(KeyCode::Char('o'), KeyModifiers::CONTROL));
+        assert_eq!(buf.mode, Mode::Normal);
+        buf.input_keys("w");
+        assert_eq!(buf.mode, Mode::Insert);
+        assert_eq!(buf.cursor.col, 6);
+        buf.handle_keys(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+
+        buf.content = vec![String::from("abc")];
+        buf.input_keys("0A");
+        buf.handle_keys(KeyEvent::new(KeyCode::Char('o'), KeyModifiers::CONTROL));
+        buf.input_keys("$X");
+        assert_eq!(buf.content[0], "abcX");
+
+        buf.handle_keys(KeyEvent::new(KeyCode::Char('o'), KeyModifiers::CONTROL));
+        buf.input_keys("0Y");
+        assert_eq!(buf.content[0], "YabcX");
+    }
 }
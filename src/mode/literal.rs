@@ -0,0 +1,132 @@
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::digraph::digraph;
+
+/// Result of a key typed after `Ctrl-v` or `Ctrl-k`
+#[derive(Debug, PartialEq)]
+pub enum Special {
+    /// More keys are needed, the typed character is kept
+    Pending(char),
+    Insert(char),
+    /// The sequence ended without using the key, which still has to be handled
+    InsertThen(Option<char>, KeyEvent),
+}
+
+/// Handles a key after `Ctrl-v`, `typed` holds the keys already typed for a character code
+/// such as `u20ac`, `x41`, `o101` or `065`
+pub fn literal(typed: &str, event: KeyEvent) -> Special {
+    let Some(kind) = typed.chars().next() else {
+        return match event.code {
+            KeyCode::Char(key)
+                if !event.modifiers.contains(KeyModifiers::CONTROL)
+                    && (key.is_ascii_digit() || "uUxXoO".contains(key)) =>
+            {
+                Special::Pending(key)
+            }
+            _ => match literal_char(event) {
+                Some(char) => Special::Insert(char),
+                None => Special::InsertThen(None, event),
+            },
+        };
+    };
+
+    let (radix, max_len, digits) = match kind {
+        'u' => (16, 4, &typed[1..]),
+        'U' => (16, 8, &typed[1..]),
+        'x' | 'X' => (16, 2, &typed[1..]),
+        'o' | 'O' => (8, 3, &typed[1..]),
+        _ => (10, 3, typed),
+    };
+
+    match event.code {
+        KeyCode::Char(key) if key.is_digit(radix) && digits.len() + 1 < max_len => {
+            Special::Pending(key)
+        }
+        KeyCode::Char(key) if key.is_digit(radix) => {
+            match code_point(&format!("{}{}", digits, key), radix) {
+                Some(char) => Special::Insert(char),
+                None => Special::InsertThen(None, event),
+            }
+        }
+        _ if digits.is_empty() => Special::InsertThen(Some(kind), event),
+        _ => Special::InsertThen(code_point(digits, radix), event),
+    }
+}
+
+/// Handles a key after `Ctrl-k`, `typed` holds the first character of the digraph
+pub fn digraph_key(typed: &str, event: KeyEvent) -> Special {
+    let KeyCode::Char(key) = event.code else {
+        return Special::InsertThen(None, event);
+    };
+
+    match typed.chars().next() {
+        Some(first) => Special::Insert(digraph(first, key)),
+        None => Special::Pending(key),
+    }
+}
+
+fn code_point(digits: &str, radix: u32) -> Option<char> {
+    let code = u32::from_str_radix(digits, radix).ok()?;
+    if radix == 10 && code > 255 {
+        return None;
+    }
+
+    char::from_u32(code)
+}
+
+/// The character a key stands for when it is inserted literally after `Ctrl-v`
+fn literal_char(event: KeyEvent) -> Option<char> {
+    match event.code {
+        KeyCode::Char(key) if event.modifiers.contains(KeyModifiers::CONTROL) => {
+            char::from_u32(key.to_ascii_uppercase() as u32 & 0x1f)
+        }
+        KeyCode::Char(key) => Some(key),
+        KeyCode::Tab => Some('\t'),
+        KeyCode::Enter => Some('\r'),
+        KeyCode::Esc => Some('\x1b'),
+        KeyCode::Backspace => Some('\x08'),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(char: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(char), KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn enters_code_points() {
+        assert_eq!(literal("", key('u')), Special::Pending('u'));
+        assert_eq!(literal("u20a", key('c')), Special::Insert('€'));
+        assert_eq!(literal("U0001F60", key('0')), Special::Insert('😀'));
+        assert_eq!(literal("06", key('5')), Special::Insert('A'));
+        assert_eq!(literal("x4", key('1')), Special::Insert('A'));
+        assert_eq!(literal("o10", key('1')), Special::Insert('A'));
+        assert_eq!(
+            literal("u41", key(' ')),
+            Special::InsertThen(Some('A'), key(' '))
+        );
+        assert_eq!(
+            literal("u", key('z')),
+            Special::InsertThen(Some('u'), key('z'))
+        );
+    }
+
+    #[test]
+    fn enters_literal_keys() {
+        assert_eq!(literal("", key('a')), Special::Insert('a'));
+        assert_eq!(
+            literal("", KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)),
+            Special::Insert('\r')
+        );
+    }
+
+    #[test]
+    fn enters_digraphs() {
+        assert_eq!(digraph_key("", key('e')), Special::Pending('e'));
+        assert_eq!(digraph_key("e", key(':')), Special::Insert('ë'));
+    }
+}
@@ -1,69 +1,286 @@
 use std::cmp::min;
 
-use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
-use crate::{actions::Action, buffer::Buffer, motion::Motion, navigation::right};
+use crate::{
+    actions::{Action, OPERATORS},
+    buffer::Buffer,
+    digraph::digraph_for,
+    history::run_command_window,
+    motion::Motion,
+    navigation::{first_not_whitespace, goto_line},
+    repeat::{finish_change, repeat_change, start_change},
+    search::{search_word, start_search},
+    substitute::{handle_confirm_keys, repeat_last},
+    utils::split_count,
+    view::{
+        desired_col, remember_col, reposition, scroll_half_page, scroll_lines, scroll_page, ViewAt,
+    },
+};
 
-use super::Mode;
+use super::{visual::start_visual, Mode};
 
 pub fn handle_normal_keys(buf: &mut Buffer, event: KeyEvent) {
+    if buf.substitution.is_some() {
+        return handle_confirm_keys(buf, event);
+    }
+
     match event.code {
+        KeyCode::Char(key) if event.modifiers.contains(KeyModifiers::CONTROL) => {
+            handle_ctrl(buf, key)
+        }
         KeyCode::Char(key) => handle_char(buf, key),
-        KeyCode::Esc => buf.keys = String::new(),
+        KeyCode::Enter if buf.command_window.is_some() && buf.keys.is_empty() => {
+            run_command_window(buf)
+        }
+        KeyCode::Enter => handle_char(buf, '\r'),
+        KeyCode::Backspace => handle_char(buf, '\x08'),
+        KeyCode::Left => handle_arrow(buf, Motion::ArrowLeft),
+        KeyCode::Right => handle_arrow(buf, Motion::ArrowRight),
+        KeyCode::Up => handle_arrow(buf, Motion::Up),
+        KeyCode::Down => handle_arrow(buf, Motion::Down),
+        KeyCode::Esc => {
+            buf.keys = String::new();
+            restart_insert(buf);
+        }
         _ => {}
     }
 }
 
 fn handle_char(buf: &mut Buffer, key: char) {
     buf.keys.push(key);
+    let (count, keys) = parse_count(&buf.keys);
 
-    let mut found = match Motion::new(&buf.keys) {
+    let mut found = match Motion::new(&keys) {
         Some(motion) => {
-            buf.cursor = motion.execute(buf);
+            move_cursor(buf, motion, count);
             true
         }
-        None => execute_keybindings(buf),
+        None => {
+            // Only commands that start inserting can use a virtual column past the end of the line
+            if !keys.is_empty() && !Action::new(&keys).is_some_and(|action| action.repeats_insert())
+            {
+                buf.cursor.col = min(buf.cursor.col, buf.row(buf.cursor.row).len());
+            }
+            execute_keybindings(buf, &keys, count)
+        }
     };
 
     if !found {
-        if let Some(action) = Action::new(&buf.keys) {
-            action.execute(buf);
+        if let Some(action) = Action::new(&keys) {
+            if action.changes() {
+                start_change(buf, count, &keys);
+            }
+            action.execute(buf, count);
+            if buf.mode != Mode::Insert {
+                finish_change(buf);
+            }
             found = true
         }
     }
 
     if found {
         buf.keys = String::new();
+        restart_insert(buf);
+    }
+}
+
+/// Moves the cursor with an arrow key, which takes a count but no operator
+fn handle_arrow(buf: &mut Buffer, motion: Motion) {
+    let (count, keys) = parse_count(&buf.keys);
+    if keys.is_empty() {
+        move_cursor(buf, motion, count);
+    }
+
+    buf.keys = String::new();
+    restart_insert(buf);
+}
+
+pub(super) fn move_cursor(buf: &mut Buffer, motion: Motion, count: Option<usize>) {
+    let desired = match motion {
+        Motion::LineEnd => usize::MAX,
+        _ => desired_col(buf),
+    };
+    buf.cursor = motion.apply(buf, count);
+    if motion == Motion::LineEnd || motion.keeps_column(buf, count) {
+        remember_col(buf, desired);
+    }
+}
+
+/// Handles the scroll commands, which are typed with control
+fn handle_ctrl(buf: &mut Buffer, key: char) {
+    // `Ctrl-v` after an operator makes its motion blockwise
+    if key == 'v'
+        && OPERATORS
+            .iter()
+            .any(|op| parse_count(&buf.keys).1 == op.to_string())
+    {
+        return buf.keys.push('\x16');
+    }
+
+    // `Ctrl-h` moves like Backspace
+    if key == 'h' {
+        return handle_char(buf, '\x08');
+    }
+
+    let (count, keys) = split_count(&buf.keys);
+    if !keys.is_empty() {
+        buf.keys = String::new();
+        return;
+    }
+
+    let repeat = count.unwrap_or(1);
+    match key {
+        'e' => scroll_lines(buf, true, repeat),
+        'y' => scroll_lines(buf, false, repeat),
+        'd' => scroll_half_page(buf, true, count),
+        'u' => scroll_half_page(buf, false, count),
+        'f' => scroll_page(buf, true, repeat),
+        'b' => scroll_page(buf, false, repeat),
+        _ => return,
     }
+
+    buf.keys = String::new();
+    restart_insert(buf);
 }
 
-fn execute_keybindings(buf: &mut Buffer) -> bool {
-    match buf.keys.as_str() {
-        "i" => buf.mode = Mode::Insert,
-        "a" => {
+/// Returns to insert mode after the single command started with `Ctrl-o`. After `$` the cursor
+/// goes past the end of the line, where typing appends
+fn restart_insert(buf: &mut Buffer) {
+    if buf.restart_insert {
+        buf.restart_insert = false;
+        if buf.mode == Mode::Normal {
             buf.mode = Mode::Insert;
-            buf.cursor = right(buf);
+            if desired_col(buf) == usize::MAX {
+                buf.cursor.col = buf.row(buf.cursor.row).len();
+            }
+        }
+    }
+}
+
+/// Splits the count off the keys, multiplying it with a count given after an operator as in `2d3w`
+pub(super) fn parse_count(keys: &str) -> (Option<usize>, String) {
+    let (count, keys) = split_count(keys);
+
+    match keys.chars().next() {
+        Some(operator) if OPERATORS.contains(&operator) => {
+            let (inner, keys) = split_count(&keys[operator.len_utf8()..]);
+            let count = match (count, inner) {
+                (Some(count), Some(inner)) => Some(count.saturating_mul(inner)),
+                (count, inner) => count.or(inner),
+            };
+
+            (count, format!("{}{}", operator, keys))
+        }
+        _ => (count, keys.to_string()),
+    }
+}
+
+fn execute_keybindings(buf: &mut Buffer, keys: &str, count: Option<usize>) -> bool {
+    match keys {
+        ":" => buf.change_mode(Mode::Command),
+        "q:" | "q/" | "q?" => buf.open_command_window = keys.chars().nth(1),
+        "v" | "V" => start_visual(buf, keys == "V"),
+        keys if is_search(keys) => {
+            let operator = buf.keys[..buf.keys.len() - 1].to_string();
+            start_search(buf, keys.ends_with('?'), &operator);
+        }
+        "." => repeat_change(buf, count),
+        "*" | "#" | "g*" | "g#" => search_word(
+            buf,
+            keys.ends_with('#'),
+            !keys.starts_with('g'),
+            count.unwrap_or(1),
+        ),
+        keys if keys.len() == 2 && keys.starts_with('m') => {
+            let mark = keys.chars().nth(1).unwrap();
+            if mark.is_ascii_lowercase() {
+                buf.marks.insert(mark, buf.cursor);
+            }
+        }
+        "&" | "g&" => repeat_last(buf, keys == "g&"),
+        "ga" => show_char_code(buf),
+        "g8" => show_char_bytes(buf),
+        "zt" | "z\r" | "zz" | "z." | "zb" | "z-" => {
+            if let Some(count) = count {
+                buf.cursor = goto_line(buf, count);
+            }
+            let at = match keys {
+                "zt" | "z\r" => ViewAt::Top,
+                "zz" | "z." => ViewAt::Center,
+                _ => ViewAt::Bottom,
+            };
+            reposition(buf, at);
+            if ["z\r", "z.", "z-"].contains(&keys) {
+                buf.cursor.col = first_not_whitespace(buf.row(buf.cursor.row));
+            }
         }
-        ":" => buf.mode = Mode::Command,
-        "x" => delete_char(buf),
         _ => return false,
     }
 
     true
 }
 
-fn delete_char(buf: &mut Buffer) {
-    let line = &mut buf.content[buf.cursor.row];
-    if line.is_empty() {
-        return;
+/// Whether the keys open the search prompt, optionally after an operator to use it as a motion
+fn is_search(keys: &str) -> bool {
+    match keys.strip_suffix(['/', '?']) {
+        Some("") => true,
+        Some(operator) => {
+            let mut chars = operator.chars();
+            matches!((chars.next(), chars.next()), (Some(char), None) if OPERATORS.contains(&char))
+        }
+        None => false,
+    }
+}
+
+fn cursor_char(buf: &Buffer) -> Option<char> {
+    buf.row(buf.cursor.row)
+        .get(buf.cursor.col..)?
+        .chars()
+        .next()
+}
+
+/// Shows the character under the cursor in decimal, hex and octal, with its digraph if it has one
+fn show_char_code(buf: &mut Buffer) {
+    let Some(char) = cursor_char(buf) else {
+        return buf.show_message("NUL");
+    };
+
+    let code = char as u32;
+    let hex = match code {
+        0..=0xff if char.is_ascii() => format!("{:02x}", code),
+        0..=0xffff => format!("{:04x}", code),
+        _ => format!("{:08x}", code),
+    };
+    let mut message = format!("<{}> {}, Hex {}, Oct {:03o}", char, code, hex, code);
+    if let Some(digraph) = digraph_for(char) {
+        message.push_str(&format!(", Digr {}", digraph));
     }
 
-    line.remove(buf.cursor.col);
-    buf.cursor.col = min(buf.cursor.col, line.len() - 1);
+    buf.show_message(message);
+}
+
+/// Shows the UTF-8 bytes of the character under the cursor in hex
+fn show_char_bytes(buf: &mut Buffer) {
+    let Some(char) = cursor_char(buf) else {
+        return buf.show_message("NUL");
+    };
+
+    let mut bytes = [0; 4];
+    let message = char
+        .encode_utf8(&mut bytes)
+        .bytes()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    buf.show_message(message);
 }
 
 #[cfg(test)]
 mod tests {
+    use ratatui::crossterm::event::KeyModifiers;
+
     use crate::buffer::Position;
 
     use super::*;
@@ -85,6 +302,19 @@ mod tests {
         assert_eq!(buf.keys, String::new());
     }
 
+    #[test]
+    fn multiplies_counts() {
+        assert_eq!(parse_count("2d3w"), (Some(6), String::from("dw")));
+        assert_eq!(
+            parse_count("99999999999999999d99999999999999999w"),
+            (Some(usize::MAX), String::from("dw"))
+        );
+
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.input_keys("99999999999999999d99999999999999999w");
+        assert_eq!(buf.content, vec![String::new()]);
+    }
+
     #[test]
     fn switch_to_command() {
         let mut buf = Buffer::test(String::new());
@@ -137,4 +367,191 @@ mod tests {
         buf.input_keys("x");
         assert_eq!(buf.content[0], "Lore ipsum odor amet,");
     }
+
+    #[test]
+    fn parses_counts() {
+        assert_eq!(parse_count("3w"), (Some(3), String::from("w")));
+        assert_eq!(parse_count("2d3w"), (Some(6), String::from("dw")));
+        assert_eq!(parse_count("d2f3"), (Some(2), String::from("df3")));
+        assert_eq!(parse_count("0"), (None, String::from("0")));
+    }
+
+    #[test]
+    fn motion_with_count() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.input_keys("3l");
+
+        assert_eq!(buf.cursor, Position { row: 0, col: 3 });
+    }
+
+    #[test]
+    fn opens_lines_with_count() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.input_keys("2ofoo");
+        buf.handle_keys(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+
+        assert_eq!(buf.content[1], "foo");
+        assert_eq!(buf.content[2], "foo");
+        assert_eq!(buf.cursor, Position { row: 2, col: 2 });
+
+        buf.input_keys("O");
+        buf.handle_keys(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        assert_eq!(buf.content[2], "");
+        assert_eq!(buf.content.len(), 10);
+    }
+
+    #[test]
+    fn changes_lines() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.input_keys("2ccnew");
+        buf.handle_keys(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+
+        assert_eq!(buf.content[0], "new");
+        assert_eq!(buf.content.len(), 6);
+
+        buf.cursor = Position { row: 1, col: 4 };
+        buf.input_keys("Cum");
+        assert_eq!(buf.content[1], "Ridium");
+
+        buf.handle_keys(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        buf.cursor = Position { row: 2, col: 0 };
+        buf.input_keys("2sM");
+        assert_eq!(buf.content[2], "Muris vitae semper varius eros morbi.");
+    }
+
+    #[test]
+    fn inserts_at_last_position() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.cursor = Position { row: 2, col: 4 };
+        buf.input_keys("a!");
+        buf.handle_keys(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+
+        buf.cursor = Position { row: 0, col: 0 };
+        buf.input_keys("gi?");
+        assert_eq!(
+            buf.content[2],
+            "Ridic!?ulus nulla consectetur proin purus ad justo nullam. "
+        );
+
+        buf.handle_keys(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        buf.input_keys("gI>");
+        assert_eq!(
+            buf.content[2],
+            ">Ridic!?ulus nulla consectetur proin purus ad justo nullam. "
+        );
+    }
+
+    #[test]
+    fn shows_character_codes() {
+        let mut buf = Buffer::test(String::new());
+        buf.content[0] = String::from("aé€");
+
+        buf.input_keys("ga");
+        assert_eq!(buf.message().content, "<a> 97, Hex 61, Oct 141");
+
+        buf.input_keys("lga");
+        assert_eq!(buf.message().content, "<é> 233, Hex 00e9, Oct 351, Digr e'");
+
+        buf.input_keys("lg8");
+        assert_eq!(buf.message().content, "e2 82 ac");
+    }
+
+    #[test]
+    fn scrolls_view() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.view.height = 3;
+
+        buf.input_keys("2");
+        buf.handle_keys(KeyEvent::new(KeyCode::Char('e'), KeyModifiers::CONTROL));
+        assert_eq!((buf.view.top, buf.cursor.row), (2, 2));
+
+        buf.input_keys("3zt");
+        assert_eq!((buf.view.top, buf.cursor.row), (2, 2));
+
+        buf.input_keys("jz");
+        buf.handle_keys(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        assert_eq!(buf.view.top, 3);
+
+        buf.input_keys("zb");
+        assert_eq!(buf.view.top, 1);
+        assert!(buf.keys.is_empty());
+    }
+
+    #[test]
+    fn keeps_desired_column() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.cursor = Position { row: 3, col: 20 };
+
+        buf.input_keys("jj");
+        assert_eq!(buf.cursor, Position { row: 5, col: 0 });
+        buf.input_keys("kk");
+        assert_eq!(buf.cursor, Position { row: 3, col: 20 });
+
+        buf.input_keys("k$j");
+        assert_eq!(buf.cursor, Position { row: 3, col: 37 });
+        buf.input_keys("jk");
+        assert_eq!(buf.cursor, Position { row: 3, col: 37 });
+        buf.input_keys("hj");
+        assert_eq!(buf.cursor, Position { row: 4, col: 10 });
+        buf.input_keys("k");
+        assert_eq!(buf.cursor, Position { row: 3, col: 36 });
+
+        buf.options.set("nostartofline").unwrap();
+        buf.input_keys("Ggg");
+        assert_eq!(buf.cursor, Position { row: 0, col: 22 });
+
+        buf.input_keys("2h");
+        buf.view.height = 4;
+        buf.options.set("scroll=2").unwrap();
+        buf.handle_keys(KeyEvent::new(KeyCode::Char('d'), KeyModifiers::CONTROL));
+        assert_eq!(buf.cursor, Position { row: 2, col: 20 });
+        buf.input_keys("jjjk");
+        assert_eq!(buf.cursor, Position { row: 4, col: 10 });
+        buf.input_keys("k");
+        assert_eq!(buf.cursor, Position { row: 3, col: 20 });
+    }
+
+    #[test]
+    fn wraps_with_whichwrap() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.cursor = Position { row: 1, col: 0 };
+
+        buf.handle_keys(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE));
+        assert_eq!(buf.cursor, Position { row: 0, col: 22 });
+        buf.input_keys(" ");
+        assert_eq!(buf.cursor, Position { row: 1, col: 0 });
+        buf.input_keys("h");
+        assert_eq!(buf.cursor, Position { row: 1, col: 0 });
+
+        buf.options.set("ww=h,l").unwrap();
+        buf.input_keys("h");
+        assert_eq!(buf.cursor, Position { row: 0, col: 22 });
+        buf.input_keys("l");
+        assert_eq!(buf.cursor, Position { row: 1, col: 0 });
+        buf.handle_keys(KeyEvent::new(KeyCode::Left, KeyModifiers::NONE));
+        assert_eq!(buf.cursor, Position { row: 1, col: 0 });
+
+        buf.cursor = Position { row: 6, col: 4 };
+        buf.input_keys("dl");
+        assert_eq!(buf.content[6], "null");
+    }
+
+    #[test]
+    fn moves_in_virtual_columns() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.options.set("ve=all").unwrap();
+        buf.cursor = Position { row: 4, col: 10 };
+
+        buf.input_keys("lllk");
+        assert_eq!(buf.cursor, Position { row: 3, col: 13 });
+        buf.input_keys("jj");
+        assert_eq!(buf.cursor, Position { row: 5, col: 13 });
+
+        buf.input_keys("ix");
+        buf.handle_keys(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        assert_eq!(buf.content[5], format!("{}x", " ".repeat(13)));
+
+        buf.input_keys("jx");
+        assert_eq!(buf.content[6], "nulla");
+    }
 }
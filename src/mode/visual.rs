@@ -0,0 +1,259 @@
+use std::cmp::{max, min};
+
+use ratatui::{
+    buffer::Buffer as TBuffer,
+    crossterm::event::{KeyCode, KeyEvent, KeyModifiers},
+    layout::Rect,
+    style::Color,
+};
+
+use crate::{
+    actions::delete::{change_lines, delete_lines, delete_region},
+    buffer::{Buffer, Position},
+    motion::{Motion, Region},
+    navigation::clamp_col,
+    view::screen_position,
+};
+
+use super::{
+    normal::{move_cursor, parse_count},
+    Mode,
+};
+
+/// Starts selecting from the cursor, whole lines with `linewise`
+pub fn start_visual(buf: &mut Buffer, linewise: bool) {
+    buf.visual_start = Some(buf.cursor);
+    buf.change_mode(match linewise {
+        true => Mode::VisualLine,
+        false => Mode::Visual,
+    });
+}
+
+pub fn handle_visual_keys(buf: &mut Buffer, event: KeyEvent) {
+    let ctrl = event.modifiers.contains(KeyModifiers::CONTROL);
+    let motion = match event.code {
+        KeyCode::Esc => return leave_visual(buf),
+        KeyCode::Char('c') if ctrl => return leave_visual(buf),
+        KeyCode::Left => Some(Motion::ArrowLeft),
+        KeyCode::Right => Some(Motion::ArrowRight),
+        KeyCode::Up => Some(Motion::Up),
+        KeyCode::Down => Some(Motion::Down),
+        _ => None,
+    };
+    if let Some(motion) = motion {
+        let (count, _) = parse_count(&buf.keys);
+        move_cursor(buf, motion, count);
+        buf.keys = String::new();
+        return;
+    }
+
+    let KeyCode::Char(key) = event.code else {
+        return;
+    };
+    if ctrl {
+        return;
+    }
+
+    buf.keys.push(key);
+    let (count, keys) = parse_count(&buf.keys);
+    match keys.as_str() {
+        // Typing the key of the current mode again leaves it, the other one switches to it
+        "v" | "V" => {
+            let linewise = keys == "V";
+            match (buf.mode, linewise) {
+                (Mode::VisualLine, true) | (Mode::Visual, false) => leave_visual(buf),
+                _ => {
+                    let start = buf.visual_start;
+                    start_visual(buf, linewise);
+                    buf.visual_start = start;
+                }
+            }
+        }
+        "o" => {
+            if let Some(start) = buf.visual_start.replace(buf.cursor) {
+                buf.cursor = start;
+            }
+        }
+        ":" => {
+            leave_visual(buf);
+            buf.change_mode(Mode::Command);
+            buf.keys = String::from("'<,'>");
+            buf.command_cursor = buf.keys.len();
+        }
+        "d" | "x" | "y" | "c" => operate(buf, key),
+        keys => match Motion::new(keys) {
+            Some(motion) => move_cursor(buf, motion, count),
+            None => return,
+        },
+    }
+
+    if matches!(buf.mode, Mode::Visual | Mode::VisualLine) {
+        buf.keys = String::new();
+    }
+}
+
+/// The selected text, from its start to the cursor in either order
+pub fn selection(buf: &Buffer) -> Option<Region> {
+    let (first, last) = ordered(buf.visual_start?, buf.cursor);
+
+    Some(match buf.mode {
+        Mode::VisualLine => Region::Lines {
+            start: first.row,
+            end: last.row,
+        },
+        _ => {
+            let line = buf.row(last.row);
+            let len = line[min(last.col, line.len())..]
+                .chars()
+                .next()
+                .map_or(0, char::len_utf8);
+            Region::Chars {
+                start: first,
+                end: Position {
+                    row: last.row,
+                    col: min(last.col, line.len()) + len,
+                },
+            }
+        }
+    })
+}
+
+/// The two ends of the selection, the one before the other first
+fn ordered(start: Position, cursor: Position) -> (Position, Position) {
+    match (start.row, start.col) <= (cursor.row, cursor.col) {
+        true => (start, cursor),
+        false => (cursor, start),
+    }
+}
+
+/// Returns to normal mode, marking the selection with `'<` and `'>`
+fn leave_visual(buf: &mut Buffer) {
+    if let (Some(start), true) = (buf.visual_start, buf.mode == Mode::VisualLine) {
+        let (first, last) = (
+            min(start.row, buf.cursor.row),
+            max(start.row, buf.cursor.row),
+        );
+        buf.marks.insert('<', Position { row: first, col: 0 });
+        buf.marks.insert(
+            '>',
+            Position {
+                row: last,
+                col: buf.row(last).len(),
+            },
+        );
+    } else if let Some(start) = buf.visual_start {
+        let (first, last) = ordered(start, buf.cursor);
+        buf.marks.insert('<', first);
+        buf.marks.insert('>', last);
+    }
+
+    buf.visual_start = None;
+    buf.change_mode(Mode::Normal);
+}
+
+/// Deletes, yanks or changes the selection
+fn operate(buf: &mut Buffer, key: char) {
+    let Some(region) = selection(buf) else {
+        return;
+    };
+    leave_visual(buf);
+
+    match (key, region) {
+        ('y', region) => {
+            buf.register.set_default(region.text(buf));
+            buf.cursor = region.start(buf);
+        }
+        ('c', Region::Lines { start, end }) => {
+            buf.cursor.row = start;
+            change_lines(buf, end - start + 1);
+            buf.change_mode(Mode::Insert);
+        }
+        (_, Region::Lines { start, end }) => {
+            buf.cursor.row = start;
+            delete_lines(buf, end - start + 1);
+        }
+        ('c', region) => {
+            delete_region(buf, &region);
+            buf.change_mode(Mode::Insert);
+        }
+        (_, region) => {
+            delete_region(buf, &region);
+            buf.cursor.col = clamp_col(buf, buf.cursor.row, buf.cursor.col);
+        }
+    }
+}
+
+/// Highlights the selected text
+pub fn render_selection(buffer: &Buffer, area: Rect, buf: &mut TBuffer) {
+    let Some(region) = selection(buffer) else {
+        return;
+    };
+
+    for (row, range) in region.ranges(buffer) {
+        let line = &buffer.row(row)[range.clone()];
+        // An empty line shows its selection as one cell
+        let cols = line.char_indices().map(|(col, _)| range.start + col);
+        let cols: Vec<usize> = if line.is_empty() {
+            vec![range.start]
+        } else {
+            cols.collect()
+        };
+
+        for col in cols {
+            if let Some(cell) = screen_position(buffer, Position { row, col }, area)
+                .and_then(|pos| buf.cell_mut(pos))
+            {
+                cell.set_bg(Color::Gray).set_fg(Color::Black);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selects_and_operates() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+
+        buf.input_keys("wvee");
+        assert_eq!(buf.mode, Mode::Visual);
+        assert_eq!(
+            selection(&buf),
+            Some(Region::Chars {
+                start: Position { row: 0, col: 6 },
+                end: Position { row: 0, col: 16 },
+            })
+        );
+        buf.input_keys("y");
+        assert_eq!(buf.mode, Mode::Normal);
+        assert_eq!(buf.register.get_default(), "ipsum odor");
+        assert_eq!(buf.marks.get(&'>'), Some(&Position { row: 0, col: 15 }));
+
+        buf.input_keys("jVjd");
+        assert_eq!(buf.content.len(), 5);
+        assert_eq!(buf.content[1], "Mauris vitae semper varius eros morbi.");
+
+        buf.input_keys("vhhox");
+        assert_eq!(buf.content[1], "auris vitae semper varius eros morbi.");
+    }
+
+    #[test]
+    fn runs_commands_on_the_selection() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+
+        buf.input_keys("jVj:");
+        assert_eq!(buf.mode, Mode::Command);
+        assert_eq!(buf.keys, "'<,'>");
+        buf.input_keys("d");
+        buf.handle_keys(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        assert_eq!(buf.content.len(), 5);
+        assert_eq!(buf.content[1], "Mauris vitae semper varius eros morbi.");
+
+        buf.input_keys("v");
+        buf.handle_keys(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        assert_eq!(buf.mode, Mode::Normal);
+        assert_eq!(buf.marks.get(&'<'), Some(&buf.cursor));
+    }
+}
@@ -1,24 +1,128 @@
+use std::{
+    cmp::{max, min},
+    ops::Range,
+};
+
 use crate::{
     buffer::{Buffer, Position},
+    indent::indent_of,
     navigation::{
-        down, end_line, find_char, find_prev_char, left, prev_word_start, right, start_line, up,
-        word_end, word_start,
+        at_word_end, clamp_col, down, end_line, find_char, find_prev_char, goto_column, goto_line,
+        last_non_blank, left_wrap, line_middle, line_start, line_start_by, match_pair,
+        next_paragraph, next_sentence, prev_paragraph, prev_sentence, prev_word_end,
+        prev_word_start, right_wrap, start_line, till_char, till_prev_char, up, word_end,
+        word_start,
     },
+    search::{search_target, Offset},
+    view::{desired_col, screen_line, screen_middle, virtual_col, ViewAt},
 };
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Motion {
     Left,
     Right,
+    /// Move like `Left` and `Right`, but wrap across lines with their own `whichwrap` flags
+    Backspace,
+    Space,
+    ArrowLeft,
+    ArrowRight,
     Up,
     Down,
+    FirstColumn,
+    /// Goes to the display column given by the count, as `|` does
+    Column,
     LineStart,
     LineEnd,
+    LastNonBlank,
+    FirstNonBlank,
+    NextLineStart,
+    PrevLineStart,
+    ScreenMiddleColumn,
+    /// Goes to the middle of the line, or to a percentage of it with a count
+    LineMiddle,
     PrevWordStart,
     StartWord,
     WordEnd,
-    Find { char: char },
-    FindPrev { char: char },
+    PrevWordEnd,
+    PrevBigWordStart,
+    StartBigWord,
+    BigWordEnd,
+    PrevBigWordEnd,
+    Find {
+        char: char,
+    },
+    FindPrev {
+        char: char,
+    },
+    Till {
+        char: char,
+    },
+    TillPrev {
+        char: char,
+    },
+    RepeatFind,
+    RepeatFindReverse,
+    FirstLine,
+    LastLine,
+    /// Goes to the matching bracket, or to a percentage of the file with a count
+    Percent,
+    ScreenTop,
+    ScreenMiddle,
+    ScreenBottom,
+    NextParagraph,
+    PrevParagraph,
+    NextSentence,
+    PrevSentence,
+    /// Repeats the last search, in the opposite direction for `SearchPrev`
+    SearchNext,
+    SearchPrev,
+}
+
+/// The text an operator acts on. Characters run up to, but not including, `end`
+#[derive(Clone, Debug, PartialEq)]
+pub enum Region {
+    Chars {
+        start: Position,
+        end: Position,
+    },
+    Lines {
+        start: usize,
+        end: usize,
+    },
+    Block {
+        start: usize,
+        end: usize,
+        cols: Range<usize>,
+    },
+}
+
+/// How an operator treats the text between the cursor and the end of a motion
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MotionKind {
+    /// Stops before the character the motion ends on
+    Exclusive,
+    Inclusive,
+    Linewise,
+}
+
+/// A `v`, `V` or `Ctrl-v` typed after an operator, making its motion characterwise, linewise or
+/// blockwise
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Force {
+    Chars,
+    Lines,
+    Block,
+}
+
+impl Force {
+    pub fn new(key: char) -> Option<Self> {
+        match key {
+            'v' => Some(Force::Chars),
+            'V' => Some(Force::Lines),
+            '\x16' => Some(Force::Block),
+            _ => None,
+        }
+    }
 }
 
 impl Motion {
@@ -28,11 +132,40 @@ impl Motion {
             "j" => Some(Motion::Down),
             "k" => Some(Motion::Up),
             "l" => Some(Motion::Right),
+            "\x08" => Some(Motion::Backspace),
+            " " => Some(Motion::Space),
+            "0" => Some(Motion::FirstColumn),
+            "|" => Some(Motion::Column),
             "^" => Some(Motion::LineStart),
             "$" => Some(Motion::LineEnd),
+            "g_" => Some(Motion::LastNonBlank),
+            "_" => Some(Motion::FirstNonBlank),
+            "+" | "\r" => Some(Motion::NextLineStart),
+            "-" => Some(Motion::PrevLineStart),
+            "gm" => Some(Motion::ScreenMiddleColumn),
+            "gM" => Some(Motion::LineMiddle),
             "b" => Some(Motion::PrevWordStart),
             "e" => Some(Motion::WordEnd),
             "w" => Some(Motion::StartWord),
+            "ge" => Some(Motion::PrevWordEnd),
+            "B" => Some(Motion::PrevBigWordStart),
+            "E" => Some(Motion::BigWordEnd),
+            "W" => Some(Motion::StartBigWord),
+            "gE" => Some(Motion::PrevBigWordEnd),
+            ";" => Some(Motion::RepeatFind),
+            "," => Some(Motion::RepeatFindReverse),
+            "gg" => Some(Motion::FirstLine),
+            "G" => Some(Motion::LastLine),
+            "%" => Some(Motion::Percent),
+            "}" => Some(Motion::NextParagraph),
+            "{" => Some(Motion::PrevParagraph),
+            ")" => Some(Motion::NextSentence),
+            "(" => Some(Motion::PrevSentence),
+            "H" => Some(Motion::ScreenTop),
+            "M" => Some(Motion::ScreenMiddle),
+            "L" => Some(Motion::ScreenBottom),
+            "n" => Some(Motion::SearchNext),
+            "N" => Some(Motion::SearchPrev),
             keys if keys.len() >= 2 => Self::two_char_motions(keys),
             _ => None,
         }
@@ -43,28 +176,419 @@ impl Motion {
         match (chars[0], chars[1]) {
             ('f', char) => Some(Motion::Find { char }),
             ('F', char) => Some(Motion::FindPrev { char }),
+            ('t', char) => Some(Motion::Till { char }),
+            ('T', char) => Some(Motion::TillPrev { char }),
             _ => None,
         }
     }
 
     pub fn execute(self, buf: &mut Buffer) -> Position {
         match self {
-            Motion::Left => left(buf),
+            Motion::Left => left_wrap(buf, 'h'),
+            Motion::Backspace => left_wrap(buf, 'b'),
+            Motion::ArrowLeft => left_wrap(buf, '<'),
             Motion::Down => down(buf),
             Motion::Up => up(buf),
-            Motion::Right => right(buf),
-            Motion::LineStart => start_line(buf),
-            Motion::LineEnd => end_line(buf),
-            Motion::PrevWordStart => prev_word_start(buf),
-            Motion::WordEnd => word_end(buf),
-            Motion::StartWord => word_start(buf),
-            Motion::Find { char } => find_char(buf, char),
-            Motion::FindPrev { char } => find_prev_char(buf, char),
+            Motion::Right => right_wrap(buf, 'l'),
+            Motion::Space => right_wrap(buf, 's'),
+            Motion::ArrowRight => right_wrap(buf, '>'),
+            Motion::FirstColumn => start_line(buf),
+            Motion::LineStart => line_start(buf, buf.cursor.row),
+            Motion::ScreenMiddleColumn => screen_middle(buf),
+            Motion::PrevWordStart => prev_word_start(buf, false),
+            Motion::WordEnd => word_end(buf, false),
+            Motion::StartWord => word_start(buf, false, false),
+            Motion::PrevWordEnd => prev_word_end(buf, false),
+            Motion::PrevBigWordStart => prev_word_start(buf, true),
+            Motion::StartBigWord => word_start(buf, true, false),
+            Motion::BigWordEnd => word_end(buf, true),
+            Motion::PrevBigWordEnd => prev_word_end(buf, true),
+            Motion::NextParagraph => next_paragraph(buf),
+            Motion::PrevParagraph => prev_paragraph(buf),
+            Motion::NextSentence => next_sentence(buf),
+            Motion::PrevSentence => prev_sentence(buf),
+            _ => self.target(buf, Some(1)).unwrap_or(buf.cursor),
+        }
+    }
+
+    /// Executes the motion `count` times and returns the resulting position without moving the
+    /// cursor, clamped to where the cursor can be
+    pub fn apply(self, buf: &mut Buffer, count: Option<usize>) -> Position {
+        let mut pos = self.target(buf, count).unwrap_or(buf.cursor);
+        pos.col = clamp_col(buf, pos.row, pos.col);
+        pos
+    }
+
+    /// Like `apply`, but returns `None` when the motion fails, e.g. a character find without a
+    /// match, so that an operator can be cancelled. The position may be just past the end of a
+    /// line, as operators need it
+    pub fn target(self, buf: &mut Buffer, count: Option<usize>) -> Option<Position> {
+        self.target_for(buf, count, false)
+    }
+
+    /// Finds the target, treating a last `w` that ends on another line like Vim does for an
+    /// operator, stopping at the end of the line instead. Only moving left, right, up and down
+    /// starts from a virtual column past the end of the line
+    fn target_for(
+        self,
+        buf: &mut Buffer,
+        count: Option<usize>,
+        operator: bool,
+    ) -> Option<Position> {
+        let cursor = buf.cursor;
+        if !matches!(
+            self,
+            Motion::Left
+                | Motion::Right
+                | Motion::Backspace
+                | Motion::Space
+                | Motion::ArrowLeft
+                | Motion::ArrowRight
+                | Motion::Up
+                | Motion::Down
+        ) {
+            buf.cursor.col = min(cursor.col, buf.row(cursor.row).len());
+        }
+
+        let target = self.target_from(buf, count, operator);
+        buf.cursor = cursor;
+        target
+    }
+
+    fn target_from(
+        self,
+        buf: &mut Buffer,
+        count: Option<usize>,
+        operator: bool,
+    ) -> Option<Position> {
+        let repeat = count.unwrap_or(1);
+
+        match self {
+            Motion::Find { .. }
+            | Motion::FindPrev { .. }
+            | Motion::Till { .. }
+            | Motion::TillPrev { .. } => {
+                buf.last_find = Some(self);
+                self.find(buf, repeat, false)
+            }
+            Motion::RepeatFind => buf.last_find?.find(buf, repeat, true),
+            Motion::RepeatFindReverse => buf.last_find?.reversed().find(buf, repeat, true),
+            Motion::Column => Some(goto_column(buf, repeat)),
+            Motion::LineEnd | Motion::LastNonBlank | Motion::FirstNonBlank => {
+                let row = min(buf.cursor.row + repeat - 1, buf.content.len() - 1);
+                Some(match self {
+                    Motion::LineEnd => end_line(buf, row),
+                    Motion::LastNonBlank => last_non_blank(buf, row),
+                    _ => line_start(buf, row),
+                })
+            }
+            Motion::NextLineStart => line_start_by(buf, true, repeat),
+            Motion::PrevLineStart => line_start_by(buf, false, repeat),
+            Motion::LineMiddle => Some(line_middle(buf, count.unwrap_or(50))),
+            Motion::FirstLine => Some(goto_line(buf, repeat)),
+            Motion::LastLine => Some(goto_line(buf, count.unwrap_or(buf.content.len()))),
+            Motion::Percent => match count {
+                Some(percent) if percent <= 100 => {
+                    Some(goto_line(buf, (percent * buf.content.len()).div_ceil(100)))
+                }
+                Some(_) => None,
+                None => match_pair(buf),
+            },
+            Motion::ScreenTop => Some(screen_line(buf, ViewAt::Top, repeat)),
+            Motion::ScreenMiddle => Some(screen_line(buf, ViewAt::Center, repeat)),
+            Motion::ScreenBottom => Some(screen_line(buf, ViewAt::Bottom, repeat)),
+            Motion::SearchNext => search_target(buf, false, repeat),
+            Motion::SearchPrev => search_target(buf, true, repeat),
+            _ => {
+                let cursor = buf.cursor;
+
+                let mut step = 1;
+                while step <= repeat {
+                    let next = match self {
+                        Motion::StartWord | Motion::StartBigWord if operator && step == repeat => {
+                            word_start(buf, self == Motion::StartBigWord, true)
+                        }
+                        // An operator can move right onto the end of the line, but not across it
+                        Motion::Right | Motion::Space | Motion::ArrowRight if operator => {
+                            let line = buf.row(buf.cursor.row);
+                            let col = buf.cursor.col
+                                + line
+                                    .get(buf.cursor.col..)
+                                    .and_then(|rest| rest.chars().next())
+                                    .map_or(0, char::len_utf8);
+                            Position { col, ..buf.cursor }
+                        }
+                        _ => self.execute(buf),
+                    };
+
+                    // A motion that stopped moving won't move again, so a huge count skips
+                    // ahead to its last step
+                    if next == buf.cursor && step < repeat {
+                        step = repeat;
+                        continue;
+                    }
+                    buf.cursor = next;
+                    if step == repeat {
+                        break;
+                    }
+                    step += 1;
+                }
+
+                Some(std::mem::replace(&mut buf.cursor, cursor))
+            }
+        }
+    }
+
+    /// Finds the `count`th occurrence for a character find. When repeating with `;` a till
+    /// skips the character next to the cursor, so it doesn't get stuck in front of it
+    fn find(self, buf: &Buffer, count: usize, repeat: bool) -> Option<Position> {
+        match self {
+            Motion::Find { char } => find_char(buf, char, count),
+            Motion::FindPrev { char } => find_prev_char(buf, char, count),
+            Motion::Till { char } => till_char(buf, char, count, repeat),
+            Motion::TillPrev { char } => till_prev_char(buf, char, count, repeat),
+            _ => None,
         }
     }
 
-    pub fn inclusive(&self) -> bool {
-        matches!(self, Motion::PrevWordStart | Motion::StartWord)
+    /// The character find going in the opposite direction, used by `,`
+    fn reversed(self) -> Self {
+        match self {
+            Motion::Find { char } => Motion::FindPrev { char },
+            Motion::FindPrev { char } => Motion::Find { char },
+            Motion::Till { char } => Motion::TillPrev { char },
+            Motion::TillPrev { char } => Motion::Till { char },
+            motion => motion,
+        }
+    }
+
+    /// The text an operator acts on when used with this motion, or `None` if the motion failed
+    pub fn region(
+        self,
+        buf: &mut Buffer,
+        count: Option<usize>,
+        force: Option<Force>,
+    ) -> Option<Region> {
+        let mut end = self.target_for(buf, count, true)?;
+        let kind = self.kind(buf, count);
+
+        // With `virtualedit=block` a blockwise region keeps the desired column past short lines
+        let desired = desired_col(buf);
+        if force == Some(Force::Block)
+            && (buf.options.virtual_edit("block") || buf.options.virtual_edit("all"))
+            && self.keeps_column(buf, count)
+            && desired != usize::MAX
+        {
+            end.col = virtual_col(buf.row(end.row), desired);
+        }
+
+        Some(region_to(buf, end, kind, force))
+    }
+
+    /// Like `region`, but `cw` and `cW` on a word change to its end like `ce`, leaving the blanks
+    /// after it
+    pub fn change_region(
+        self,
+        buf: &mut Buffer,
+        count: Option<usize>,
+        force: Option<Force>,
+    ) -> Option<Region> {
+        let on_blank = buf.row(buf.cursor.row)[buf.cursor.col..]
+            .chars()
+            .next()
+            .is_none_or(char::is_whitespace);
+        if !matches!(self, Motion::StartWord | Motion::StartBigWord) || on_blank {
+            return self.region(buf, count, force);
+        }
+
+        let big = self == Motion::StartBigWord;
+        let cursor = buf.cursor;
+        let mut steps = count.unwrap_or(1);
+        if at_word_end(buf, big) {
+            steps -= 1;
+        }
+        for _ in 0..steps {
+            let next = word_end(buf, big);
+            if next == buf.cursor {
+                break;
+            }
+            buf.cursor = next;
+        }
+
+        let end = std::mem::replace(&mut buf.cursor, cursor);
+        Some(region_to(buf, end, MotionKind::Inclusive, force))
+    }
+
+    /// Whether the motion moves between lines keeping the desired column, which depends on
+    /// `startofline` for jumps to other lines
+    pub fn keeps_column(&self, buf: &Buffer, count: Option<usize>) -> bool {
+        match self {
+            Motion::Up | Motion::Down => true,
+            Motion::Percent => count.is_some() && !buf.options.startofline(),
+            Motion::FirstLine
+            | Motion::LastLine
+            | Motion::ScreenTop
+            | Motion::ScreenMiddle
+            | Motion::ScreenBottom => !buf.options.startofline(),
+            _ => false,
+        }
+    }
+
+    /// How an operator treats the text the motion moves over
+    pub fn kind(&self, buf: &Buffer, count: Option<usize>) -> MotionKind {
+        match self {
+            Motion::Up
+            | Motion::Down
+            | Motion::FirstLine
+            | Motion::LastLine
+            | Motion::ScreenTop
+            | Motion::ScreenMiddle
+            | Motion::ScreenBottom
+            | Motion::FirstNonBlank
+            | Motion::NextLineStart
+            | Motion::PrevLineStart => MotionKind::Linewise,
+            Motion::Percent if count.is_some() => MotionKind::Linewise,
+            Motion::LineEnd
+            | Motion::LastNonBlank
+            | Motion::WordEnd
+            | Motion::PrevWordEnd
+            | Motion::BigWordEnd
+            | Motion::PrevBigWordEnd
+            | Motion::Find { .. }
+            | Motion::Till { .. }
+            | Motion::Percent => MotionKind::Inclusive,
+            Motion::RepeatFind => buf
+                .last_find
+                .map_or(MotionKind::Exclusive, |find| find.kind(buf, count)),
+            Motion::RepeatFindReverse => buf.last_find.map_or(MotionKind::Exclusive, |find| {
+                find.reversed().kind(buf, count)
+            }),
+            // A search is inclusive with an end offset and linewise with a line offset
+            Motion::SearchNext | Motion::SearchPrev => {
+                match buf.last_search.as_ref().map(|search| search.offset) {
+                    Some(Offset::End(_)) => MotionKind::Inclusive,
+                    Some(Offset::Lines(_)) => MotionKind::Linewise,
+                    _ => MotionKind::Exclusive,
+                }
+            }
+            _ => MotionKind::Exclusive,
+        }
+    }
+}
+
+/// The region from the cursor to `end` for a motion of the given kind, changed by a `v`, `V` or
+/// `Ctrl-v` typed after the operator
+fn region_to(buf: &Buffer, mut end: Position, kind: MotionKind, force: Option<Force>) -> Region {
+    let from = buf.cursor;
+    let mut kind = match (force, kind) {
+        (Some(Force::Lines), _) => MotionKind::Linewise,
+        (Some(Force::Block), _) => return block_region(buf, from, end),
+        (Some(Force::Chars), MotionKind::Exclusive) => MotionKind::Inclusive,
+        (Some(Force::Chars), _) => MotionKind::Exclusive,
+        (None, kind) => kind,
+    };
+
+    // An exclusive motion ending at the start of a later line stops at the end of the line
+    // before it, and covers whole lines if it started before the first non-blank
+    if kind == MotionKind::Exclusive && end.row > from.row && end.col == 0 {
+        end.row -= 1;
+        end.col = buf.row(end.row).len();
+        if from.col <= indent_of(buf.row(from.row)).len() {
+            kind = MotionKind::Linewise;
+        }
+    }
+
+    if kind == MotionKind::Linewise {
+        return Region::Lines {
+            start: min(from.row, end.row),
+            end: max(from.row, end.row),
+        };
+    }
+
+    let (start, mut end) = match (from.row, from.col) <= (end.row, end.col) {
+        true => (from, end),
+        false => (end, from),
+    };
+    if kind == MotionKind::Inclusive {
+        end.col += buf.row(end.row)[end.col..]
+            .chars()
+            .next()
+            .map_or(0, char::len_utf8);
+    }
+
+    Region::Chars { start, end }
+}
+
+/// The rectangle with corners at `from` and `to`, its columns counted in characters
+fn block_region(buf: &Buffer, from: Position, to: Position) -> Region {
+    let char_col = |pos: Position| {
+        let line = buf.row(pos.row);
+        line[..min(pos.col, line.len())].chars().count() + pos.col.saturating_sub(line.len())
+    };
+    let (left, right) = (char_col(from), char_col(to));
+
+    Region::Block {
+        start: min(from.row, to.row),
+        end: max(from.row, to.row),
+        cols: min(left, right)..max(left, right) + 1,
+    }
+}
+
+impl Region {
+    /// The byte range covered on each line, leaving out the line breaks
+    pub fn ranges(&self, buf: &Buffer) -> Vec<(usize, Range<usize>)> {
+        let (start, end) = match self {
+            Region::Chars { start, end } => (start.row, end.row),
+            Region::Lines { start, end } | Region::Block { start, end, .. } => (*start, *end),
+        };
+
+        (start..=end)
+            .map(|row| {
+                let line = buf.row(row);
+                let range = match self {
+                    Region::Chars { start, end } => {
+                        let from = if row == start.row { start.col } else { 0 };
+                        let to = if row == end.row { end.col } else { line.len() };
+                        from..to
+                    }
+                    Region::Lines { .. } => 0..line.len(),
+                    Region::Block { cols, .. } => {
+                        let byte = |col: usize| {
+                            line.char_indices()
+                                .nth(col)
+                                .map_or(line.len(), |(idx, _)| idx)
+                        };
+                        byte(cols.start)..byte(cols.end)
+                    }
+                };
+                (row, range)
+            })
+            .collect()
+    }
+
+    /// The text of the region as it is stored in a register, linewise text surrounded by line
+    /// breaks
+    pub fn text(&self, buf: &Buffer) -> String {
+        let text = self
+            .ranges(buf)
+            .into_iter()
+            .map(|(row, range)| &buf.row(row)[range])
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        match self {
+            Region::Lines { .. } => format!("\n{}\n", text),
+            _ => text,
+        }
+    }
+
+    /// The position at the start of the region
+    pub fn start(&self, buf: &Buffer) -> Position {
+        let (row, range) = self.ranges(buf).swap_remove(0);
+        Position {
+            row,
+            col: range.start,
+        }
     }
 }
 
@@ -75,6 +599,98 @@ mod tests {
     #[test]
     fn create_multi_char_motions() {
         let motion = Motion::new("fa");
-        assert_eq!(motion, Some(Motion::Find { char: 'a' }))
+        assert_eq!(motion, Some(Motion::Find { char: 'a' }));
+        assert_eq!(Motion::new("T;"), Some(Motion::TillPrev { char: ';' }));
+        assert_eq!(Motion::new("gg"), Some(Motion::FirstLine));
+    }
+
+    #[test]
+    fn moves_to_lines() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.cursor = Position { row: 1, col: 5 };
+
+        assert_eq!(
+            Motion::FirstLine.apply(&mut buf, None),
+            Position { row: 0, col: 0 }
+        );
+        assert_eq!(
+            Motion::LastLine.apply(&mut buf, None),
+            Position { row: 6, col: 0 }
+        );
+        assert_eq!(
+            Motion::LastLine.apply(&mut buf, Some(3)),
+            Position { row: 2, col: 0 }
+        );
+        assert_eq!(
+            Motion::Percent.apply(&mut buf, Some(50)),
+            Position { row: 3, col: 0 }
+        );
+        assert_eq!(Motion::Percent.target(&mut buf, Some(101)), None);
+
+        buf.options.set("nostartofline").unwrap();
+        assert_eq!(
+            Motion::FirstLine.apply(&mut buf, Some(99)),
+            Position { row: 6, col: 4 }
+        );
+    }
+
+    #[test]
+    fn repeats_finds() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.cursor.row = 2;
+
+        buf.cursor = Motion::Till { char: 'u' }.apply(&mut buf, Some(2));
+        assert_eq!(buf.cursor.col, 6);
+
+        buf.cursor = Motion::RepeatFind.apply(&mut buf, None);
+        assert_eq!(buf.cursor.col, 10);
+
+        buf.cursor = Motion::RepeatFindReverse.apply(&mut buf, Some(2));
+        assert_eq!(buf.cursor.col, 6);
+        assert_eq!(buf.last_find, Some(Motion::Till { char: 'u' }));
+
+        assert_eq!(Motion::Find { char: 'u' }.target(&mut buf, Some(9)), None);
+        assert_eq!(buf.cursor.col, 6);
+    }
+
+    #[test]
+    fn moves_within_and_between_lines() {
+        let mut buf = Buffer::test(String::new());
+        buf.content = vec![
+            String::from("  indented line "),
+            String::from("second"),
+            String::from("  third"),
+        ];
+        buf.view.width = 10;
+
+        buf.input_keys("$^");
+        assert_eq!(buf.cursor.col, 2);
+        buf.input_keys("0");
+        assert_eq!(buf.cursor.col, 0);
+        buf.input_keys("5|");
+        assert_eq!(buf.cursor.col, 4);
+        buf.input_keys("g_");
+        assert_eq!(buf.cursor.col, 14);
+        buf.input_keys("gm");
+        assert_eq!(buf.cursor.col, 5);
+        buf.input_keys("gM");
+        assert_eq!(buf.cursor.col, 8);
+
+        buf.input_keys("2+");
+        assert_eq!(buf.cursor, Position { row: 2, col: 2 });
+        buf.input_keys("-");
+        assert_eq!(buf.cursor, Position { row: 1, col: 0 });
+        buf.input_keys("\r");
+        assert_eq!(buf.cursor, Position { row: 2, col: 2 });
+        assert_eq!(Motion::NextLineStart.target(&mut buf, None), None);
+
+        buf.input_keys("k2_");
+        assert_eq!(buf.cursor, Position { row: 2, col: 2 });
+
+        buf.input_keys("ggd2$");
+        assert_eq!(buf.content, vec!["  ", "  third"]);
+
+        buf.input_keys("d_");
+        assert_eq!(buf.content, vec!["  third"]);
     }
 }
@@ -1,66 +1,112 @@
-use std::cmp::{max, min};
+use std::{cmp::min, ops::Range};
 
 use crate::{
     buffer::{Buffer, Position},
     mode::Mode,
+    syntax::Syntax,
+    view::{col_at_display, col_on_row},
 };
 
 pub fn right(buf: &Buffer) -> Position {
-    let mut row_len = buf.row(buf.cursor.row).len();
-    if let Mode::Normal = buf.mode {
-        row_len -= 1;
-    }
+    let row = buf.row(buf.cursor.row);
+    let step = row
+        .get(buf.cursor.col..)
+        .and_then(|rest| rest.chars().next())
+        .map_or(1, char::len_utf8);
 
-    if buf.cursor.col >= row_len {
-        buf.cursor
-    } else {
-        Position {
-            row: buf.cursor.row,
-            col: buf.cursor.col + 1,
-        }
+    Position {
+        row: buf.cursor.row,
+        col: clamp_col(buf, buf.cursor.row, buf.cursor.col + step),
     }
 }
 
 pub fn left(buf: &Buffer) -> Position {
-    if buf.cursor.col == 0 {
-        buf.cursor
-    } else {
-        Position {
+    let row = buf.row(buf.cursor.row);
+    if buf.cursor.col > row.len() {
+        return Position {
             row: buf.cursor.row,
             col: buf.cursor.col - 1,
-        }
+        };
+    }
+
+    match row
+        .get(..buf.cursor.col)
+        .and_then(|before| before.chars().next_back())
+    {
+        Some(char) => Position {
+            row: buf.cursor.row,
+            col: buf.cursor.col - char.len_utf8(),
+        },
+        None => buf.cursor,
     }
 }
 
+/// Moves right, or to the start of the next line at the end of one when `whichwrap` has `flag`
+pub fn right_wrap(buf: &Buffer, flag: char) -> Position {
+    let pos = right(buf);
+    if pos == buf.cursor && buf.cursor.row + 1 < buf.content.len() && buf.options.wraps(flag) {
+        return Position {
+            row: buf.cursor.row + 1,
+            col: 0,
+        };
+    }
+
+    pos
+}
+
+/// Moves left, or to the end of the previous line at the start of one when `whichwrap` has
+/// `flag`
+pub fn left_wrap(buf: &Buffer, flag: char) -> Position {
+    let pos = left(buf);
+    if pos == buf.cursor && buf.cursor.row > 0 && buf.options.wraps(flag) {
+        let row = buf.cursor.row - 1;
+        return Position {
+            row,
+            col: clamp_col(buf, row, buf.row(row).len()),
+        };
+    }
+
+    pos
+}
+
+/// The column closest to `col` that the cursor can be on in the row. Normal mode keeps the
+/// cursor on a character, unless `virtualedit` has `onemore` to allow the end of the line or
+/// `all` to allow any column
+pub fn clamp_col(buf: &Buffer, row: usize, col: usize) -> usize {
+    let line = buf.row(row);
+    if buf.options.virtual_edit("all") {
+        col
+    } else if buf.mode == Mode::Insert || buf.options.virtual_edit("onemore") {
+        min(col, line.len())
+    } else {
+        min(
+            col,
+            line.len() - line.chars().next_back().map_or(0, char::len_utf8),
+        )
+    }
+}
+
+/// Moves up a line, to the column closest to the desired one
 pub fn up(buf: &Buffer) -> Position {
     if buf.cursor.row == 0 {
         return buf.cursor;
     }
 
-    let prev_row = buf
-        .content
-        .get(buf.cursor.row - 1)
-        .unwrap_or_else(|| panic!("row: {} doesn't exist", buf.cursor.row));
-
     Position {
         row: buf.cursor.row - 1,
-        col: min(buf.cursor.col, max(1, prev_row.len()) - 1),
+        col: col_on_row(buf, buf.cursor.row - 1),
     }
 }
 
+/// Moves down a line, to the column closest to the desired one
 pub fn down(buf: &Buffer) -> Position {
     if buf.cursor.row >= buf.content.len() - 1 {
         return buf.cursor;
     }
 
-    let next_row = buf
-        .content
-        .get(buf.cursor.row + 1)
-        .unwrap_or_else(|| panic!("row: {}, doesn't exist", buf.cursor.row));
-
     Position {
         row: buf.cursor.row + 1,
-        col: min(buf.cursor.col, max(next_row.len(), 1) - 1),
+        col: col_on_row(buf, buf.cursor.row + 1),
     }
 }
 
@@ -71,226 +117,576 @@ pub fn start_line(buf: &Buffer) -> Position {
     }
 }
 
-pub fn end_line(buf: &Buffer) -> Position {
-    if let Some(row) = buf.content.get(buf.cursor.row) {
-        Position {
-            row: buf.cursor.row,
-            col: max(1, row.len()) - 1,
-        }
-    } else {
-        buf.cursor
+/// The first non-blank character of the row, as `^`, `_`, `+` and `-` go to
+pub fn line_start(buf: &Buffer, row: usize) -> Position {
+    Position {
+        row,
+        col: first_not_whitespace(buf.row(row)),
     }
 }
 
-const WORD_DELIMITERS: [char; 11] = ['(', ')', '[', ']', '{', '}', '$', '^', '!', '.', ','];
+/// The last non-blank character of the row, as `g_` goes to
+pub fn last_non_blank(buf: &Buffer, row: usize) -> Position {
+    Position {
+        row,
+        col: last_not_whitespace(buf.row(row)),
+    }
+}
 
-#[allow(clippy::iter_skip_zero)]
-pub fn word_end(buf: &Buffer) -> Position {
-    let mut prev = 'a';
-    let row_iterator = buf.content.iter().enumerate().skip(buf.cursor.row);
+/// The first non-blank `count` lines below the cursor, or above it when `down` isn't set. Fails
+/// when the cursor is already on the last or first line
+pub fn line_start_by(buf: &Buffer, down: bool, count: usize) -> Option<Position> {
+    let row = buf.cursor.row;
+    let row = match down {
+        true if row + 1 < buf.content.len() => min(row + count, buf.content.len() - 1),
+        false if row > 0 => row.saturating_sub(count),
+        _ => return None,
+    };
+
+    Some(line_start(buf, row))
+}
 
-    for (row, line) in row_iterator {
-        let iterator = if buf.cursor.row == row {
-            let mut iterator = line.chars().enumerate().skip(buf.cursor.col + 1);
+/// The character at display column `number`, counted from 1, as `|` goes to
+pub fn goto_column(buf: &Buffer, number: usize) -> Position {
+    Position {
+        row: buf.cursor.row,
+        col: col_at_display(buf.row(buf.cursor.row), number.saturating_sub(1)),
+    }
+}
 
-            if let Some((_, char)) = iterator.next() {
-                prev = char;
-            }
+/// The character `percent` of the way into the line, as `gM` goes to
+pub fn line_middle(buf: &Buffer, percent: usize) -> Position {
+    let line = buf.row(buf.cursor.row);
+    let chars = line.chars().count() * min(percent, 100) / 100;
+    Position {
+        row: buf.cursor.row,
+        col: line
+            .char_indices()
+            .nth(chars)
+            .map_or(line.len(), |(idx, _)| idx),
+    }
+}
 
-            iterator
-        } else {
-            line.chars().enumerate().skip(0)
-        };
+/// The last character of the row, as `$` goes to
+pub fn end_line(buf: &Buffer, row: usize) -> Position {
+    Position {
+        row,
+        col: last_char(buf.row(row)),
+    }
+}
 
-        for (idx, char) in iterator {
-            if (buf.cursor.row != row || buf.cursor.col != max(idx, 1) - 1)
-                && ((is_word_delimiter(char) && !is_word_delimiter(prev))
-                    || (is_word_delimiter(prev) && !is_word_delimiter(char) && char != ' ')
-                    || (char == ' ' && prev != ' '))
-            {
-                return Position {
-                    row,
-                    col: max(idx, 1) - 1,
-                };
-            }
+/// The position on line `number`, counted from 1 and clamped to the buffer. Goes to the first
+/// non-blank with `startofline`, otherwise to the desired column
+pub fn goto_line(buf: &Buffer, number: usize) -> Position {
+    let row = number.clamp(1, buf.content.len()) - 1;
+    let col = if buf.options.startofline() {
+        first_not_whitespace(buf.row(row))
+    } else {
+        col_on_row(buf, row)
+    };
 
-            prev = char;
-        }
+    Position { row, col }
+}
 
-        if buf.cursor.col != last_not_whitespace(line) && !line.is_empty() {
-            return Position {
-                row,
-                col: last_not_whitespace(line),
-            };
-        }
+/// Moves to the next empty line after a paragraph, or just past the end of the last line
+pub fn next_paragraph(buf: &Buffer) -> Position {
+    let last = buf.content.len() - 1;
+    let mut row = buf.cursor.row;
 
-        prev = ' ';
+    while row < last && buf.content[row].is_empty() {
+        row += 1;
+    }
+    while row < last && !buf.content[row].is_empty() {
+        row += 1;
     }
 
     Position {
-        row: buf.content.len() - 1,
-        col: last_not_whitespace(buf.content.last().unwrap()),
+        row,
+        col: buf.content[row].len(),
     }
 }
 
-#[allow(clippy::iter_skip_zero, non_snake_case)]
-pub fn prev_word_start(buf: &Buffer) -> Position {
-    let mut prev = 'a';
-    let row_iterator = buf
-        .content
-        .iter()
+/// Moves to the previous empty line before a paragraph, or the start of the first line
+pub fn prev_paragraph(buf: &Buffer) -> Position {
+    let mut row = buf.cursor.row;
+
+    while row > 0 && buf.content[row].is_empty() {
+        row -= 1;
+    }
+    while row > 0 && !buf.content[row].is_empty() {
+        row -= 1;
+    }
+
+    Position { row, col: 0 }
+}
+
+pub fn next_sentence(buf: &Buffer) -> Position {
+    match sentence_starts(buf)
+        .into_iter()
+        .find(|start| (start.row, start.col) > (buf.cursor.row, buf.cursor.col))
+    {
+        Some(start) => start,
+        None => {
+            let row = buf.content.len() - 1;
+            Position {
+                row,
+                col: buf.content[row].len(),
+            }
+        }
+    }
+}
+
+pub fn prev_sentence(buf: &Buffer) -> Position {
+    sentence_starts(buf)
+        .into_iter()
         .rev()
-        .enumerate()
-        .skip(buf.content.len() - 1 - buf.cursor.row)
-        .map(|value| (buf.content.len() - 1 - value.0, value.1));
+        .find(|start| (start.row, start.col) < (buf.cursor.row, buf.cursor.col))
+        .unwrap_or_default()
+}
 
-    for (row, line) in row_iterator {
-        let iterator = if buf.cursor.row == row {
-            line.chars()
-                .rev()
-                .enumerate()
-                .skip(line.len() - buf.cursor.col)
-        } else {
-            line.chars().rev().enumerate().skip(0)
+/// The positions where sentences start. A sentence ends with a character from `sentenceend`,
+/// optionally followed by closing brackets or quotes, and then the end of the line or at least
+/// `sentencespaces` spaces. Empty lines count as sentences of their own
+fn sentence_starts(buf: &Buffer) -> Vec<Position> {
+    let mut starts = Vec::new();
+    let mut ended = true;
+    let (sentence_end, spaces_after) = (buf.options.sentenceend(), buf.options.sentencespaces());
+
+    for (row, line) in buf.content.iter().enumerate() {
+        if line.is_empty() {
+            if row == 0 || !buf.content[row - 1].is_empty() {
+                starts.push(Position { row, col: 0 });
+            }
+            ended = true;
+            continue;
         }
-        .map(|value| (line.len() - 1 - value.0, value.1));
-
-        for (idx, char) in iterator {
-            if (buf.cursor.row != row || buf.cursor.col != idx + 1)
-                && ((is_word_delimiter(char) && !is_word_delimiter(prev) && prev != ' ')
-                    || (!is_word_delimiter(char) && is_word_delimiter(prev))
-                    || (char == ' ' && prev != ' '))
-            {
-                return Position { row, col: idx + 1 };
+
+        let chars: Vec<(usize, char)> = line.char_indices().collect();
+        let mut idx = 0;
+        while idx < chars.len() {
+            let (col, char) = chars[idx];
+            idx += 1;
+            if char.is_whitespace() {
+                continue;
             }
 
-            prev = char;
+            if ended {
+                starts.push(Position { row, col });
+                ended = false;
+            }
+
+            if sentence_end.contains(char) {
+                while idx < chars.len() && SENTENCE_CLOSE.contains(&chars[idx].1) {
+                    idx += 1;
+                }
+
+                let spaces = chars[idx..]
+                    .iter()
+                    .take_while(|(_, char)| *char == ' ' || *char == '\t')
+                    .count();
+                ended = idx == chars.len() || spaces >= spaces_after;
+            }
         }
+    }
 
-        if buf.cursor.col != first_not_whitespace(line) {
-            return Position {
-                row,
-                col: first_not_whitespace(line),
+    starts
+}
+
+const SENTENCE_CLOSE: [char; 4] = [')', ']', '"', '\''];
+
+/// Finds the bracket matching the first bracket at or after the cursor on its line
+pub fn match_pair(buf: &Buffer) -> Option<Position> {
+    let line = buf.row(buf.cursor.row);
+    let col = line[buf.cursor.col..]
+        .char_indices()
+        .find(|(_, char)| bracket_pair(buf, *char).is_some())
+        .map(|(idx, _)| buf.cursor.col + idx)?;
+
+    matching_bracket(buf, Position { col, ..buf.cursor })
+}
+
+/// Finds the bracket matching the one at `pos` across lines. Brackets in strings and comments
+/// are skipped when the file type is known, unless the bracket at `pos` is in one itself
+pub fn matching_bracket(buf: &Buffer, pos: Position) -> Option<Position> {
+    let char = buf.row(pos.row)[pos.col..].chars().next()?;
+    let (open, close, forward) = bracket_pair(buf, char)?;
+    let syntax = Syntax::for_file(&buf.filename);
+    let skipped = |row: usize| {
+        syntax.map_or(Vec::new(), |syntax| {
+            syntax.strings_and_comments(buf.row(row))
+        })
+    };
+    let in_skipped =
+        |ranges: &[Range<usize>], col: usize| ranges.iter().any(|range| range.contains(&col));
+    let skip = !in_skipped(&skipped(pos.row), pos.col);
+
+    let mut depth = 0;
+    let rows: Box<dyn Iterator<Item = usize>> = match forward {
+        true => Box::new(pos.row..buf.content.len()),
+        false => Box::new((0..=pos.row).rev()),
+    };
+
+    for row in rows {
+        let line = buf.row(row);
+        let ranges = if skip { skipped(row) } else { Vec::new() };
+        let chars: Box<dyn Iterator<Item = (usize, char)>> = match forward {
+            true => Box::new(line.char_indices()),
+            false => Box::new(line.char_indices().rev()),
+        };
+
+        for (col, char) in chars {
+            let passed = match forward {
+                true => col <= pos.col,
+                false => col >= pos.col,
             };
-        } else if line.is_empty() && buf.cursor.row != row {
-            return Position { row, col: 0 };
-        }
+            if (row == pos.row && passed) || in_skipped(&ranges, col) {
+                continue;
+            }
 
-        prev = ' ';
+            if char == open {
+                depth += 1;
+            } else if char == close {
+                if depth == 0 {
+                    return Some(Position { row, col });
+                }
+                depth -= 1;
+            }
+        }
     }
 
-    Position {
-        row: 0,
-        col: first_not_whitespace(&buf.content[0]),
-    }
+    None
+}
+
+/// The pair a bracket belongs to from `matchpairs`, as the bracket itself, the one to look for
+/// and whether to search forward
+fn bracket_pair(buf: &Buffer, char: char) -> Option<(char, char, bool)> {
+    buf.options
+        .matchpairs()
+        .iter()
+        .find_map(|&(open, close)| match char {
+            _ if char == open => Some((open, close, true)),
+            _ if char == close => Some((close, open, false)),
+            _ => None,
+        })
 }
 
-#[allow(clippy::iter_skip_zero)]
-pub fn word_start(buf: &Buffer) -> Position {
-    let mut prev = 'a';
-    let row_iterator = buf.content.iter().enumerate().skip(buf.cursor.row);
+/// Moves to the start of the next word, or of the next WORD when `big` is set. With
+/// `stop_at_eol` it stops at the end of the line instead, as an operator does for its last word
+pub fn word_start(buf: &Buffer, big: bool, stop_at_eol: bool) -> Position {
+    let mut pos = buf.cursor;
+    let class = char_class(buf, pos, big);
 
-    for (row, line) in row_iterator {
-        let iterator = if buf.cursor.row == row {
-            let mut iterator = line.chars().enumerate().skip(buf.cursor.col);
+    let mut step = inc(buf, &mut pos);
+    if step == Step::Stuck || (step == Step::NextLine && stop_at_eol) {
+        return pos;
+    }
 
-            if let Some((_, char)) = iterator.next() {
-                prev = char;
+    if class != CharClass::Blank {
+        while char_class(buf, pos, big) == class {
+            step = inc(buf, &mut pos);
+            if step == Step::Stuck || (step == Step::NextLine && stop_at_eol) {
+                return pos;
             }
+        }
+    }
 
-            iterator
-        } else {
-            line.chars().enumerate().skip(0)
-        };
+    while char_class(buf, pos, big) == CharClass::Blank && !is_empty_line(buf, pos) {
+        step = inc(buf, &mut pos);
+        if step == Step::Stuck || (step == Step::NextLine && stop_at_eol) {
+            return pos;
+        }
+    }
 
-        for (idx, char) in iterator {
-            if (buf.cursor.row != row || buf.cursor.col != idx)
-                && ((is_word_delimiter(char) && !is_word_delimiter(prev))
-                    || (is_word_delimiter(prev) && !is_word_delimiter(char) && char != ' ')
-                    || (char != ' ' && prev == ' '))
-            {
-                return Position { row, col: idx };
-            }
+    pos
+}
 
-            prev = char;
+/// Moves to the start of the previous word or WORD, stopping at empty lines
+pub fn prev_word_start(buf: &Buffer, big: bool) -> Position {
+    let mut pos = buf.cursor;
+    if dec(buf, &mut pos) == Step::Stuck {
+        return pos;
+    }
+
+    while char_class(buf, pos, big) == CharClass::Blank {
+        if is_empty_line(buf, pos) {
+            return pos;
         }
+        if dec(buf, &mut pos) == Step::Stuck {
+            return pos;
+        }
+    }
 
-        if line.is_empty() && buf.cursor.row != row {
-            return Position { row, col: 0 };
+    let class = char_class(buf, pos, big);
+    while char_class(buf, pos, big) == class {
+        if dec(buf, &mut pos) == Step::Stuck {
+            return pos;
         }
+    }
+
+    inc(buf, &mut pos);
+    pos
+}
 
-        prev = ' ';
+/// Moves to the end of the current or next word or WORD
+pub fn word_end(buf: &Buffer, big: bool) -> Position {
+    let mut pos = buf.cursor;
+    let class = char_class(buf, pos, big);
+    if inc(buf, &mut pos) == Step::Stuck {
+        return buf.cursor;
     }
 
-    Position {
-        row: buf.content.len() - 1,
-        col: last_not_whitespace(buf.content.last().unwrap()),
+    if char_class(buf, pos, big) != class || class == CharClass::Blank {
+        while char_class(buf, pos, big) == CharClass::Blank {
+            if inc(buf, &mut pos) == Step::Stuck {
+                return buf.cursor;
+            }
+        }
     }
+
+    let class = char_class(buf, pos, big);
+    while char_class(buf, pos, big) == class {
+        if inc(buf, &mut pos) == Step::Stuck {
+            return buf.cursor;
+        }
+    }
+
+    dec(buf, &mut pos);
+    pos
 }
 
-pub fn find_char(buf: &Buffer, search: char) -> Position {
-    let line = &buf.content[buf.cursor.row];
-    let iterator = line.chars().enumerate().skip(buf.cursor.col + 1);
+/// Moves to the end of the previous word or WORD, stopping at empty lines
+pub fn prev_word_end(buf: &Buffer, big: bool) -> Position {
+    let mut pos = buf.cursor;
+    let class = char_class(buf, pos, big);
+    if dec(buf, &mut pos) == Step::Stuck {
+        return pos;
+    }
 
-    for (idx, char) in iterator {
-        if char == search {
-            return Position {
-                row: buf.cursor.row,
-                col: idx,
-            };
+    if class != CharClass::Blank {
+        while char_class(buf, pos, big) == class {
+            if dec(buf, &mut pos) == Step::Stuck {
+                return pos;
+            }
+        }
+    }
+
+    while char_class(buf, pos, big) == CharClass::Blank && !is_empty_line(buf, pos) {
+        if dec(buf, &mut pos) == Step::Stuck {
+            return pos;
         }
     }
 
-    buf.cursor
+    pos
 }
 
-pub fn find_prev_char(buf: &Buffer, search: char) -> Position {
-    let line = &buf.content[buf.cursor.row];
-    let iterator = line
+/// Whether the cursor is on the last character of a word or WORD, where `cw` stops
+pub fn at_word_end(buf: &Buffer, big: bool) -> bool {
+    let class = char_class(buf, buf.cursor, big);
+    let len = buf.row(buf.cursor.row)[buf.cursor.col..]
         .chars()
-        .rev()
-        .enumerate()
-        .skip(line.len() - buf.cursor.col)
-        .map(|value| (line.len() - 1 - value.0, value.1));
-
-    for (idx, char) in iterator {
-        if char == search {
-            return Position {
-                row: buf.cursor.row,
-                col: idx,
-            };
-        }
+        .next()
+        .map_or(0, char::len_utf8);
+    let next = Position {
+        row: buf.cursor.row,
+        col: buf.cursor.col + len,
+    };
+
+    class != CharClass::Blank && char_class(buf, next, big) != class
+}
+
+/// The classes word motions use to find boundaries, a word is a run of the same class
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum CharClass {
+    Blank,
+    Punctuation,
+    Keyword,
+}
+
+/// The class of the character at `pos`, the end of a line counts as blank. For WORDs every
+/// character that isn't blank has the same class
+fn char_class(buf: &Buffer, pos: Position, big: bool) -> CharClass {
+    match buf.row(pos.row)[pos.col..].chars().next() {
+        None => CharClass::Blank,
+        Some(char) if char.is_whitespace() => CharClass::Blank,
+        Some(_) if big => CharClass::Punctuation,
+        Some(char) if buf.options.iskeyword().contains(char) => CharClass::Keyword,
+        Some(_) => CharClass::Punctuation,
     }
+}
 
-    buf.cursor
+fn is_empty_line(buf: &Buffer, pos: Position) -> bool {
+    buf.row(pos.row).is_empty()
 }
 
-fn is_word_delimiter(char: char) -> bool {
-    WORD_DELIMITERS.contains(&char)
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Step {
+    SameLine,
+    NextLine,
+    Stuck,
 }
 
-fn last_not_whitespace(line: &str) -> usize {
-    let mut iterator = line.chars().rev().enumerate().filter_map(|value| {
-        if value.1 != ' ' {
-            Some(line.len() - 1 - value.0)
-        } else {
-            None
-        }
-    });
+/// Moves forward by one character. The end of a line is a position of its own, just past the
+/// last character
+fn inc(buf: &Buffer, pos: &mut Position) -> Step {
+    let line = buf.row(pos.row);
+    if let Some(char) = line[pos.col..].chars().next() {
+        pos.col += char.len_utf8();
+        Step::SameLine
+    } else if pos.row + 1 < buf.content.len() {
+        *pos = Position {
+            row: pos.row + 1,
+            col: 0,
+        };
+        Step::NextLine
+    } else {
+        Step::Stuck
+    }
+}
+
+/// Moves back by one character, onto the end of the previous line at the start of a line
+fn dec(buf: &Buffer, pos: &mut Position) -> Step {
+    let line = buf.row(pos.row);
+    if let Some(char) = line[..pos.col].chars().next_back() {
+        pos.col -= char.len_utf8();
+        Step::SameLine
+    } else if pos.row > 0 {
+        pos.row -= 1;
+        pos.col = buf.row(pos.row).len();
+        Step::NextLine
+    } else {
+        Step::Stuck
+    }
+}
+
+pub fn find_char(buf: &Buffer, search: char, count: usize) -> Option<Position> {
+    let col = next_occurrence(buf.row(buf.cursor.row), buf.cursor.col, search, count)?;
+    Some(Position {
+        row: buf.cursor.row,
+        col,
+    })
+}
+
+pub fn find_prev_char(buf: &Buffer, search: char, count: usize) -> Option<Position> {
+    let col = prev_occurrence(buf.row(buf.cursor.row), buf.cursor.col, search, count)?;
+    Some(Position {
+        row: buf.cursor.row,
+        col,
+    })
+}
+
+/// Finds the position before the `count`th occurrence of `search`, skipping an occurrence right
+/// after the cursor when `skip_adjacent` is set
+pub fn till_char(
+    buf: &Buffer,
+    search: char,
+    count: usize,
+    skip_adjacent: bool,
+) -> Option<Position> {
+    let line = buf.row(buf.cursor.row);
+    let mut from = buf.cursor.col;
+    if skip_adjacent {
+        from += line[from..].chars().next().map_or(0, char::len_utf8);
+    }
+
+    let found = next_occurrence(line, from, search, count)?;
+    let prev = line[..found].chars().next_back().map_or(0, char::len_utf8);
+    Some(Position {
+        row: buf.cursor.row,
+        col: found - prev,
+    })
+}
 
-    match iterator.next() {
-        Some(idx) => idx,
-        None => max(line.len(), 1) - 1,
+/// Finds the position after the `count`th previous occurrence of `search`, skipping an
+/// occurrence right before the cursor when `skip_adjacent` is set
+pub fn till_prev_char(
+    buf: &Buffer,
+    search: char,
+    count: usize,
+    skip_adjacent: bool,
+) -> Option<Position> {
+    let line = buf.row(buf.cursor.row);
+    let mut from = buf.cursor.col;
+    if skip_adjacent {
+        from -= line[..from].chars().next_back().map_or(0, char::len_utf8);
     }
+
+    let found = prev_occurrence(line, from, search, count)?;
+    let len = line[found..].chars().next().map_or(0, char::len_utf8);
+    Some(Position {
+        row: buf.cursor.row,
+        col: found + len,
+    })
+}
+
+fn next_occurrence(line: &str, col: usize, search: char, count: usize) -> Option<usize> {
+    line.char_indices()
+        .filter(|&(idx, char)| idx > col && char == search)
+        .nth(count - 1)
+        .map(|(idx, _)| idx)
+}
+
+fn prev_occurrence(line: &str, col: usize, search: char, count: usize) -> Option<usize> {
+    line.char_indices()
+        .rev()
+        .filter(|&(idx, char)| idx < col && char == search)
+        .nth(count - 1)
+        .map(|(idx, _)| idx)
 }
 
-fn first_not_whitespace(line: &str) -> usize {
-    let mut iterator =
-        line.chars()
-            .enumerate()
-            .filter_map(|value| if value.1 != ' ' { Some(value.0) } else { None });
+/// The keyword under or after the cursor, or the non-blank text there when the rest of the line
+/// has no keyword, along with whether it is a keyword
+pub fn word_under_cursor(buf: &Buffer) -> Option<(Range<usize>, bool)> {
+    let line = buf.row(buf.cursor.row);
+    let col = min(buf.cursor.col, line.len());
+
+    for keyword in [true, false] {
+        let matches = |char: char| match keyword {
+            true => buf.options.iskeyword().contains(char),
+            false => !char.is_whitespace(),
+        };
+
+        let start = match line[col..].chars().next() {
+            Some(char) if matches(char) => line[..col]
+                .char_indices()
+                .rev()
+                .take_while(|(_, char)| matches(*char))
+                .last()
+                .map_or(col, |(idx, _)| idx),
+            _ => match line[col..].find(matches) {
+                Some(idx) => col + idx,
+                None => continue,
+            },
+        };
+        let end = line[start..]
+            .find(|char| !matches(char))
+            .map_or(line.len(), |idx| start + idx);
+
+        return Some((start..end, keyword));
+    }
 
-    iterator.next().unwrap_or_default()
+    None
+}
+
+/// The first non-blank character of a line, or its last character when it's all blank
+pub fn first_not_whitespace(line: &str) -> usize {
+    line.find(|char: char| !char.is_whitespace())
+        .unwrap_or_else(|| last_char(line))
+}
+
+fn last_not_whitespace(line: &str) -> usize {
+    match line
+        .char_indices()
+        .rev()
+        .find(|(_, char)| !char.is_whitespace())
+    {
+        Some((idx, _)) => idx,
+        None => last_char(line),
+    }
+}
+
+/// Where the last character of a line starts, or 0 on an empty line
+fn last_char(line: &str) -> usize {
+    line.len() - line.chars().next_back().map_or(0, char::len_utf8)
 }
 
 #[cfg(test)]
@@ -339,13 +735,48 @@ mod tests {
 
         buf.cursor = Position { row: 0, col: 22 };
         assert_eq!(right(&buf), buf.cursor);
+
+        buf.cursor = Position { row: 5, col: 0 };
+        assert_eq!(right(&buf), buf.cursor);
+    }
+
+    #[test]
+    fn clamps_with_virtualedit() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.cursor = Position { row: 0, col: 22 };
+
+        buf.options.set("ve=onemore").unwrap();
+        assert_eq!(right(&buf), Position { row: 0, col: 23 });
+        assert_eq!(clamp_col(&buf, 5, 3), 0);
+
+        buf.options.set("ve=all").unwrap();
+        buf.cursor.col = 23;
+        assert_eq!(right(&buf), Position { row: 0, col: 24 });
+        buf.cursor.col = 25;
+        assert_eq!(left(&buf), Position { row: 0, col: 24 });
+    }
+
+    #[test]
+    fn wraps_across_lines() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.cursor = Position { row: 1, col: 0 };
+
+        assert_eq!(left_wrap(&buf, 'h'), buf.cursor);
+        assert_eq!(left_wrap(&buf, 'b'), Position { row: 0, col: 22 });
+
+        buf.cursor = Position { row: 0, col: 22 };
+        assert_eq!(right_wrap(&buf, 'l'), buf.cursor);
+        assert_eq!(right_wrap(&buf, 's'), Position { row: 1, col: 0 });
+
+        buf.cursor = Position { row: 6, col: 4 };
+        assert_eq!(right_wrap(&buf, 's'), buf.cursor);
     }
 
     #[test]
     fn cursor_end() {
         let buf = Buffer::test(String::from("test.txt"));
 
-        assert_eq!(end_line(&buf), Position { row: 0, col: 22 });
+        assert_eq!(end_line(&buf, 0), Position { row: 0, col: 22 });
     }
 
     #[test]
@@ -359,109 +790,258 @@ mod tests {
     #[test]
     fn cursor_word_end() {
         let mut buf = Buffer::test(String::from("test.txt"));
-        assert_eq!(word_end(&buf), Position { row: 0, col: 4 });
+        assert_eq!(word_end(&buf, false), Position { row: 0, col: 4 });
 
         buf.cursor = Position { row: 0, col: 17 };
-        buf.cursor = word_end(&buf);
+        buf.cursor = word_end(&buf, false);
         assert_eq!(buf.cursor, Position { row: 0, col: 20 });
 
         buf.cursor = Position { row: 3, col: 36 };
-        buf.cursor = word_end(&buf);
+        buf.cursor = word_end(&buf, false);
         assert_eq!(buf.cursor, Position { row: 3, col: 37 });
-        assert_eq!(word_end(&buf), Position { row: 4, col: 4 });
+        assert_eq!(word_end(&buf, false), Position { row: 4, col: 4 });
 
         buf.cursor = Position { row: 4, col: 0 };
-        buf.cursor = word_end(&buf);
+        buf.cursor = word_end(&buf, false);
         assert_eq!(buf.cursor, Position { row: 4, col: 4 });
-        buf.cursor = word_end(&buf);
+        buf.cursor = word_end(&buf, false);
         assert_eq!(buf.cursor, Position { row: 4, col: 5 });
-        buf.cursor = word_end(&buf);
+        buf.cursor = word_end(&buf, false);
         assert_eq!(buf.cursor, Position { row: 4, col: 10 });
 
         buf.cursor = Position { row: 4, col: 10 };
-        assert_eq!(word_end(&buf), Position { row: 6, col: 4 })
+        assert_eq!(word_end(&buf, false), Position { row: 6, col: 4 })
     }
 
     #[test]
     fn cursor_prev_word_start() {
         let mut buf = Buffer::test(String::from("test.txt"));
-        assert_eq!(prev_word_start(&buf), Position { row: 0, col: 0 });
+        assert_eq!(prev_word_start(&buf, false), Position { row: 0, col: 0 });
 
         buf.cursor = Position { row: 0, col: 6 };
-        assert_eq!(prev_word_start(&buf), Position { row: 0, col: 0 });
+        assert_eq!(prev_word_start(&buf, false), Position { row: 0, col: 0 });
 
         buf.cursor = Position { row: 0, col: 14 };
-        assert_eq!(prev_word_start(&buf), Position { row: 0, col: 12 });
+        assert_eq!(prev_word_start(&buf, false), Position { row: 0, col: 12 });
 
         buf.cursor = Position { row: 1, col: 0 };
-        assert_eq!(prev_word_start(&buf), Position { row: 0, col: 21 });
+        assert_eq!(prev_word_start(&buf, false), Position { row: 0, col: 21 });
 
         buf.cursor = Position { row: 4, col: 10 };
-        buf.cursor = prev_word_start(&buf);
+        buf.cursor = prev_word_start(&buf, false);
         assert_eq!(buf.cursor, Position { row: 4, col: 6 });
-        buf.cursor = prev_word_start(&buf);
+        buf.cursor = prev_word_start(&buf, false);
         assert_eq!(buf.cursor, Position { row: 4, col: 5 });
-        assert_eq!(prev_word_start(&buf), Position { row: 4, col: 0 });
+        assert_eq!(prev_word_start(&buf, false), Position { row: 4, col: 0 });
 
         buf.cursor = Position { row: 6, col: 0 };
-        buf.cursor = prev_word_start(&buf);
+        buf.cursor = prev_word_start(&buf, false);
         assert_eq!(buf.cursor, Position { row: 5, col: 0 });
-        assert_eq!(prev_word_start(&buf), Position { row: 4, col: 6 });
+        assert_eq!(prev_word_start(&buf, false), Position { row: 4, col: 6 });
+    }
+
+    #[test]
+    fn word_under_cursor_test() {
+        let mut buf = Buffer::test(String::new());
+        buf.content[0] = String::from("a.b  foo_bar -> ");
+
+        buf.cursor.col = 9;
+        assert_eq!(word_under_cursor(&buf), Some((5..12, true)));
+
+        buf.cursor.col = 3;
+        assert_eq!(word_under_cursor(&buf), Some((5..12, true)));
+
+        buf.cursor.col = 12;
+        assert_eq!(word_under_cursor(&buf), Some((13..15, false)));
+
+        buf.cursor.col = 15;
+        assert_eq!(word_under_cursor(&buf), None);
     }
 
     #[test]
     fn cursor_word_start() {
         let mut buf = Buffer::test(String::from("test.txt"));
-        assert_eq!(word_start(&buf), Position { row: 0, col: 6 });
+        assert_eq!(word_start(&buf, false, false), Position { row: 0, col: 6 });
 
         buf.cursor = Position { row: 0, col: 17 };
-        assert_eq!(word_start(&buf), Position { row: 0, col: 21 });
+        assert_eq!(word_start(&buf, false, false), Position { row: 0, col: 21 });
 
         buf.cursor = Position { row: 0, col: 21 };
-        assert_eq!(word_start(&buf), Position { row: 1, col: 0 });
+        assert_eq!(word_start(&buf, false, false), Position { row: 1, col: 0 });
 
         buf.cursor = Position { row: 4, col: 0 };
-        buf.cursor = word_start(&buf);
+        buf.cursor = word_start(&buf, false, false);
         assert_eq!(buf.cursor, Position { row: 4, col: 5 });
 
         buf.cursor = Position { row: 4, col: 5 };
-        buf.cursor = word_start(&buf);
+        buf.cursor = word_start(&buf, false, false);
         assert_eq!(buf.cursor, Position { row: 4, col: 6 });
-        buf.cursor = word_start(&buf);
+        buf.cursor = word_start(&buf, false, false);
         assert_eq!(buf.cursor, Position { row: 5, col: 0 });
-        buf.cursor = word_start(&buf);
+        buf.cursor = word_start(&buf, false, false);
         assert_eq!(buf.cursor, Position { row: 6, col: 0 });
-        assert_eq!(word_start(&buf), Position { row: 6, col: 4 });
+        assert_eq!(word_start(&buf, false, false), Position { row: 6, col: 5 });
+    }
+
+    #[test]
+    fn cursor_big_words() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.content[0] = String::from("foo::bar baz-qux ;");
+
+        assert_eq!(word_start(&buf, false, false), Position { row: 0, col: 3 });
+        assert_eq!(word_start(&buf, true, false), Position { row: 0, col: 9 });
+        assert_eq!(word_end(&buf, true), Position { row: 0, col: 7 });
+
+        buf.cursor = Position { row: 0, col: 17 };
+        assert_eq!(prev_word_start(&buf, true), Position { row: 0, col: 9 });
+        assert_eq!(prev_word_end(&buf, false), Position { row: 0, col: 15 });
+        assert_eq!(prev_word_end(&buf, true), Position { row: 0, col: 15 });
+
+        buf.cursor = Position { row: 0, col: 14 };
+        assert_eq!(prev_word_end(&buf, false), Position { row: 0, col: 12 });
+        assert_eq!(prev_word_end(&buf, true), Position { row: 0, col: 7 });
+
+        buf.cursor = Position { row: 0, col: 9 };
+        assert_eq!(word_end(&buf, false), Position { row: 0, col: 11 });
+        buf.options.set("isk=@,48-57,_,-").unwrap();
+        assert_eq!(word_end(&buf, false), Position { row: 0, col: 15 });
     }
 
     #[test]
     fn cursor_find_char() {
         let mut buf = Buffer::test(String::from("test.txt"));
-        assert_eq!(find_char(&buf, 'i'), Position { row: 0, col: 6 });
-        assert_eq!(find_char(&buf, 'f'), buf.cursor);
+        assert_eq!(find_char(&buf, 'i', 1), Some(Position { row: 0, col: 6 }));
+        assert_eq!(find_char(&buf, 'f', 1), None);
 
         buf.cursor = Position { row: 2, col: 10 };
-        assert_eq!(find_char(&buf, 'c'), Position { row: 2, col: 16 });
+        assert_eq!(find_char(&buf, 'c', 1), Some(Position { row: 2, col: 16 }));
+        assert_eq!(find_char(&buf, 'c', 2), Some(Position { row: 2, col: 21 }));
     }
 
     #[test]
     fn cursor_find_prev_char() {
         let mut buf = Buffer::test(String::from("test.txt"));
         buf.cursor = Position { row: 2, col: 16 };
-        buf.cursor = find_prev_char(&buf, 'c');
+        buf.cursor = find_prev_char(&buf, 'c', 1).unwrap();
         assert_eq!(buf.cursor, Position { row: 2, col: 4 });
-        assert_eq!(find_prev_char(&buf, 'c'), buf.cursor);
+        assert_eq!(find_prev_char(&buf, 'c', 1), None);
+    }
+
+    #[test]
+    fn cursor_till_char() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.cursor = Position { row: 2, col: 10 };
+
+        assert_eq!(
+            till_char(&buf, 'u', 1, false),
+            Some(Position { row: 2, col: 10 })
+        );
+        assert_eq!(
+            till_char(&buf, 'u', 1, true),
+            Some(Position { row: 2, col: 24 })
+        );
+        assert_eq!(
+            till_prev_char(&buf, 's', 1, false),
+            Some(Position { row: 2, col: 9 })
+        );
+        buf.cursor.col = 9;
+        assert_eq!(till_prev_char(&buf, 's', 1, true), None);
+    }
+
+    #[test]
+    fn cursor_match_pair() {
+        let mut buf = Buffer::test(String::from("test.rs"));
+        buf.content = vec![
+            String::from("fn f(a: [u8; 2]) {"),
+            String::from("    g(\"}\"); // }"),
+            String::from("}"),
+        ];
+
+        assert_eq!(match_pair(&buf), Some(Position { row: 0, col: 15 }));
+
+        buf.cursor.col = 17;
+        assert_eq!(match_pair(&buf), Some(Position { row: 2, col: 0 }));
+
+        buf.cursor = Position { row: 2, col: 0 };
+        assert_eq!(match_pair(&buf), Some(Position { row: 0, col: 17 }));
+
+        buf.cursor = Position { row: 0, col: 8 };
+        assert_eq!(match_pair(&buf), Some(Position { row: 0, col: 14 }));
+
+        buf.options.set("matchpairs=(:),<:>").unwrap();
+        buf.content[0] = String::from("Vec<Vec<u8>>");
+        buf.cursor = Position { row: 0, col: 11 };
+        assert_eq!(match_pair(&buf), Some(Position { row: 0, col: 3 }));
+    }
+
+    #[test]
+    fn cursor_paragraph() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+
+        buf.cursor = next_paragraph(&buf);
+        assert_eq!(buf.cursor, Position { row: 5, col: 0 });
+        buf.cursor = next_paragraph(&buf);
+        assert_eq!(buf.cursor, Position { row: 6, col: 5 });
+
+        buf.cursor = prev_paragraph(&buf);
+        assert_eq!(buf.cursor, Position { row: 5, col: 0 });
+        buf.cursor = prev_paragraph(&buf);
+        assert_eq!(buf.cursor, Position { row: 0, col: 0 });
+    }
+
+    #[test]
+    fn cursor_sentence() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.content[0] = String::from("One (two.) Three?  Four!");
+
+        buf.cursor = next_sentence(&buf);
+        assert_eq!(buf.cursor, Position { row: 0, col: 11 });
+        buf.cursor = next_sentence(&buf);
+        assert_eq!(buf.cursor, Position { row: 0, col: 19 });
+        buf.cursor = next_sentence(&buf);
+        assert_eq!(buf.cursor, Position { row: 1, col: 0 });
+        buf.cursor = next_sentence(&buf);
+        assert_eq!(buf.cursor, Position { row: 2, col: 0 });
+
+        buf.cursor = Position { row: 0, col: 14 };
+        assert_eq!(prev_sentence(&buf), Position { row: 0, col: 11 });
+
+        buf.options.set("sentencespaces=2").unwrap();
+        assert_eq!(prev_sentence(&buf), Position { row: 0, col: 0 });
     }
 
     #[test]
     fn first_not_whitespace_test() {
         let line = " This is a string";
         assert_eq!(first_not_whitespace(line), 1);
+        assert_eq!(first_not_whitespace("   "), 2);
+        assert_eq!(first_not_whitespace(""), 0);
     }
 
     #[test]
     fn last_not_whitespace_test() {
         let line = "This is a string ";
         assert_eq!(last_not_whitespace(line), 15);
+        assert_eq!(last_not_whitespace(" \u{3000}"), 1);
+    }
+
+    #[test]
+    fn cursor_line_starts() {
+        let mut buf = Buffer::test(String::new());
+        buf.content = vec![String::from("\t foo  "), String::from("  é€x ")];
+
+        assert_eq!(line_start(&buf, 0), Position { row: 0, col: 2 });
+        assert_eq!(last_non_blank(&buf, 1), Position { row: 1, col: 7 });
+        assert_eq!(
+            line_start_by(&buf, true, 5),
+            Some(Position { row: 1, col: 2 })
+        );
+        assert_eq!(line_start_by(&buf, false, 1), None);
+
+        buf.cursor.row = 1;
+        assert_eq!(goto_column(&buf, 4), Position { row: 1, col: 4 });
+        assert_eq!(goto_column(&buf, 99), Position { row: 1, col: 9 });
+        assert_eq!(line_middle(&buf, 50), Position { row: 1, col: 4 });
+        assert_eq!(line_middle(&buf, 100), Position { row: 1, col: 9 });
     }
 }
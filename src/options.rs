@@ -0,0 +1,788 @@
+use std::{cell::RefCell, rc::Rc};
+
+/// The type of an option, with its default value
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Kind {
+    Bool(bool),
+    Number(usize),
+    String(&'static str),
+    /// A comma separated list
+    List(&'static str),
+}
+
+/// Where the value of an option applies. Buffer and window options start out with the global
+/// value in each new buffer, and as every buffer has a window of its own, both live with it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scope {
+    Global,
+    Buffer,
+    Window,
+}
+
+/// An option of the registry, with a check of the values a string or list option accepts
+struct Definition {
+    name: &'static str,
+    short: &'static str,
+    kind: Kind,
+    scope: Scope,
+    valid: fn(&str) -> bool,
+}
+
+const fn option(name: &'static str, short: &'static str, kind: Kind, scope: Scope) -> Definition {
+    Definition {
+        name,
+        short,
+        kind,
+        scope,
+        valid: |_| true,
+    }
+}
+
+const fn checked(
+    name: &'static str,
+    short: &'static str,
+    kind: Kind,
+    scope: Scope,
+    valid: fn(&str) -> bool,
+) -> Definition {
+    Definition {
+        name,
+        short,
+        kind,
+        scope,
+        valid,
+    }
+}
+
+/// Every option `:set` knows, by full and short name
+const OPTIONS: &[Definition] = &[
+    option("autoindent", "ai", Kind::Bool(false), Scope::Buffer),
+    checked(
+        "backspace",
+        "bs",
+        Kind::List("indent,eol,start"),
+        Scope::Global,
+        |value| items_in(value, &["indent", "eol", "start"]),
+    ),
+    option("cindent", "cin", Kind::Bool(false), Scope::Buffer),
+    checked(
+        "completeopt",
+        "cot",
+        Kind::List("menu,preview"),
+        Scope::Global,
+        |value| {
+            items_in(
+                value,
+                &[
+                    "menu", "menuone", "longest", "preview", "noinsert", "noselect",
+                ],
+            )
+        },
+    ),
+    option("dictionary", "dict", Kind::List(""), Scope::Global),
+    option("expandtab", "et", Kind::Bool(false), Scope::Buffer),
+    option("history", "hi", Kind::Number(50), Scope::Global),
+    option("hlsearch", "hls", Kind::Bool(false), Scope::Global),
+    option("ignorecase", "ic", Kind::Bool(false), Scope::Global),
+    option("incsearch", "is", Kind::Bool(false), Scope::Global),
+    checked(
+        "iskeyword",
+        "isk",
+        Kind::List("@,48-57,_,192-255"),
+        Scope::Buffer,
+        |value| KeywordChars::parse(value).is_some(),
+    ),
+    checked(
+        "matchpairs",
+        "mps",
+        Kind::List("(:),{:},[:]"),
+        Scope::Buffer,
+        |value| parse_pairs(value).is_some(),
+    ),
+    option("pumheight", "ph", Kind::Number(0), Scope::Global),
+    option("scroll", "scr", Kind::Number(0), Scope::Window),
+    option("scrolloff", "so", Kind::Number(0), Scope::Global),
+    option(
+        "sentenceend",
+        "sentenceend",
+        Kind::String(".!?"),
+        Scope::Global,
+    ),
+    option(
+        "sentencespaces",
+        "sentencespaces",
+        Kind::Number(1),
+        Scope::Global,
+    ),
+    option("shiftwidth", "sw", Kind::Number(8), Scope::Buffer),
+    option("sidescrolloff", "siso", Kind::Number(0), Scope::Global),
+    option("smartcase", "scs", Kind::Bool(false), Scope::Global),
+    option("smartindent", "si", Kind::Bool(false), Scope::Buffer),
+    option("startofline", "sol", Kind::Bool(true), Scope::Global),
+    checked(
+        "virtualedit",
+        "ve",
+        Kind::List(""),
+        Scope::Global,
+        |value| items_in(value, &["block", "insert", "all", "onemore", "none"]),
+    ),
+    checked(
+        "whichwrap",
+        "ww",
+        Kind::List("b,s"),
+        Scope::Global,
+        |value| items_in(value, &["b", "s", "h", "l", "<", ">", "~", "[", "]"]),
+    ),
+    option("wrapscan", "ws", Kind::Bool(true), Scope::Global),
+];
+
+fn items_in(value: &str, allowed: &[&str]) -> bool {
+    list_items(value).all(|item| allowed.contains(&item))
+}
+
+fn list_items(value: &str) -> impl Iterator<Item = &str> {
+    value.split(',').filter(|item| !item.is_empty())
+}
+
+fn find(name: &str) -> Option<usize> {
+    OPTIONS
+        .iter()
+        .position(|option| option.name == name || option.short == name)
+}
+
+/// The value of an option
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Number(usize),
+    String(String),
+    List(Vec<String>),
+}
+
+impl Value {
+    fn default_of(kind: Kind) -> Value {
+        match kind {
+            Kind::Bool(value) => Value::Bool(value),
+            Kind::Number(value) => Value::Number(value),
+            Kind::String(value) => Value::String(value.to_string()),
+            Kind::List(value) => Value::List(list_items(value).map(String::from).collect()),
+        }
+    }
+
+    /// The value as `:set` shows it
+    fn show(&self, name: &str) -> String {
+        match self {
+            Value::Bool(true) => name.to_string(),
+            Value::Bool(false) => format!("no{}", name),
+            Value::Number(value) => format!("{}={}", name, value),
+            Value::String(value) => format!("{}={}", name, value),
+            Value::List(items) => format!("{}={}", name, items.join(",")),
+        }
+    }
+}
+
+/// Which values `:set`, `:setlocal` and `:setglobal` change and show. The global value of a
+/// buffer or window option is the one new buffers start with
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SetScope {
+    Both,
+    Local,
+    Global,
+}
+
+/// Settings changed with `:set`. The global values are shared between all buffers, and the
+/// buffer and window values are the buffer's own
+#[derive(Debug)]
+pub struct Options {
+    local: Vec<Value>,
+    global: Rc<RefCell<Vec<Value>>>,
+    /// The parsed local `iskeyword` and `matchpairs`, which are looked up on every character
+    iskeyword: KeywordChars,
+    matchpairs: Vec<(char, char)>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        let values: Vec<Value> = OPTIONS
+            .iter()
+            .map(|option| Value::default_of(option.kind))
+            .collect();
+
+        Options::with_values(values.clone(), Rc::new(RefCell::new(values)))
+    }
+}
+
+impl Options {
+    fn with_values(local: Vec<Value>, global: Rc<RefCell<Vec<Value>>>) -> Self {
+        let mut options = Options {
+            local,
+            global,
+            iskeyword: KeywordChars::default(),
+            matchpairs: Vec::new(),
+        };
+        options.parse_lists();
+        options
+    }
+
+    /// Options for a new buffer, sharing the global values and starting from them
+    pub fn for_buffer(&self) -> Options {
+        let local = self.global.borrow().clone();
+        Options::with_values(local, Rc::clone(&self.global))
+    }
+
+    /// Applies the arguments of a `:set` command, e.g. `ai sw=4 noet`, returning what it shows
+    pub fn set(&mut self, args: &str) -> Result<String, String> {
+        self.set_scoped(args, SetScope::Both)
+    }
+
+    /// Applies the arguments of `:set`, `:setlocal` or `:setglobal`. Without arguments it shows
+    /// the options that differ from their default, and `all` shows every option
+    pub fn set_scoped(&mut self, args: &str, scope: SetScope) -> Result<String, String> {
+        let args = split_args(args);
+        if args.is_empty() || args == ["all"] {
+            let changed_only = args.is_empty();
+            let lines: Vec<String> = OPTIONS
+                .iter()
+                .enumerate()
+                .filter(|(idx, option)| {
+                    !changed_only || self.value(*idx, scope) != Value::default_of(option.kind)
+                })
+                .map(|(idx, option)| self.value(idx, scope).show(option.name))
+                .collect();
+            return Ok(format!("--- Options ---\n{}", lines.join("\n")));
+        }
+
+        let mut shown = Vec::new();
+        for arg in &args {
+            if let Some(value) = self.set_one(arg, scope)? {
+                shown.push(value);
+            }
+        }
+
+        Ok(shown.join("  "))
+    }
+
+    /// Applies one argument, returning the value when it asks to show it
+    fn set_one(&mut self, arg: &str, scope: SetScope) -> Result<Option<String>, String> {
+        let invalid = || format!("Invalid argument: {}", arg);
+        let name_len = arg
+            .find(|char: char| !char.is_ascii_alphabetic())
+            .unwrap_or(arg.len());
+        let (name, rest) = arg.split_at(name_len);
+
+        let (idx, prefix) = match find(name) {
+            Some(idx) => (idx, ""),
+            None => {
+                let (prefix, name) = match name.strip_prefix("no") {
+                    Some(name) => ("no", name),
+                    None => ("inv", name.strip_prefix("inv").unwrap_or(name)),
+                };
+                let idx = find(name).ok_or_else(|| format!("Unknown option: {}", arg))?;
+                if !matches!(OPTIONS[idx].kind, Kind::Bool(_)) || !rest.is_empty() {
+                    return Err(invalid());
+                }
+                (idx, prefix)
+            }
+        };
+        let option = &OPTIONS[idx];
+        let current = self.value(idx, scope);
+
+        let value = match (prefix, rest, current) {
+            ("no", _, _) => Value::Bool(false),
+            ("inv", _, Value::Bool(value)) | (_, "!", Value::Bool(value)) => Value::Bool(!value),
+            (_, "", Value::Bool(_)) => Value::Bool(true),
+            (_, "", value) | (_, "?", value) => return Ok(Some(value.show(option.name))),
+            (_, "&" | "&vim", _) => Value::default_of(option.kind),
+            (_, rest, current) => {
+                let (operator, value) = split_operator(rest).ok_or_else(invalid)?;
+                let value = match option.name {
+                    // The old numeric values stand for growing sets of the flags
+                    "backspace" => match value {
+                        "0" => "",
+                        "1" => "indent,eol",
+                        "2" => "indent,eol,start",
+                        value => value,
+                    },
+                    _ => value,
+                };
+                let value = apply(current, operator, value).ok_or_else(|| match option.kind {
+                    Kind::Number(_) => format!("Number required after =: {}", arg),
+                    _ => invalid(),
+                })?;
+                match &value {
+                    Value::String(text) if !(option.valid)(text) => return Err(invalid()),
+                    Value::List(items) if !(option.valid)(&items.join(",")) => {
+                        return Err(invalid())
+                    }
+                    _ => value,
+                }
+            }
+        };
+
+        self.put(idx, value, scope);
+        Ok(None)
+    }
+
+    /// The value `:set`, `:setlocal` or `:setglobal` shows
+    fn value(&self, idx: usize, scope: SetScope) -> Value {
+        match (OPTIONS[idx].scope, scope) {
+            (Scope::Global, _) | (_, SetScope::Global) => self.global.borrow()[idx].clone(),
+            _ => self.local[idx].clone(),
+        }
+    }
+
+    fn put(&mut self, idx: usize, value: Value, scope: SetScope) {
+        let option = &OPTIONS[idx];
+        if option.scope == Scope::Global || scope != SetScope::Local {
+            self.global.borrow_mut()[idx] = value.clone();
+        }
+        if option.scope != Scope::Global && scope != SetScope::Global {
+            self.local[idx] = value;
+            self.parse_lists();
+        }
+    }
+
+    fn parse_lists(&mut self) {
+        // The values were checked when they were set
+        let (iskeyword, matchpairs) = (self.list("iskeyword"), self.list("matchpairs"));
+        self.iskeyword = KeywordChars::parse(&iskeyword.join(",")).unwrap_or_default();
+        self.matchpairs = parse_pairs(&matchpairs.join(",")).unwrap_or_default();
+    }
+
+    /// The value the buffer uses
+    fn get(&self, name: &str) -> Value {
+        self.value(find(name).unwrap(), SetScope::Local)
+    }
+
+    fn bool(&self, name: &str) -> bool {
+        match self.get(name) {
+            Value::Bool(value) => value,
+            _ => unreachable!("{} is not a boolean option", name),
+        }
+    }
+
+    fn number(&self, name: &str) -> usize {
+        match self.get(name) {
+            Value::Number(value) => value,
+            _ => unreachable!("{} is not a number option", name),
+        }
+    }
+
+    fn list(&self, name: &str) -> Vec<String> {
+        match self.get(name) {
+            Value::List(items) => items,
+            _ => unreachable!("{} is not a list option", name),
+        }
+    }
+
+    pub fn autoindent(&self) -> bool {
+        self.bool("autoindent")
+    }
+
+    pub fn smartindent(&self) -> bool {
+        self.bool("smartindent")
+    }
+
+    pub fn cindent(&self) -> bool {
+        self.bool("cindent")
+    }
+
+    pub fn expandtab(&self) -> bool {
+        self.bool("expandtab")
+    }
+
+    pub fn startofline(&self) -> bool {
+        self.bool("startofline")
+    }
+
+    pub fn wrapscan(&self) -> bool {
+        self.bool("wrapscan")
+    }
+
+    pub fn ignorecase(&self) -> bool {
+        self.bool("ignorecase")
+    }
+
+    pub fn smartcase(&self) -> bool {
+        self.bool("smartcase")
+    }
+
+    pub fn incsearch(&self) -> bool {
+        self.bool("incsearch")
+    }
+
+    pub fn hlsearch(&self) -> bool {
+        self.bool("hlsearch")
+    }
+
+    pub fn shiftwidth(&self) -> usize {
+        self.number("shiftwidth")
+    }
+
+    pub fn scroll(&self) -> usize {
+        self.number("scroll")
+    }
+
+    /// Sets the window's `scroll`, as a count given to `CTRL-D` and `CTRL-U` does
+    pub fn set_scroll(&mut self, lines: usize) {
+        self.put(
+            find("scroll").unwrap(),
+            Value::Number(lines),
+            SetScope::Local,
+        );
+    }
+
+    pub fn scrolloff(&self) -> usize {
+        self.number("scrolloff")
+    }
+
+    pub fn sidescrolloff(&self) -> usize {
+        self.number("sidescrolloff")
+    }
+
+    pub fn history(&self) -> usize {
+        self.number("history")
+    }
+
+    pub fn pumheight(&self) -> usize {
+        self.number("pumheight")
+    }
+
+    pub fn sentenceend(&self) -> String {
+        match self.get("sentenceend") {
+            Value::String(value) => value,
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn sentencespaces(&self) -> usize {
+        self.number("sentencespaces")
+    }
+
+    pub fn iskeyword(&self) -> &KeywordChars {
+        &self.iskeyword
+    }
+
+    pub fn matchpairs(&self) -> &[(char, char)] {
+        &self.matchpairs
+    }
+
+    pub fn completeopt(&self) -> Vec<String> {
+        self.list("completeopt")
+    }
+
+    pub fn dictionary(&self) -> Vec<String> {
+        self.list("dictionary")
+    }
+
+    /// Whether `whichwrap` lets the key move across line boundaries
+    pub fn wraps(&self, key: char) -> bool {
+        self.list("whichwrap")
+            .iter()
+            .any(|flag| flag.chars().eq([key]))
+    }
+
+    /// Whether `backspace` allows deleting over `item`: `indent`, `eol` or `start`
+    pub fn backspaces_over(&self, item: &str) -> bool {
+        self.list("backspace").iter().any(|value| value == item)
+    }
+
+    /// Whether `virtualedit` has `item`: `block`, `onemore` or `all`
+    pub fn virtual_edit(&self, item: &str) -> bool {
+        self.list("virtualedit").iter().any(|value| value == item)
+    }
+}
+
+/// Splits `:set` arguments on whitespace, where a backslash keeps the next character
+fn split_args(args: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut part = String::new();
+    let mut chars = args.chars();
+
+    while let Some(char) = chars.next() {
+        match char {
+            '\\' => part.extend(chars.next()),
+            char if char.is_whitespace() => {
+                if !part.is_empty() {
+                    parts.push(std::mem::take(&mut part));
+                }
+            }
+            char => part.push(char),
+        }
+    }
+    if !part.is_empty() {
+        parts.push(part);
+    }
+
+    parts
+}
+
+/// Splits `=value`, `:value`, `+=value`, `-=value` or `^=value` into the operator and the value
+fn split_operator(rest: &str) -> Option<(char, &str)> {
+    match rest.chars().next()? {
+        '=' | ':' => Some(('=', &rest[1..])),
+        operator @ ('+' | '-' | '^') => Some((operator, rest[1..].strip_prefix('=')?)),
+        _ => None,
+    }
+}
+
+/// The value after `:set` with the operator: `+=` adds to a number or appends to a string or
+/// list, `-=` subtracts or removes, and `^=` multiplies or prepends
+fn apply(current: Value, operator: char, value: &str) -> Option<Value> {
+    Some(match current {
+        Value::Bool(_) => return None,
+        Value::Number(current) => {
+            let value: usize = value.parse().ok()?;
+            Value::Number(match operator {
+                '+' => current.saturating_add(value),
+                '-' => current.saturating_sub(value),
+                '^' => current.saturating_mul(value),
+                _ => value,
+            })
+        }
+        Value::String(current) => Value::String(match operator {
+            '+' => current + value,
+            '-' => current.replacen(value, "", 1),
+            '^' => format!("{}{}", value, current),
+            _ => value.to_string(),
+        }),
+        Value::List(mut items) => {
+            let new: Vec<String> = list_items(value).map(String::from).collect();
+            match operator {
+                '+' => {
+                    for item in new {
+                        if !items.contains(&item) {
+                            items.push(item);
+                        }
+                    }
+                }
+                '-' => items.retain(|item| !new.contains(item)),
+                '^' => {
+                    items.retain(|item| !new.contains(item));
+                    items.splice(0..0, new);
+                }
+                _ => items = new,
+            }
+            Value::List(items)
+        }
+    })
+}
+
+/// Parses a `matchpairs` value like `(:),<:>`
+fn parse_pairs(value: &str) -> Option<Vec<(char, char)>> {
+    value
+        .split(',')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let mut chars = pair.chars();
+            match (chars.next(), chars.next(), chars.next(), chars.next()) {
+                (Some(open), Some(':'), Some(close), None) if open != close => Some((open, close)),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// The characters that make up keywords, parsed from an `iskeyword` value like
+/// `@,48-57,_,192-255`. Parts are `@` for letters, a character, a character code or a range of
+/// either, and a part starting with `^` excludes the characters again
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeywordChars {
+    parts: Vec<(bool, KeywordPart)>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum KeywordPart {
+    Letters,
+    Range(u32, u32),
+}
+
+impl KeywordChars {
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut parts = Vec::new();
+
+        for part in value.split(',').filter(|part| !part.is_empty()) {
+            let (include, part) = match part.strip_prefix('^') {
+                Some(rest) if !rest.is_empty() => (false, rest),
+                _ => (true, part),
+            };
+
+            let range = match part {
+                "@" => KeywordPart::Letters,
+                "@-@" => KeywordPart::Range('@' as u32, '@' as u32),
+                _ => match part.char_indices().skip(1).find(|(_, char)| *char == '-') {
+                    Some((idx, _)) if idx + 1 < part.len() => {
+                        KeywordPart::Range(Self::code(&part[..idx])?, Self::code(&part[idx + 1..])?)
+                    }
+                    _ => {
+                        let code = Self::code(part)?;
+                        KeywordPart::Range(code, code)
+                    }
+                },
+            };
+            parts.push((include, range));
+        }
+
+        Some(KeywordChars { parts })
+    }
+
+    /// A character code, or a single character standing for itself
+    fn code(part: &str) -> Option<u32> {
+        match part.parse() {
+            Ok(code) => Some(code),
+            Err(_) => {
+                let mut chars = part.chars();
+                let char = chars.next()?;
+                chars.next().is_none().then_some(char as u32)
+            }
+        }
+    }
+
+    /// Whether the character is a keyword character. Characters above 255 are keyword
+    /// characters when they are alphanumeric, like Vim does for Unicode
+    pub fn contains(&self, char: char) -> bool {
+        let code = char as u32;
+        if code > 255 {
+            return char.is_alphanumeric();
+        }
+
+        self.parts
+            .iter()
+            .rev()
+            .find(|(_, part)| match *part {
+                KeywordPart::Letters => char.is_alphabetic(),
+                KeywordPart::Range(start, end) => (start..=end).contains(&code),
+            })
+            .is_some_and(|(include, _)| *include)
+    }
+}
+
+impl Default for KeywordChars {
+    fn default() -> Self {
+        KeywordChars::parse("@,48-57,_,192-255").unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sets_options() {
+        let mut options = Options::default();
+
+        options.set("ai sw=4 et").unwrap();
+        assert!(options.autoindent());
+        assert!(options.expandtab());
+        assert_eq!(options.shiftwidth(), 4);
+
+        options.set("noautoindent").unwrap();
+        assert!(!options.autoindent());
+
+        options.set("cot=menuone,noselect").unwrap();
+        assert_eq!(options.completeopt(), vec!["menuone", "noselect"]);
+
+        options.set("sentenceend=.;: sentencespaces=2").unwrap();
+        assert_eq!(options.sentenceend(), ".;:");
+        assert_eq!(options.sentencespaces(), 2);
+    }
+
+    #[test]
+    fn sets_edge_options() {
+        let mut options = Options::default();
+        assert!(options.wraps('b') && !options.wraps('h'));
+
+        options.set("ww=h,l,<,> bs=1 ve=onemore").unwrap();
+        assert!(options.wraps('h') && options.wraps('<') && !options.wraps('b'));
+        assert!(options.backspaces_over("eol") && !options.backspaces_over("start"));
+        assert!(options.virtual_edit("onemore") && !options.virtual_edit("all"));
+    }
+
+    #[test]
+    fn changes_and_shows_values() {
+        let mut options = Options::default();
+
+        options.set("invai et! sw+=4 sw-=2").unwrap();
+        assert!(options.autoindent() && options.expandtab());
+        assert_eq!(options.shiftwidth(), 10);
+        assert_eq!(
+            options.set("ai? sw isk?").unwrap(),
+            "autoindent  shiftwidth=10  iskeyword=@,48-57,_,192-255"
+        );
+
+        options
+            .set("ww+=h,b ww^=< cot-=menu sentenceend+=;")
+            .unwrap();
+        assert_eq!(options.list("whichwrap"), vec!["<", "b", "s", "h"]);
+        assert_eq!(options.completeopt(), vec!["preview"]);
+        assert_eq!(options.sentenceend(), ".!?;");
+
+        options.set("sw& noai").unwrap();
+        assert_eq!(
+            options.set("").unwrap(),
+            "--- Options ---\ncompleteopt=preview\nexpandtab\nsentenceend=.!?;\nwhichwrap=<,b,s,h"
+        );
+        assert!(options.set("all").unwrap().contains("\nnohlsearch\n"));
+    }
+
+    #[test]
+    fn sets_scopes() {
+        let mut global = Options::default();
+        let mut buffer = global.for_buffer();
+
+        buffer.set_scoped("sw=2 ic", SetScope::Local).unwrap();
+        assert_eq!(buffer.shiftwidth(), 2);
+        assert_eq!(global.shiftwidth(), 8);
+        assert!(global.ignorecase());
+
+        buffer.set_scoped("sw=4", SetScope::Global).unwrap();
+        assert_eq!(buffer.shiftwidth(), 2);
+        assert_eq!(
+            buffer.set_scoped("sw?", SetScope::Global).unwrap(),
+            "shiftwidth=4"
+        );
+        assert_eq!(global.for_buffer().shiftwidth(), 4);
+
+        global.set("sw=6 isk+=-").unwrap();
+        assert_eq!(buffer.shiftwidth(), 2);
+        assert!(global.iskeyword().contains('-') && !buffer.iskeyword().contains('-'));
+    }
+
+    #[test]
+    fn reports_errors() {
+        let mut options = Options::default();
+
+        assert_eq!(options.set("foo"), Err(String::from("Unknown option: foo")));
+        assert_eq!(
+            options.set("sw=x"),
+            Err(String::from("Number required after =: sw=x"))
+        );
+        for arg in ["nosw", "ai=1", "sw!", "ww=x", "ve+=some", "isk=a-"] {
+            assert_eq!(options.set(arg), Err(format!("Invalid argument: {}", arg)));
+        }
+        assert_eq!(options.shiftwidth(), 8);
+    }
+
+    #[test]
+    fn parses_iskeyword() {
+        let keywords = KeywordChars::parse("@,48-57,_,-,^x,192-255").unwrap();
+
+        assert!(keywords.contains('a'));
+        assert!(keywords.contains('7'));
+        assert!(keywords.contains('-'));
+        assert!(keywords.contains('é'));
+        assert!(keywords.contains('ж'));
+        assert!(!keywords.contains('x'));
+        assert!(!keywords.contains(':'));
+
+        let mut options = Options::default();
+        assert_eq!(
+            options.set("mps=(:)<:>"),
+            Err(String::from("Invalid argument: mps=(:)<:>"))
+        );
+        assert_eq!(
+            options.set("isk=@,a-"),
+            Err(String::from("Invalid argument: isk=@,a-"))
+        );
+    }
+}
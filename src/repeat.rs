@@ -0,0 +1,87 @@
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::{buffer::Buffer, mode::Mode};
+
+/// A change made from normal mode, kept so it can be repeated with `.`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Change {
+    pub count: Option<usize>,
+    pub keys: String,
+    pub inserted: Vec<KeyEvent>,
+}
+
+pub fn start_change(buf: &mut Buffer, count: Option<usize>, keys: &str) {
+    buf.recording = Some(Change {
+        count,
+        keys: keys.to_string(),
+        inserted: Vec::new(),
+    });
+}
+
+pub fn record_key(buf: &mut Buffer, event: KeyEvent) {
+    if let Some(change) = buf.recording.as_mut() {
+        change.inserted.push(event);
+    }
+}
+
+pub fn finish_change(buf: &mut Buffer) {
+    if let Some(change) = buf.recording.take() {
+        buf.last_change = Some(change);
+    }
+}
+
+pub fn repeat_change(buf: &mut Buffer, count: Option<usize>) {
+    let Some(change) = buf.last_change.clone() else {
+        return;
+    };
+
+    let mut keys = count
+        .or(change.count)
+        .map_or(String::new(), |count| count.to_string());
+    keys.push_str(&change.keys);
+    buf.keys = String::new();
+
+    for char in keys.chars() {
+        buf.handle_keys(KeyEvent::new(KeyCode::Char(char), KeyModifiers::NONE));
+    }
+
+    for event in change.inserted {
+        buf.handle_keys(event);
+    }
+
+    if buf.mode == Mode::Insert {
+        buf.handle_keys(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeats_delete() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.input_keys("dw.");
+
+        assert_eq!(buf.content[0], "odor amet, ");
+    }
+
+    #[test]
+    fn repeats_with_new_count() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.input_keys("x3.");
+
+        assert_eq!(buf.content[0], "m ipsum odor amet, ");
+        assert_eq!(buf.last_change.as_ref().unwrap().count, Some(3));
+    }
+
+    #[test]
+    fn repeats_insert() {
+        let mut buf = Buffer::test(String::new());
+        buf.input_keys("2ihi");
+        buf.handle_keys(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        buf.input_keys(".");
+
+        assert_eq!(buf.content[0], "hihhihii");
+    }
+}
@@ -0,0 +1,836 @@
+use std::cmp::min;
+
+use ratatui::{
+    buffer::Buffer as TBuffer,
+    crossterm::event::{KeyCode, KeyEvent, KeyModifiers},
+    layout::Rect,
+    style::Color,
+};
+use regex::{Regex, RegexBuilder};
+
+use crate::{
+    buffer::{Buffer, Position},
+    history::HistoryKind,
+    mode::Mode,
+    navigation::word_under_cursor,
+    options::Options,
+    view::{screen_position, visible_lines},
+};
+
+/// The last pattern searched for with `/` or `?`, repeated by `n` and `N`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Search {
+    pub pattern: String,
+    pub backward: bool,
+    pub offset: Offset,
+}
+
+/// Where the cursor ends up relative to a match, given after the pattern as in `/foo/e+1`
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Offset {
+    #[default]
+    None,
+    Lines(isize),
+    Start(isize),
+    End(isize),
+}
+
+impl Offset {
+    /// Parses an offset like `+2`, `-`, `e-1`, `s` or `b+3`
+    pub fn parse(value: &str) -> Option<Self> {
+        let number = |value: &str| match value {
+            "" => Some(0),
+            "+" => Some(1),
+            "-" => Some(-1),
+            _ => value.strip_prefix('+').unwrap_or(value).parse().ok(),
+        };
+
+        match value.chars().next() {
+            None => Some(Offset::None),
+            Some('e') => number(&value[1..]).map(Offset::End),
+            Some('s' | 'b') => number(&value[1..]).map(Offset::Start),
+            Some(_) => number(value).map(Offset::Lines),
+        }
+    }
+}
+
+/// A search being typed at the `/` or `?` prompt
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchPrompt {
+    pub backward: bool,
+    start: Position,
+    /// The normal mode keys typed before the prompt, like `2d`, to use the search as a motion
+    operator: String,
+}
+
+impl SearchPrompt {
+    pub fn delimiter(&self) -> char {
+        match self.backward {
+            true => '?',
+            false => '/',
+        }
+    }
+}
+
+/// Opens the search prompt. The `operator` keys are executed with the search once it is entered
+pub fn start_search(buf: &mut Buffer, backward: bool, operator: &str) {
+    buf.search_prompt = Some(SearchPrompt {
+        backward,
+        start: buf.cursor,
+        operator: operator.to_string(),
+    });
+    buf.change_mode(Mode::Search);
+}
+
+/// Searches for the pattern typed at the prompt, then jumps to it like `n`
+pub fn execute_search(buf: &mut Buffer) {
+    let Some(prompt) = buf.search_prompt.take() else {
+        return;
+    };
+    let input = std::mem::take(&mut buf.keys);
+    buf.cursor = prompt.start;
+    buf.change_mode(Mode::Normal);
+    buf.history
+        .add(HistoryKind::Search, &input, buf.options.history());
+
+    let (pattern, offset) = split_offset(&input, prompt.delimiter());
+    let Some(offset) = Offset::parse(offset) else {
+        return buf.show_error(format!("Invalid search offset: {}", offset));
+    };
+
+    // An empty pattern searches for the last one again
+    let pattern = match (pattern, &buf.last_search) {
+        ("", Some(search)) => search.pattern.clone(),
+        ("", None) => return buf.show_error("No previous regular expression"),
+        (pattern, _) => pattern.to_string(),
+    };
+    buf.register.set('/', pattern.clone());
+    buf.last_search = Some(Search {
+        pattern,
+        backward: prompt.backward,
+        offset,
+    });
+
+    for char in format!("{}n", prompt.operator).chars() {
+        buf.handle_keys(KeyEvent::new(KeyCode::Char(char), KeyModifiers::NONE));
+    }
+}
+
+/// Closes the prompt, moving the cursor back from where `incsearch` showed a match
+pub fn cancel_search(buf: &mut Buffer) {
+    if let Some(prompt) = buf.search_prompt.take() {
+        buf.cursor = prompt.start;
+    }
+    buf.change_mode(Mode::Normal);
+}
+
+/// Moves the cursor to the first match of the pattern typed so far with `incsearch`
+pub fn preview_search(buf: &mut Buffer) {
+    let Some(prompt) = buf.search_prompt.clone() else {
+        return;
+    };
+    buf.cursor = prompt.start;
+
+    let (pattern, _) = split_offset(&buf.keys, prompt.delimiter());
+    if !buf.options.incsearch() || pattern.is_empty() {
+        return;
+    }
+
+    if let Some((pos, _, _)) = compile(pattern, &buf.options)
+        .ok()
+        .and_then(|regex| find_match(buf, &regex, prompt.start, prompt.backward, 1))
+    {
+        buf.cursor = pos;
+    }
+}
+
+/// Searches for the word under the cursor like `*` and `#`, matching only whole keywords when
+/// `whole` is set, unlike `g*` and `g#`
+pub fn search_word(buf: &mut Buffer, backward: bool, whole: bool, count: usize) {
+    let Some((range, keyword)) = word_under_cursor(buf) else {
+        return buf.show_error("No string under cursor");
+    };
+
+    let mut pattern = escape(&buf.row(buf.cursor.row)[range.clone()]);
+    if whole && keyword {
+        pattern = format!("\\<{}\\>", pattern);
+    }
+    buf.register.set('/', pattern.clone());
+    buf.last_search = Some(Search {
+        pattern,
+        backward,
+        offset: Offset::None,
+    });
+
+    // Searching from the start of the word skips over the word itself
+    buf.cursor.col = range.start;
+    if let Some(pos) = search_target(buf, false, count) {
+        buf.cursor = pos;
+    }
+}
+
+/// Escapes the characters that are special in a pattern
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for char in text.chars() {
+        if "\\.*$^~[".contains(char) {
+            escaped.push('\\');
+        }
+        escaped.push(char);
+    }
+
+    escaped
+}
+
+/// Finds the `count`th match of the last search, in the other direction when `reverse` is set as
+/// for `N`. Shows the match counter, or an error when nothing matches
+pub fn search_target(buf: &mut Buffer, reverse: bool, count: usize) -> Option<Position> {
+    let Some(search) = buf.last_search.clone() else {
+        buf.show_error("No previous regular expression");
+        return None;
+    };
+    let regex = match compile(&search.pattern, &buf.options) {
+        Ok(regex) => regex,
+        Err(message) => {
+            buf.show_error(message);
+            return None;
+        }
+    };
+    let backward = search.backward != reverse;
+    buf.highlight_search = true;
+
+    // Start from the match the cursor was moved away from by the offset, so it isn't found again
+    let mut from = buf.cursor;
+    if let Offset::Lines(lines) = search.offset {
+        from.row = from.row.saturating_add_signed(-lines);
+        from.row = min(from.row, buf.content.len() - 1);
+        from.col = if backward { 0 } else { buf.row(from.row).len() };
+    } else if let Some((start, end, _)) = find_match(buf, &regex, from, backward, 1) {
+        if offset_target(buf, search.offset, start, end) == buf.cursor {
+            from = start;
+        }
+    }
+
+    let Some((start, end, wrapped)) = find_match(buf, &regex, from, backward, count) else {
+        let message = match (buf.options.wrapscan(), backward) {
+            (true, _) => format!("Pattern not found: {}", search.pattern),
+            (false, true) => format!("search hit TOP without match for: {}", search.pattern),
+            (false, false) => format!("search hit BOTTOM without match for: {}", search.pattern),
+        };
+        buf.show_error(message);
+        return None;
+    };
+
+    let (index, total) = match_count(buf, &regex, start);
+    buf.show_message(format!(
+        "{}{}  {}[{}/{}]",
+        if backward { '?' } else { '/' },
+        search.pattern,
+        if wrapped { "W " } else { "" },
+        index,
+        total
+    ));
+
+    Some(offset_target(buf, search.offset, start, end))
+}
+
+/// Where the offset of a search puts the cursor for a match from `start` to `end`
+fn offset_target(buf: &Buffer, offset: Offset, start: Position, end: usize) -> Position {
+    let line = buf.row(start.row);
+    match offset {
+        Offset::None => start,
+        Offset::Start(chars) => Position {
+            row: start.row,
+            col: move_chars(line, start.col, chars),
+        },
+        Offset::End(chars) => {
+            let last = line[..end]
+                .char_indices()
+                .next_back()
+                .filter(|(idx, _)| *idx >= start.col)
+                .map_or(start.col, |(idx, _)| idx);
+            Position {
+                row: start.row,
+                col: move_chars(line, last, chars),
+            }
+        }
+        Offset::Lines(lines) => Position {
+            row: min(
+                start.row.saturating_add_signed(lines),
+                buf.content.len() - 1,
+            ),
+            col: 0,
+        },
+    }
+}
+
+/// Finds the `count`th match after `from`, or before it going backward, wrapping around the end of
+/// the buffer with `wrapscan`. Returns the start and end of the match and whether it wrapped
+pub fn find_match(
+    buf: &Buffer,
+    regex: &Regex,
+    from: Position,
+    backward: bool,
+    count: usize,
+) -> Option<(Position, usize, bool)> {
+    let mut found = (from, from.col, false);
+
+    for _ in 0..count {
+        let (start, end, wrapped) = next_match(buf, regex, found.0, backward)?;
+        found = (start, end, found.2 || wrapped);
+    }
+
+    Some(found)
+}
+
+fn next_match(
+    buf: &Buffer,
+    regex: &Regex,
+    from: Position,
+    backward: bool,
+) -> Option<(Position, usize, bool)> {
+    let rows = buf.content.len();
+
+    for step in 0..=rows {
+        let (row, wrapped) = match backward {
+            true => ((from.row + rows - step % rows) % rows, step > from.row),
+            false => ((from.row + step) % rows, from.row + step >= rows),
+        };
+        if wrapped && !buf.options.wrapscan() {
+            return None;
+        }
+
+        // The line of the cursor is searched on both sides of it, first away from it and last
+        // after wrapping around
+        let mut matches = regex
+            .find_iter(buf.row(row))
+            .map(|found| found.range())
+            .filter(|found| match (step, backward) {
+                (0, true) => found.start < from.col,
+                (0, false) => found.start > from.col,
+                (step, true) if step == rows => found.start >= from.col,
+                (step, false) if step == rows => found.start <= from.col,
+                _ => true,
+            });
+        let found = match backward {
+            true => matches.last(),
+            false => matches.next(),
+        };
+
+        if let Some(found) = found {
+            return Some((
+                Position {
+                    row,
+                    col: found.start,
+                },
+                found.end,
+                wrapped,
+            ));
+        }
+    }
+
+    None
+}
+
+/// The number of the match starting at `start` and the number of matches in the buffer
+fn match_count(buf: &Buffer, regex: &Regex, start: Position) -> (usize, usize) {
+    let starts: Vec<(usize, usize)> = buf
+        .content
+        .iter()
+        .enumerate()
+        .flat_map(|(row, line)| regex.find_iter(line).map(move |found| (row, found.start())))
+        .collect();
+    let index = starts
+        .iter()
+        .filter(|found| **found <= (start.row, start.col))
+        .count();
+
+    (index, starts.len())
+}
+
+/// Moves `count` characters right from the byte column, or left for a negative count
+fn move_chars(line: &str, col: usize, count: isize) -> usize {
+    match count {
+        0.. => line[col..]
+            .char_indices()
+            .nth(count as usize)
+            .map_or(line.len(), |(idx, _)| col + idx),
+        _ => line[..col]
+            .char_indices()
+            .rev()
+            .nth(count.unsigned_abs() - 1)
+            .map_or(0, |(idx, _)| idx),
+    }
+}
+
+/// Splits the text typed at the prompt into the pattern and the offset after the first unescaped
+/// delimiter
+pub fn split_offset(input: &str, delimiter: char) -> (&str, &str) {
+    let mut escaped = false;
+
+    for (idx, char) in input.char_indices() {
+        match char {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            char if char == delimiter => return (&input[..idx], &input[idx + 1..]),
+            _ => {}
+        }
+    }
+
+    (input, "")
+}
+
+/// Compiles a Vim pattern, ignoring case with `ignorecase` unless `smartcase` is set and the
+/// pattern has uppercase letters, or `\c` or `\C` is used
+pub fn compile(pattern: &str, options: &Options) -> Result<Regex, String> {
+    let (regex, ignore_case) = translate(pattern)?;
+    let ignore_case = ignore_case
+        .unwrap_or(options.ignorecase() && !(options.smartcase() && has_uppercase(pattern)));
+
+    RegexBuilder::new(&regex)
+        .case_insensitive(ignore_case)
+        .build()
+        .map_err(|_| format!("Invalid pattern: {}", pattern))
+}
+
+fn has_uppercase(pattern: &str) -> bool {
+    let mut chars = pattern.chars();
+    while let Some(char) = chars.next() {
+        match char {
+            '\\' => {
+                chars.next();
+            }
+            char if char.is_uppercase() => return true,
+            _ => {}
+        }
+    }
+
+    false
+}
+
+/// Translates a pattern in Vim's magic syntax to a regex, returning whether `\c` or `\C` set the
+/// case to be ignored
+fn translate(pattern: &str) -> Result<(String, Option<bool>), String> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut regex = String::new();
+    let mut ignore_case = None;
+    // Whether an item begins here, where `^` is an anchor and `*` a literal
+    let mut at_start = true;
+    let mut idx = 0;
+
+    while idx < chars.len() {
+        let char = chars[idx];
+        idx += 1;
+        let starting = std::mem::replace(&mut at_start, false);
+
+        match char {
+            '\\' => {
+                let Some(&next) = chars.get(idx) else {
+                    regex.push_str("\\\\");
+                    break;
+                };
+                idx += 1;
+
+                match next {
+                    '(' | '|' => {
+                        regex.push(next);
+                        at_start = true;
+                    }
+                    ')' | '+' => regex.push(next),
+                    '?' | '=' => regex.push('?'),
+                    '{' => {
+                        let end = chars[idx..]
+                            .iter()
+                            .position(|char| *char == '}')
+                            .ok_or_else(|| format!("Missing }} in pattern: {}", pattern))?;
+                        let bounds: String = chars[idx..idx + end].iter().collect();
+                        idx += end + 1;
+                        regex.push_str(&quantifier(bounds.trim_end_matches('\\')));
+                    }
+                    '<' => regex.push_str("\\b{start}"),
+                    '>' => regex.push_str("\\b{end}"),
+                    's' => regex.push_str("[ \\t]"),
+                    'S' => regex.push_str("[^ \\t]"),
+                    'd' => regex.push_str("[0-9]"),
+                    'D' => regex.push_str("[^0-9]"),
+                    'w' => regex.push_str("[0-9A-Za-z_]"),
+                    'W' => regex.push_str("[^0-9A-Za-z_]"),
+                    'h' => regex.push_str("[A-Za-z_]"),
+                    'H' => regex.push_str("[^A-Za-z_]"),
+                    'a' => regex.push_str("[A-Za-z]"),
+                    'A' => regex.push_str("[^A-Za-z]"),
+                    'l' => regex.push_str("[a-z]"),
+                    'L' => regex.push_str("[^a-z]"),
+                    'u' => regex.push_str("[A-Z]"),
+                    'U' => regex.push_str("[^A-Z]"),
+                    'x' => regex.push_str("[0-9A-Fa-f]"),
+                    'X' => regex.push_str("[^0-9A-Fa-f]"),
+                    't' => regex.push_str("\\t"),
+                    'e' => regex.push_str("\\x1b"),
+                    // Lines are matched one at a time, so a line break would never be found
+                    'n' => {
+                        return Err(format!(
+                            "Multi-line patterns are not supported: {}",
+                            pattern
+                        ))
+                    }
+                    'c' => {
+                        ignore_case = Some(true);
+                        at_start = starting;
+                    }
+                    'C' => {
+                        ignore_case = ignore_case.or(Some(false));
+                        at_start = starting;
+                    }
+                    '1'..='9' => {
+                        return Err(format!("Backreferences are not supported: {}", pattern))
+                    }
+                    next => regex.push_str(&regex::escape(&next.to_string())),
+                }
+            }
+            '^' if starting => {
+                regex.push('^');
+                at_start = true;
+            }
+            '$' if ends_branch(&chars[idx..]) => regex.push('$'),
+            '*' if !starting => regex.push('*'),
+            '.' => regex.push('.'),
+            '[' => match class(&chars[idx..]) {
+                Some((class, len)) => {
+                    regex.push_str(&class);
+                    idx += len;
+                }
+                None => regex.push_str("\\["),
+            },
+            char => regex.push_str(&regex::escape(&char.to_string())),
+        }
+    }
+
+    Ok((regex, ignore_case))
+}
+
+/// Whether the pattern ends at the start of `rest`, so that `$` before it is an anchor
+fn ends_branch(rest: &[char]) -> bool {
+    matches!(rest, [] | ['\\', '|' | ')', ..])
+}
+
+/// Translates the bounds of `\{n,m}`, where a leading `-` matches as few as possible
+fn quantifier(bounds: &str) -> String {
+    let (lazy, bounds) = match bounds.strip_prefix('-') {
+        Some(bounds) => (true, bounds),
+        None => (false, bounds),
+    };
+
+    let quantifier = match bounds.split_once(',') {
+        None if bounds.is_empty() => String::from("*"),
+        None => format!("{{{}}}", bounds),
+        Some(("", max)) => format!("{{0,{}}}", max),
+        Some((min, max)) => format!("{{{},{}}}", min, max),
+    };
+
+    match lazy {
+        true => format!("{}?", quantifier),
+        false => quantifier,
+    }
+}
+
+/// Translates a character class following a `[`, returning it with the number of characters it
+/// took up, or `None` if it isn't closed
+fn class(chars: &[char]) -> Option<(String, usize)> {
+    let mut class = String::from("[");
+    let mut idx = 0;
+
+    if chars.first() == Some(&'^') {
+        class.push('^');
+        idx += 1;
+    }
+    if chars.get(idx) == Some(&']') {
+        class.push_str("\\]");
+        idx += 1;
+    }
+
+    while let Some(&char) = chars.get(idx) {
+        idx += 1;
+        match char {
+            ']' => {
+                class.push(']');
+                return Some((class, idx));
+            }
+            '\\' => {
+                let next = *chars.get(idx)?;
+                idx += 1;
+                match next {
+                    't' | 'n' | ']' | '^' | '-' | '\\' => {
+                        class.push('\\');
+                        class.push(next);
+                    }
+                    'e' => class.push_str("\\x1b"),
+                    next => {
+                        class.push_str("\\\\");
+                        class.push_str(&regex::escape(&next.to_string()));
+                    }
+                }
+            }
+            '[' | '&' | '~' => {
+                class.push('\\');
+                class.push(char);
+            }
+            char => class.push(char),
+        }
+    }
+
+    None
+}
+
+/// Highlights the matches of the last search with `hlsearch`, or of the pattern being typed with
+/// `incsearch`
+pub fn render_matches(buffer: &Buffer, area: Rect, buf: &mut TBuffer) {
+    let pattern = match (&buffer.search_prompt, &buffer.last_search) {
+        (Some(prompt), _) if buffer.options.incsearch() => {
+            split_offset(&buffer.keys, prompt.delimiter()).0
+        }
+        (_, Some(search)) if buffer.options.hlsearch() && buffer.highlight_search => {
+            &search.pattern
+        }
+        _ => return,
+    };
+    let Ok(regex) = compile(pattern, &buffer.options) else {
+        return;
+    };
+
+    for (idx, line) in visible_lines(buffer, area).iter().enumerate() {
+        let row = buffer.view.top + idx;
+        for found in regex.find_iter(line) {
+            for (col, _) in found.as_str().char_indices() {
+                let pos = Position {
+                    row,
+                    col: found.start() + col,
+                };
+                if let Some(cell) =
+                    screen_position(buffer, pos, area).and_then(|pos| buf.cell_mut(pos))
+                {
+                    cell.set_bg(Color::Yellow).set_fg(Color::Black);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enter(buf: &mut Buffer) {
+        buf.handle_keys(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+    }
+
+    #[test]
+    fn translates_patterns() {
+        assert_eq!(translate("a.b*").unwrap().0, "a.b*");
+        assert_eq!(translate("\\(ab\\)\\+").unwrap().0, "(ab)+");
+        assert_eq!(translate("\\<foo\\>").unwrap().0, "\\b{start}foo\\b{end}");
+        assert_eq!(translate("a\\{-1,}").unwrap().0, "a{1,}?");
+        assert_eq!(translate("^*a$").unwrap().0, "^\\*a$");
+        assert_eq!(translate("a(b)|c+").unwrap().0, "a\\(b\\)\\|c\\+");
+        assert_eq!(translate("[^]a]").unwrap().0, "[^\\]a]");
+        assert_eq!(
+            translate("\\cFoo").unwrap(),
+            (String::from("Foo"), Some(true))
+        );
+    }
+
+    #[test]
+    fn matches_case() {
+        let mut options = Options::default();
+        assert!(!compile("lorem", &options).unwrap().is_match("Lorem"));
+
+        options.set("ignorecase").unwrap();
+        assert!(compile("lorem", &options).unwrap().is_match("Lorem"));
+
+        options.set("smartcase").unwrap();
+        assert!(!compile("Lorem", &options).unwrap().is_match("LOREM"));
+        assert!(compile("lorem\\S", &options).unwrap().is_match("LOREM."));
+        assert!(!compile("lorem\\C", &options).unwrap().is_match("Lorem"));
+    }
+
+    #[test]
+    fn parses_offsets() {
+        assert_eq!(split_offset("a\\/b/e+1", '/'), ("a\\/b", "e+1"));
+        assert_eq!(Offset::parse("e+1"), Some(Offset::End(1)));
+        assert_eq!(Offset::parse("b-2"), Some(Offset::Start(-2)));
+        assert_eq!(Offset::parse("-"), Some(Offset::Lines(-1)));
+        assert_eq!(Offset::parse(""), Some(Offset::None));
+        assert_eq!(Offset::parse("x"), None);
+    }
+
+    #[test]
+    fn searches_and_repeats() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+
+        buf.input_keys("/ipsum");
+        assert_eq!(buf.mode, Mode::Search);
+        enter(&mut buf);
+        assert_eq!(buf.cursor, Position { row: 0, col: 6 });
+        assert_eq!(buf.mode, Mode::Normal);
+        assert_eq!(buf.message().content, "/ipsum  [1/2]");
+        assert_eq!(buf.register.get('/'), "ipsum");
+
+        buf.input_keys("n");
+        assert_eq!(buf.cursor, Position { row: 4, col: 6 });
+
+        buf.input_keys("n");
+        assert_eq!(buf.cursor, Position { row: 0, col: 6 });
+        assert_eq!(buf.message().content, "/ipsum  W [1/2]");
+
+        buf.input_keys("N");
+        assert_eq!(buf.cursor, Position { row: 4, col: 6 });
+
+        buf.input_keys("gg?nulla");
+        enter(&mut buf);
+        assert_eq!(buf.cursor, Position { row: 6, col: 0 });
+
+        buf.options.set("nowrapscan").unwrap();
+        buf.input_keys("gg");
+        buf.input_keys("n");
+        assert_eq!(buf.cursor, Position { row: 0, col: 0 });
+        assert_eq!(
+            buf.message().content,
+            "search hit TOP without match for: nulla"
+        );
+
+        buf.options.set("wrapscan").unwrap();
+        buf.input_keys("/xyz");
+        enter(&mut buf);
+        assert_eq!(buf.message().content, "Pattern not found: xyz");
+    }
+
+    #[test]
+    fn applies_offsets() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+
+        buf.input_keys("/amet/e+1");
+        enter(&mut buf);
+        assert_eq!(buf.cursor, Position { row: 0, col: 21 });
+
+        buf.input_keys("/elit/-1");
+        enter(&mut buf);
+        assert_eq!(buf.cursor, Position { row: 0, col: 0 });
+
+        buf.input_keys("3/nulla/s+2");
+        enter(&mut buf);
+        assert_eq!(buf.cursor, Position { row: 6, col: 2 });
+
+        buf.input_keys("//");
+        enter(&mut buf);
+        assert_eq!(buf.cursor, Position { row: 2, col: 10 });
+    }
+
+    #[test]
+    fn repeats_offsets_backward() {
+        let mut buf = Buffer::test(String::new());
+        buf.content = vec![String::from("foo abc foo")];
+
+        buf.input_keys("/foo/e");
+        enter(&mut buf);
+        assert_eq!(buf.cursor.col, 10);
+        buf.input_keys("N");
+        assert_eq!(buf.cursor.col, 2);
+        buf.input_keys("N");
+        assert_eq!(buf.cursor.col, 10);
+
+        buf.input_keys("?foo?e");
+        enter(&mut buf);
+        assert_eq!(buf.cursor.col, 2);
+
+        buf.input_keys("/foo/s+2");
+        enter(&mut buf);
+        assert_eq!(buf.cursor.col, 10);
+        buf.input_keys("N");
+        assert_eq!(buf.cursor.col, 2);
+    }
+
+    #[test]
+    fn searches_as_motion() {
+        let mut buf = Buffer::test(String::new());
+        buf.content = vec![String::from("foo bar baz"); 2];
+
+        buf.input_keys("d/bar");
+        enter(&mut buf);
+        assert_eq!(buf.content[0], "bar baz");
+
+        buf.input_keys("d/ba/e");
+        enter(&mut buf);
+        assert_eq!(buf.content[0], "z");
+
+        buf.input_keys("j.");
+        assert_eq!(buf.content[1], "r baz");
+    }
+
+    #[test]
+    fn searches_word_under_cursor() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+
+        buf.input_keys("*");
+        assert_eq!(buf.cursor, Position { row: 4, col: 0 });
+        assert_eq!(buf.register.get('/'), "\\<Lorem\\>");
+
+        buf.input_keys("n");
+        assert_eq!(buf.cursor, Position { row: 0, col: 0 });
+
+        buf.input_keys("G#");
+        assert_eq!(buf.cursor, Position { row: 2, col: 10 });
+
+        buf.input_keys("Gg#");
+        assert_eq!(buf.cursor, Position { row: 2, col: 49 });
+
+        buf.input_keys("N");
+        assert_eq!(buf.cursor, Position { row: 6, col: 0 });
+
+        buf.content[5] = String::from("a.b a.b");
+        buf.cursor = Position { row: 5, col: 1 };
+        buf.input_keys("g*");
+        assert_eq!(buf.cursor, Position { row: 5, col: 6 });
+        assert_eq!(buf.register.get('/'), "b");
+
+        buf.content[5] = String::from("  .. ..");
+        buf.cursor = Position { row: 5, col: 0 };
+        buf.input_keys("*");
+        assert_eq!(buf.cursor, Position { row: 5, col: 5 });
+        assert_eq!(buf.register.get('/'), "\\.\\.");
+    }
+
+    #[test]
+    fn previews_and_cancels() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.options.set("incsearch").unwrap();
+
+        buf.input_keys("/mor");
+        assert_eq!(buf.cursor, Position { row: 3, col: 32 });
+
+        buf.handle_keys(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        assert_eq!(buf.cursor, Position { row: 0, col: 0 });
+        assert_eq!(buf.mode, Mode::Normal);
+        assert_eq!(buf.last_search, None);
+    }
+
+    #[test]
+    fn highlights_matches() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        let area = Rect::new(0, 0, 20, 3);
+        let mut screen = TBuffer::empty(area);
+        buf.options.set("hlsearch").unwrap();
+
+        buf.input_keys("/ip");
+        enter(&mut buf);
+        render_matches(&buf, area, &mut screen);
+        assert_eq!(screen[(6, 0)].bg, Color::Yellow);
+        assert_eq!(screen[(7, 0)].bg, Color::Yellow);
+        assert_eq!(screen[(8, 0)].bg, Color::Reset);
+
+        buf.input_keys(":nohlsearch");
+        enter(&mut buf);
+        let mut screen = TBuffer::empty(area);
+        render_matches(&buf, area, &mut screen);
+        assert_eq!(screen[(6, 0)].bg, Color::Reset);
+    }
+}
@@ -0,0 +1,638 @@
+use std::{cmp::min, ops::Range};
+
+use ratatui::{
+    buffer::Buffer as TBuffer,
+    crossterm::event::{KeyCode, KeyEvent, KeyModifiers},
+    layout::Rect,
+    style::Color,
+};
+use regex::{Captures, Regex};
+
+use crate::{
+    buffer::{Buffer, Position},
+    ex::{is_delimiter, split_pattern, ExCommand, LineRange},
+    navigation::first_not_whitespace,
+    search::{compile, Offset, Search},
+    utils::split_count,
+    view::{screen_position, scroll_lines},
+};
+
+/// The last `:s`, repeated by `&`, `g&` and `:&&`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Substitute {
+    pub pattern: String,
+    pub replacement: String,
+    pub flags: Flags,
+}
+
+/// The flags given after a substitution, like the `gc` in `:s/a/b/gc`
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Flags {
+    pub global: bool,
+    pub confirm: bool,
+    /// Set by `i` and `I` to ignore case or not, whatever the options say
+    pub ignore_case: Option<bool>,
+    /// Only counts the matches, as `n` does
+    pub count_only: bool,
+    /// Doesn't report a pattern without matches as an error, as `e` does
+    pub no_error: bool,
+}
+
+impl Flags {
+    /// Parses flags, where a leading `&` keeps the flags of the last substitution
+    fn parse(flags: &str, last: Flags) -> (Self, &str) {
+        let (mut parsed, flags) = match flags.strip_prefix('&') {
+            Some(rest) => (last, rest),
+            None => (Flags::default(), flags),
+        };
+
+        let end = flags
+            .find(|char: char| !"gciIne".contains(char))
+            .unwrap_or(flags.len());
+        for flag in flags[..end].chars() {
+            match flag {
+                'g' => parsed.global = true,
+                'c' => parsed.confirm = true,
+                'i' => parsed.ignore_case = Some(true),
+                'I' => parsed.ignore_case = Some(false),
+                'n' => parsed.count_only = true,
+                _ => parsed.no_error = true,
+            }
+        }
+
+        (parsed, &flags[end..])
+    }
+}
+
+/// A substitution in progress, kept while the `c` flag waits for an answer
+#[derive(Clone, Debug)]
+pub struct Substitution {
+    regex: Regex,
+    pattern: String,
+    replacement: String,
+    flags: Flags,
+    /// Where to look for the next match
+    from: Position,
+    last_row: usize,
+    /// The match waiting for confirmation, as its row and byte range
+    current: Option<(usize, Range<usize>)>,
+    /// Set when `a` answered to replace all remaining matches
+    all: bool,
+    matched: bool,
+    /// The number of substitutions, or matches with the `n` flag, and the lines they were on
+    found: usize,
+    lines: usize,
+    counted_row: Option<usize>,
+}
+
+/// Runs `:s/pattern/replacement/flags count`, or repeats the last substitution when there is no
+/// pattern, as in `:s g`
+pub fn substitute(buf: &mut Buffer, command: &ExCommand) -> Result<(), String> {
+    let args = command.args.as_str();
+    let Some(delim) = args
+        .chars()
+        .next()
+        .filter(|char| is_delimiter(*char) && *char != '&')
+    else {
+        return repeat_substitute(buf, command.lines(buf), args, false);
+    };
+
+    let (pattern, rest) = split_pattern(&args[delim.len_utf8()..], delim);
+    let (replacement, rest) = split_pattern(rest, delim);
+    let last = buf.last_substitute.as_ref();
+    let (flags, rest) = Flags::parse(rest, last.map_or(Flags::default(), |last| last.flags));
+
+    let replacement = expand_tilde(&replacement, last.map_or("", |last| &last.replacement));
+    let range = with_count(buf, command.lines(buf), rest)?;
+    start(buf, pattern, replacement, flags, range)
+}
+
+/// Runs `:&` or `:&&`, repeating the last substitution on the range with new flags, or the same
+/// ones with `&&`
+pub fn repeat_command(buf: &mut Buffer, command: &ExCommand) -> Result<(), String> {
+    repeat_substitute(buf, command.lines(buf), &command.args, false)
+}
+
+/// Repeats the last substitution with `&` on the cursor line, or `g&` on all lines with the same
+/// flags and the last search pattern
+pub fn repeat_last(buf: &mut Buffer, everywhere: bool) {
+    let result = match everywhere {
+        true => {
+            let range = LineRange {
+                start: 0,
+                end: buf.content.len() - 1,
+            };
+            repeat_substitute(buf, range, "&", true)
+        }
+        false => {
+            let row = buf.cursor.row;
+            repeat_substitute(
+                buf,
+                LineRange {
+                    start: row,
+                    end: row,
+                },
+                "",
+                false,
+            )
+        }
+    };
+
+    if let Err(message) = result {
+        buf.show_error(message);
+    }
+}
+
+fn repeat_substitute(
+    buf: &mut Buffer,
+    range: LineRange,
+    args: &str,
+    search_pattern: bool,
+) -> Result<(), String> {
+    let last = buf
+        .last_substitute
+        .clone()
+        .ok_or("No previous substitute regular expression")?;
+    let (flags, rest) = Flags::parse(args.trim_start(), last.flags);
+    let range = with_count(buf, range, rest)?;
+
+    let pattern = match search_pattern {
+        true => String::new(),
+        false => last.pattern,
+    };
+    start(buf, pattern, last.replacement, flags, range)
+}
+
+/// Applies a count after the flags, making the range that many lines from its end
+fn with_count(buf: &Buffer, range: LineRange, rest: &str) -> Result<LineRange, String> {
+    let rest = rest.trim();
+    match split_count(rest) {
+        (Some(count), "") => Ok(LineRange {
+            start: range.end,
+            end: min(range.end + count - 1, buf.content.len() - 1),
+        }),
+        (_, "") => Ok(range),
+        (_, trailing) => Err(format!("Trailing characters: {}", trailing)),
+    }
+}
+
+/// Replaces `~` in a replacement with the previous one, as Vim does unless it's escaped
+fn expand_tilde(replacement: &str, previous: &str) -> String {
+    let mut expanded = String::new();
+    let mut chars = replacement.chars();
+
+    while let Some(char) = chars.next() {
+        match char {
+            '\\' => {
+                expanded.push('\\');
+                expanded.extend(chars.next());
+            }
+            '~' => expanded.push_str(previous),
+            char => expanded.push(char),
+        }
+    }
+
+    expanded
+}
+
+fn start(
+    buf: &mut Buffer,
+    pattern: String,
+    replacement: String,
+    flags: Flags,
+    range: LineRange,
+) -> Result<(), String> {
+    // An empty pattern uses the last search
+    let pattern = match pattern.as_str() {
+        "" => buf
+            .last_search
+            .as_ref()
+            .map(|search| search.pattern.clone())
+            .ok_or("No previous regular expression")?,
+        _ => pattern,
+    };
+    let regex = match flags.ignore_case {
+        Some(true) => compile(&format!("{}\\c", pattern), &buf.options)?,
+        Some(false) => compile(&format!("{}\\C", pattern), &buf.options)?,
+        None => compile(&pattern, &buf.options)?,
+    };
+
+    buf.register.set('/', pattern.clone());
+    buf.last_search = Some(Search {
+        pattern: pattern.clone(),
+        backward: false,
+        offset: Offset::None,
+    });
+    buf.highlight_search = true;
+    buf.last_substitute = Some(Substitute {
+        pattern: pattern.clone(),
+        replacement: replacement.clone(),
+        flags,
+    });
+
+    let substitution = Substitution {
+        regex,
+        pattern,
+        replacement,
+        flags,
+        from: Position {
+            row: range.start,
+            col: 0,
+        },
+        last_row: range.end,
+        current: None,
+        all: false,
+        matched: false,
+        found: 0,
+        lines: 0,
+        counted_row: None,
+    };
+    advance(buf, substitution)
+}
+
+/// Handles the matches up to the next one that needs confirming, or finishes
+fn advance(buf: &mut Buffer, mut sub: Substitution) -> Result<(), String> {
+    while let Some((row, range)) = sub.next_match(buf) {
+        sub.matched = true;
+        if sub.flags.confirm && !sub.all && !sub.flags.count_only {
+            buf.cursor = Position {
+                row,
+                col: range.start,
+            };
+            buf.show_message(format!(
+                "replace with {} (y/n/a/q/l/^E/^Y)?",
+                sub.replacement
+            ));
+            sub.current = Some((row, range));
+            buf.substitution = Some(sub);
+            return Ok(());
+        }
+
+        match sub.flags.count_only {
+            true => {
+                sub.count(row);
+                sub.skip(buf, row, range);
+            }
+            false => sub.replace(buf, row, range),
+        }
+    }
+
+    finish(buf, &sub)
+}
+
+/// Answers the prompt of a substitution with the `c` flag
+pub fn handle_confirm_keys(buf: &mut Buffer, event: KeyEvent) {
+    let Some(mut sub) = buf.substitution.take() else {
+        return;
+    };
+    let Some((row, range)) = sub.current.take() else {
+        return;
+    };
+
+    let ctrl = event.modifiers.contains(KeyModifiers::CONTROL);
+    let result = match event.code {
+        KeyCode::Char('e') if ctrl => return keep_asking(buf, sub, row, range, true),
+        KeyCode::Char('y') if ctrl => return keep_asking(buf, sub, row, range, false),
+        KeyCode::Char('y') => {
+            sub.replace(buf, row, range);
+            advance(buf, sub)
+        }
+        KeyCode::Char('n') => {
+            sub.skip(buf, row, range);
+            advance(buf, sub)
+        }
+        KeyCode::Char('a') => {
+            sub.all = true;
+            sub.replace(buf, row, range);
+            advance(buf, sub)
+        }
+        KeyCode::Char('l') => {
+            sub.replace(buf, row, range);
+            finish(buf, &sub)
+        }
+        KeyCode::Char('q') | KeyCode::Esc => finish(buf, &sub),
+        _ => return keep_asking(buf, sub, row, range, false),
+    };
+
+    if let Err(message) = result {
+        buf.show_error(message);
+    }
+}
+
+/// Puts the prompt back after a key that doesn't answer it, scrolling for `Ctrl-e` and `Ctrl-y`
+fn keep_asking(
+    buf: &mut Buffer,
+    mut sub: Substitution,
+    row: usize,
+    range: Range<usize>,
+    downward: bool,
+) {
+    if downward || buf.view.top > 0 {
+        scroll_lines(buf, downward, 1);
+    }
+    sub.current = Some((row, range));
+    buf.substitution = Some(sub);
+}
+
+fn finish(buf: &mut Buffer, sub: &Substitution) -> Result<(), String> {
+    if !sub.matched {
+        return match sub.flags.no_error {
+            true => Ok(()),
+            false => Err(format!("Pattern not found: {}", sub.pattern)),
+        };
+    }
+    if sub.found == 0 {
+        // Every match was skipped, which leaves nothing to report but the prompt to clear
+        buf.show_message("");
+        return Ok(());
+    }
+
+    let noun = match (sub.flags.count_only, sub.found) {
+        (true, 1) => "match",
+        (true, _) => "matches",
+        (false, 1) => "substitution",
+        (false, _) => "substitutions",
+    };
+    let lines = match sub.lines {
+        1 => "line",
+        _ => "lines",
+    };
+    buf.show_message(format!("{} {} on {} {}", sub.found, noun, sub.lines, lines));
+
+    if let (false, Some(row)) = (sub.flags.count_only, sub.counted_row) {
+        buf.cursor = Position {
+            row,
+            col: first_not_whitespace(buf.row(row)),
+        };
+    }
+
+    Ok(())
+}
+
+impl Substitution {
+    /// The next match from where the last one ended, only one per line without the `g` flag
+    fn next_match(&self, buf: &Buffer) -> Option<(usize, Range<usize>)> {
+        let mut from = self.from;
+
+        while from.row <= self.last_row {
+            let line = buf.row(from.row);
+            if from.col <= line.len() {
+                if let Some(found) = self.regex.find_at(line, from.col) {
+                    return Some((from.row, found.range()));
+                }
+            }
+            from = Position {
+                row: from.row + 1,
+                col: 0,
+            };
+        }
+
+        None
+    }
+
+    fn replace(&mut self, buf: &mut Buffer, row: usize, range: Range<usize>) {
+        let line = buf.row(row).clone();
+        let Some(captures) = self.regex.captures_at(&line, range.start) else {
+            return;
+        };
+        let text = expand(&self.replacement, &captures);
+
+        let mut lines = text.split('\n');
+        let first = lines.next().unwrap_or_default();
+        let mut rows = vec![format!("{}{}", &line[..range.start], first)];
+        rows.extend(lines.map(String::from));
+        let last = rows.len() - 1;
+        let col = rows[last].len();
+        rows[last].push_str(&line[range.end..]);
+
+        // The line keeps its place and the lines it splits into follow it
+        let mut rows = rows.into_iter();
+        buf.content[row] = rows.next().unwrap();
+        buf.insert_lines(row + 1, rows);
+        self.last_row += last;
+        self.count(row);
+        self.counted_row = Some(row + last);
+        self.step_past(buf, row + last, col, range.is_empty());
+    }
+
+    fn skip(&mut self, buf: &Buffer, row: usize, range: Range<usize>) {
+        self.step_past(buf, row, range.end, range.is_empty());
+    }
+
+    /// Counts a match, and its line unless the last match was on it too
+    fn count(&mut self, row: usize) {
+        self.found += 1;
+        if self.counted_row != Some(row) {
+            self.lines += 1;
+        }
+        self.counted_row = Some(row);
+    }
+
+    /// Continues after a match ending at `col`, on the next line without the `g` flag. An empty
+    /// match steps over a character so the same spot isn't matched again
+    fn step_past(&mut self, buf: &Buffer, row: usize, col: usize, empty: bool) {
+        let line = buf.row(row);
+        let step = match empty {
+            true => line[col..].chars().next().map_or(1, char::len_utf8),
+            false => 0,
+        };
+
+        self.from = match self.flags.global && col + step <= line.len() {
+            true => Position {
+                row,
+                col: col + step,
+            },
+            false => Position {
+                row: row + 1,
+                col: 0,
+            },
+        };
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Case {
+    Upper,
+    Lower,
+}
+
+/// Builds the replacement for a match: `&` and `\0` insert the match, `\1` to `\9` its groups,
+/// `\u` and `\l` change the case of the next character and `\U` and `\L` of all following ones
+/// up to `\E`. `\r` breaks the line
+fn expand(replacement: &str, captures: &Captures) -> String {
+    let mut text = String::new();
+    let mut next_case = None;
+    let mut case = None;
+
+    let mut chars = replacement.chars();
+    while let Some(char) = chars.next() {
+        match char {
+            '&' => push_cased(&mut text, &captures[0], &mut next_case, case),
+            '\\' => match chars.next() {
+                Some(digit @ '0'..='9') => {
+                    let group = captures
+                        .get(digit.to_digit(10).unwrap() as usize)
+                        .map_or("", |group| group.as_str());
+                    push_cased(&mut text, group, &mut next_case, case);
+                }
+                Some('u') => next_case = Some(Case::Upper),
+                Some('l') => next_case = Some(Case::Lower),
+                Some('U') => case = Some(Case::Upper),
+                Some('L') => case = Some(Case::Lower),
+                Some('E' | 'e') => {
+                    case = None;
+                    next_case = None;
+                }
+                Some('r') => text.push('\n'),
+                Some('n') => text.push('\0'),
+                Some('t') => text.push('\t'),
+                Some(char) => push_cased(&mut text, &char.to_string(), &mut next_case, case),
+                None => text.push('\\'),
+            },
+            char => push_cased(&mut text, &char.to_string(), &mut next_case, case),
+        }
+    }
+
+    text
+}
+
+/// Adds text to a replacement, changing the case of its first character for `\u` or `\l` and of
+/// all of them for `\U` or `\L`
+fn push_cased(text: &mut String, insert: &str, next_case: &mut Option<Case>, case: Option<Case>) {
+    for char in insert.chars() {
+        match next_case.take().or(case) {
+            Some(Case::Upper) => text.extend(char.to_uppercase()),
+            Some(Case::Lower) => text.extend(char.to_lowercase()),
+            None => text.push(char),
+        }
+    }
+}
+
+/// Highlights the match a substitution with the `c` flag asks about
+pub fn render_confirm(buffer: &Buffer, area: Rect, buf: &mut TBuffer) {
+    let Some((row, range)) = buffer
+        .substitution
+        .as_ref()
+        .and_then(|sub| sub.current.clone())
+    else {
+        return;
+    };
+
+    for (col, _) in buffer.row(row)[range.clone()].char_indices() {
+        let pos = Position {
+            row,
+            col: range.start + col,
+        };
+        if let Some(cell) = screen_position(buffer, pos, area).and_then(|pos| buf.cell_mut(pos)) {
+            cell.set_bg(Color::Red).set_fg(Color::White);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(buf: &mut Buffer, command: &str) {
+        buf.input_keys(command);
+        buf.handle_keys(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+    }
+
+    #[test]
+    fn substitutes_with_flags() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+
+        run(&mut buf, ":%s/nulla/X/g");
+        assert_eq!(
+            buf.content[2],
+            "Ridiculus X consectetur proin purus ad justo Xm. "
+        );
+        assert_eq!(buf.content[6], "X");
+        assert_eq!(buf.message().content, "3 substitutions on 2 lines");
+        assert_eq!(buf.cursor, Position { row: 6, col: 0 });
+
+        run(&mut buf, ":%s/lorem/Y/n");
+        assert_eq!(buf.message().content, "Pattern not found: lorem");
+        run(&mut buf, ":%s/lorem/Y/in");
+        assert_eq!(buf.message().content, "2 matches on 2 lines");
+        assert_eq!(buf.content[0], "Lorem ipsum odor amet, ");
+
+        run(&mut buf, ":1s/zzz/Y/e");
+        assert_eq!(buf.message().content, "2 matches on 2 lines");
+        run(&mut buf, ":1s/o/0/g x");
+        assert_eq!(buf.message().content, "Trailing characters: x");
+        run(&mut buf, r":%s/\n//");
+        assert_eq!(
+            buf.message().content,
+            "Multi-line patterns are not supported: \\n"
+        );
+    }
+
+    #[test]
+    fn expands_replacements() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+
+        run(&mut buf, r":s/\(\w\+\) \(\w\+\)/\2 \1/");
+        assert_eq!(buf.content[0], "ipsum Lorem odor amet, ");
+        run(&mut buf, r":s/odor/\U&\E!/");
+        assert_eq!(buf.content[0], "ipsum Lorem ODOR! amet, ");
+        run(&mut buf, r":s/amet/\u&~/");
+        assert_eq!(buf.content[0], "ipsum Lorem ODOR! AmetAMET!, ");
+        run(&mut buf, r":s#/\|, #\r#");
+        assert_eq!(buf.content[0], "ipsum Lorem ODOR! AmetAMET!");
+        assert_eq!(buf.content[1], "");
+        assert_eq!(buf.content.len(), 8);
+    }
+
+    #[test]
+    fn confirms_each_match() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+
+        run(&mut buf, ":%s/nulla/N/gc");
+        assert_eq!(buf.cursor, Position { row: 2, col: 10 });
+        assert_eq!(buf.message().content, "replace with N (y/n/a/q/l/^E/^Y)?");
+
+        buf.input_keys("yn");
+        assert_eq!(buf.cursor, Position { row: 6, col: 0 });
+        buf.input_keys("y");
+        assert!(buf.substitution.is_none());
+        assert_eq!(
+            buf.content[2],
+            "Ridiculus N consectetur proin purus ad justo nullam. "
+        );
+        assert_eq!(buf.content[6], "N");
+        assert_eq!(buf.message().content, "2 substitutions on 2 lines");
+
+        run(&mut buf, ":%s/o/0/c");
+        buf.input_keys("l");
+        assert_eq!(buf.content[0], "L0rem ipsum odor amet, ");
+        assert_eq!(buf.message().content, "1 substitution on 1 line");
+
+        run(&mut buf, ":%s/N/x/c");
+        buf.input_keys("nn");
+        assert!(buf.substitution.is_none());
+        assert_eq!(buf.message().content, "");
+    }
+
+    #[test]
+    fn repeats_substitutions() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+
+        run(&mut buf, ":s/o/0/g");
+        buf.input_keys("j&");
+        assert_eq!(buf.content[1], "c0nsectetuer adipiscing elit. ");
+
+        run(&mut buf, ":s/e/E/g");
+        buf.input_keys("j");
+        run(&mut buf, ":&&");
+        assert_eq!(
+            buf.content[2],
+            "Ridiculus nulla consEctEtur proin purus ad justo nullam. "
+        );
+
+        run(&mut buf, "/a");
+        buf.input_keys("g&");
+        assert_eq!(buf.content[3], "MEuris vitEe semper vErius eros morbi.");
+        assert_eq!(buf.content[4], "Lorem.ipsum");
+    }
+}
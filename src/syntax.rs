@@ -0,0 +1,69 @@
+use std::{ops::Range, path::Path};
+
+/// What little is known about the syntax of a file from its extension, used to skip strings and
+/// comments
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Syntax {
+    comment: &'static str,
+}
+
+impl Syntax {
+    pub fn for_file(filename: &str) -> Option<Self> {
+        let extension = Path::new(filename).extension()?.to_str()?;
+        let comment = match extension {
+            "rs" | "c" | "h" | "cpp" | "hpp" | "cc" | "js" | "ts" | "go" | "java" | "cs" => "//",
+            "py" | "sh" | "bash" | "rb" | "toml" | "yaml" | "yml" | "pl" => "#",
+            "lua" | "sql" | "hs" => "--",
+            _ => return None,
+        };
+
+        Some(Syntax { comment })
+    }
+
+    /// The byte ranges of the line that are inside double quoted strings or a line comment
+    pub fn strings_and_comments(&self, line: &str) -> Vec<Range<usize>> {
+        let mut ranges = Vec::new();
+        let mut string_start = None;
+        let mut escaped = false;
+
+        for (idx, char) in line.char_indices() {
+            match string_start {
+                Some(start) => {
+                    if char == '"' && !escaped {
+                        ranges.push(start..idx + 1);
+                        string_start = None;
+                    }
+                    escaped = char == '\\' && !escaped;
+                }
+                None if line[idx..].starts_with(self.comment) => {
+                    ranges.push(idx..line.len());
+                    return ranges;
+                }
+                None if char == '"' => string_start = Some(idx),
+                None => {}
+            }
+        }
+
+        if let Some(start) = string_start {
+            ranges.push(start..line.len());
+        }
+
+        ranges
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_strings_and_comments() {
+        let syntax = Syntax::for_file("main.rs").unwrap();
+
+        assert_eq!(
+            syntax.strings_and_comments(r#"f("(\"", x) // ("#),
+            vec![2..7, 12..16]
+        );
+        assert_eq!(Syntax::for_file("notes.txt"), None);
+    }
+}
@@ -6,3 +6,15 @@ pub(crate) fn split_first_char(string: &str) -> (char, String) {
 
     (prefix, remaining)
 }
+
+pub(crate) fn split_count(string: &str) -> (Option<usize>, &str) {
+    if string.starts_with('0') {
+        return (None, string);
+    }
+
+    let idx = string
+        .find(|char: char| !char.is_ascii_digit())
+        .unwrap_or(string.len());
+
+    (string[..idx].parse().ok(), &string[idx..])
+}
@@ -0,0 +1,403 @@
+use std::cmp::{max, min};
+
+use ratatui::{
+    layout::{Position as TuiPosition, Rect},
+    text::Span,
+};
+
+use crate::{
+    buffer::{Buffer, Position},
+    navigation::{clamp_col, goto_line},
+};
+
+/// The part of a buffer that is visible, as the first line and the first display column shown,
+/// and the number of lines and columns that fit on the screen
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct View {
+    pub top: usize,
+    pub left: usize,
+    pub height: usize,
+    pub width: usize,
+    /// The number of lines the message area had, which a long message is paged by
+    pub message_height: usize,
+    /// The display column vertical motions return to, with the cursor position it belongs to. It
+    /// no longer applies once the cursor has moved elsewhere, and `usize::MAX` sticks to the end
+    /// of the line
+    pub desired_col: Option<(Position, usize)>,
+}
+
+/// Where `zt`, `zz` and `zb` put the cursor line
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ViewAt {
+    Top,
+    Center,
+    Bottom,
+}
+
+/// Scrolls the view of the buffer so the cursor is visible in `area`, keeping `scrolloff` lines
+/// and `sidescrolloff` columns around it where possible
+pub fn scroll_to_cursor(buf: &mut Buffer, area: Rect) {
+    let (height, width) = (area.height as usize, area.width as usize);
+    if height == 0 || width == 0 {
+        return;
+    }
+    buf.view.height = height;
+    buf.view.width = width;
+
+    let row = buf.cursor.row;
+    let scrolloff = scrolloff(buf);
+    if row < buf.view.top + scrolloff {
+        buf.view.top = row.saturating_sub(scrolloff);
+    } else if row + scrolloff >= buf.view.top + height {
+        buf.view.top = row + scrolloff + 1 - height;
+    }
+
+    let col = display_col(buf.row(row), buf.cursor.col);
+    let sidescrolloff = min(buf.options.sidescrolloff(), (width - 1) / 2);
+    if col < buf.view.left + sidescrolloff {
+        buf.view.left = col.saturating_sub(sidescrolloff);
+    } else if col + sidescrolloff >= buf.view.left + width {
+        buf.view.left = col + sidescrolloff + 1 - width;
+    }
+}
+
+/// Scrolls the view by `count` lines, as `Ctrl-e` and `Ctrl-y` do, moving the cursor if it would
+/// leave the screen
+pub fn scroll_lines(buf: &mut Buffer, down: bool, count: usize) {
+    let last = buf.content.len() - 1;
+    buf.view.top = match down {
+        true => min(buf.view.top + count, last),
+        false => buf.view.top.saturating_sub(count),
+    };
+
+    keep_cursor_in_view(buf);
+}
+
+/// Scrolls the view and the cursor by the `scroll` option or half a screen, as `Ctrl-d` and
+/// `Ctrl-u` do. A count sets the `scroll` option first
+pub fn scroll_half_page(buf: &mut Buffer, down: bool, count: Option<usize>) {
+    if let Some(count) = count {
+        buf.options.set_scroll(count);
+    }
+    let amount = match buf.options.scroll() {
+        0 => max(height(buf) / 2, 1),
+        scroll => scroll,
+    };
+
+    let last = buf.content.len() - 1;
+    let row = match down {
+        true => min(buf.cursor.row + amount, last),
+        false => buf.cursor.row.saturating_sub(amount),
+    };
+    buf.view.top = match down {
+        true => min(
+            buf.view.top + amount,
+            buf.content.len().saturating_sub(height(buf)),
+        ),
+        false => buf.view.top.saturating_sub(amount),
+    };
+    goto_row(buf, row);
+
+    keep_cursor_in_view(buf);
+}
+
+/// Scrolls the view by `count` screens minus two lines, as `Ctrl-f` and `Ctrl-b` do
+pub fn scroll_page(buf: &mut Buffer, down: bool, count: usize) {
+    let amount = max(height(buf), 3) - 2;
+    let last = buf.content.len() - 1;
+
+    for _ in 0..count {
+        buf.view.top = match down {
+            true => min(buf.view.top + amount, last),
+            false => buf.view.top.saturating_sub(amount),
+        };
+    }
+
+    let row = buf.cursor.row;
+    keep_cursor_in_view(buf);
+    if buf.cursor.row != row {
+        goto_row(buf, buf.cursor.row);
+    }
+}
+
+/// Scrolls the view so the cursor line is at the top, the center or the bottom of the screen
+pub fn reposition(buf: &mut Buffer, at: ViewAt) {
+    let row = buf.cursor.row;
+    buf.view.top = match at {
+        ViewAt::Top => row,
+        ViewAt::Center => row.saturating_sub((height(buf) - 1) / 2),
+        ViewAt::Bottom => (row + 1).saturating_sub(height(buf)),
+    };
+}
+
+/// The line `count` lines from the top or the bottom of the screen for `H` and `L`, or the middle
+/// line for `M`, staying `scrolloff` lines away from the edges
+pub fn screen_line(buf: &Buffer, at: ViewAt, count: usize) -> Position {
+    let last = buf.content.len() - 1;
+    let top = min(buf.view.top, last);
+    let bottom = min(top + height(buf) - 1, last);
+    let scrolloff = scrolloff(buf);
+
+    let row = match at {
+        ViewAt::Top if top > 0 => max(top + count - 1, top + scrolloff),
+        ViewAt::Top => top + count - 1,
+        ViewAt::Center => top + (bottom - top) / 2,
+        ViewAt::Bottom if bottom < last => min(
+            bottom.saturating_sub(count - 1),
+            bottom.saturating_sub(scrolloff),
+        ),
+        ViewAt::Bottom => bottom.saturating_sub(count - 1),
+    };
+
+    goto_line(buf, row.clamp(top, bottom) + 1)
+}
+
+/// The position half a screen width to the right of the first column shown, as `gm` goes to
+pub fn screen_middle(buf: &Buffer) -> Position {
+    let line = buf.row(buf.cursor.row);
+    Position {
+        row: buf.cursor.row,
+        col: col_at_display(line, buf.view.left + buf.view.width / 2),
+    }
+}
+
+/// Moves the cursor to the row like `goto_line`, keeping the desired column without `startofline`
+fn goto_row(buf: &mut Buffer, row: usize) {
+    let desired = desired_col(buf);
+    buf.cursor = goto_line(buf, row + 1);
+    if !buf.options.startofline() {
+        remember_col(buf, desired);
+    }
+}
+
+/// Moves the cursor onto the screen after scrolling, keeping `scrolloff` lines around it
+fn keep_cursor_in_view(buf: &mut Buffer) {
+    let last = buf.content.len() - 1;
+    let scrolloff = scrolloff(buf);
+    let bottom = buf.view.top + height(buf) - 1;
+
+    let first = match buf.view.top {
+        0 => 0,
+        top => top + scrolloff,
+    };
+    let limit = match bottom >= last {
+        true => last,
+        false => bottom - scrolloff,
+    };
+
+    let row = buf
+        .cursor
+        .row
+        .clamp(min(first, last), max(min(first, last), limit));
+    if row != buf.cursor.row {
+        let desired = desired_col(buf);
+        buf.cursor.col = col_on_row(buf, row);
+        buf.cursor.row = row;
+        remember_col(buf, desired);
+    }
+}
+
+fn height(buf: &Buffer) -> usize {
+    max(buf.view.height, 1)
+}
+
+fn scrolloff(buf: &Buffer) -> usize {
+    min(buf.options.scrolloff(), (height(buf) - 1) / 2)
+}
+
+/// The screen cell showing the buffer position in `area`, or `None` if it is scrolled out of view
+pub fn screen_position(buf: &Buffer, pos: Position, area: Rect) -> Option<TuiPosition> {
+    let line = buf.content.get(pos.row)?;
+    let row = pos.row.checked_sub(buf.view.top)?;
+    let col = display_col(line, pos.col).checked_sub(buf.view.left)?;
+
+    if row >= area.height as usize || col >= area.width as usize {
+        return None;
+    }
+
+    Some(TuiPosition {
+        x: area.x + col as u16,
+        y: area.y + row as u16,
+    })
+}
+
+/// The lines of the buffer that are visible in `area`
+pub fn visible_lines(buf: &Buffer, area: Rect) -> &[String] {
+    let top = min(buf.view.top, buf.content.len());
+    let bottom = min(top + area.height as usize, buf.content.len());
+    &buf.content[top..bottom]
+}
+
+/// The display column a vertical motion from the cursor aims for, the cursor's own unless one was
+/// remembered at the cursor position
+pub fn desired_col(buf: &Buffer) -> usize {
+    match buf.view.desired_col {
+        Some((pos, col)) if pos == buf.cursor => col,
+        _ => display_col(buf.row(buf.cursor.row), buf.cursor.col),
+    }
+}
+
+/// Remembers the display column to return to while the cursor stays where it is
+pub fn remember_col(buf: &mut Buffer, col: usize) {
+    buf.view.desired_col = Some((buf.cursor, col));
+}
+
+/// The byte column on `row` that is closest to the desired column where the cursor can be
+pub fn col_on_row(buf: &Buffer, row: usize) -> usize {
+    let line = buf.row(row);
+    let col = match desired_col(buf) {
+        usize::MAX => line.len(),
+        display => virtual_col(line, display),
+    };
+    clamp_col(buf, row, col)
+}
+
+/// Like `col_at_display`, but a display column past the end of the line gives a virtual column
+/// just as far past it
+pub fn virtual_col(line: &str, display: usize) -> usize {
+    let width = display_col(line, line.len());
+    match display.checked_sub(width) {
+        Some(extra) if extra > 0 => line.len() + extra,
+        _ => col_at_display(line, display),
+    }
+}
+
+/// The byte column of the character shown at the display column, or the end of the line when it
+/// is shorter
+pub fn col_at_display(line: &str, display: usize) -> usize {
+    line.char_indices()
+        .find(|(idx, char)| display_col(line, idx + char.len_utf8()) > display)
+        .map_or(line.len(), |(idx, _)| idx)
+}
+
+/// The number of screen columns taken by the line up to the byte column
+pub fn display_col(line: &str, col: usize) -> usize {
+    // Virtual columns past the end of the line take one screen column each
+    Span::raw(&line[..min(col, line.len())]).width() + col.saturating_sub(line.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follows_cursor() {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        let area = Rect::new(0, 0, 10, 3);
+
+        buf.cursor = Position { row: 4, col: 0 };
+        scroll_to_cursor(&mut buf, area);
+        assert_eq!(
+            buf.view,
+            View {
+                top: 2,
+                left: 0,
+                height: 3,
+                width: 10,
+                message_height: 0,
+                desired_col: None
+            }
+        );
+
+        buf.options.set("scrolloff=1").unwrap();
+        buf.cursor = Position { row: 2, col: 0 };
+        scroll_to_cursor(&mut buf, area);
+        assert_eq!(buf.view.top, 1);
+
+        buf.cursor = Position { row: 2, col: 30 };
+        scroll_to_cursor(&mut buf, area);
+        assert_eq!(buf.view.left, 21);
+
+        buf.options.set("sidescrolloff=3").unwrap();
+        buf.cursor.col = 22;
+        scroll_to_cursor(&mut buf, area);
+        assert_eq!(buf.view.left, 19);
+    }
+
+    #[test]
+    fn maps_positions_to_cells() {
+        let mut buf = Buffer::test(String::new());
+        buf.content = vec![String::from("aé€b"), String::from("x")];
+        buf.view = View {
+            top: 0,
+            left: 1,
+            height: 1,
+            width: 10,
+            message_height: 0,
+            desired_col: None,
+        };
+        let area = Rect::new(2, 1, 10, 1);
+
+        assert_eq!(
+            screen_position(&buf, Position { row: 0, col: 6 }, area),
+            Some(TuiPosition { x: 4, y: 1 })
+        );
+        assert_eq!(
+            screen_position(&buf, Position { row: 0, col: 0 }, area),
+            None
+        );
+        assert_eq!(
+            screen_position(&buf, Position { row: 1, col: 0 }, area),
+            None
+        );
+    }
+
+    fn scrolled(height: usize) -> Buffer {
+        let mut buf = Buffer::test(String::from("test.txt"));
+        buf.view.height = height;
+        buf
+    }
+
+    #[test]
+    fn scrolls_lines_and_pages() {
+        let mut buf = scrolled(3);
+
+        scroll_lines(&mut buf, true, 2);
+        assert_eq!(buf.view.top, 2);
+        assert_eq!(buf.cursor.row, 2);
+
+        scroll_lines(&mut buf, false, 1);
+        assert_eq!(buf.cursor.row, 2);
+
+        scroll_half_page(&mut buf, true, None);
+        assert_eq!((buf.view.top, buf.cursor.row), (2, 3));
+
+        scroll_half_page(&mut buf, true, Some(2));
+        assert_eq!((buf.view.top, buf.cursor.row), (4, 5));
+        assert_eq!(buf.options.scroll(), 2);
+
+        scroll_page(&mut buf, false, 1);
+        assert_eq!((buf.view.top, buf.cursor.row), (3, 5));
+        scroll_page(&mut buf, false, 2);
+        assert_eq!((buf.view.top, buf.cursor.row), (1, 3));
+    }
+
+    #[test]
+    fn repositions_view() {
+        let mut buf = scrolled(5);
+        buf.cursor.row = 4;
+
+        reposition(&mut buf, ViewAt::Center);
+        assert_eq!(buf.view.top, 2);
+        reposition(&mut buf, ViewAt::Bottom);
+        assert_eq!(buf.view.top, 0);
+        reposition(&mut buf, ViewAt::Top);
+        assert_eq!(buf.view.top, 4);
+    }
+
+    #[test]
+    fn finds_screen_lines() {
+        let mut buf = scrolled(4);
+        buf.view.top = 1;
+
+        assert_eq!(screen_line(&buf, ViewAt::Top, 1).row, 1);
+        assert_eq!(screen_line(&buf, ViewAt::Top, 2).row, 2);
+        assert_eq!(screen_line(&buf, ViewAt::Center, 1).row, 2);
+        assert_eq!(screen_line(&buf, ViewAt::Bottom, 1).row, 4);
+        assert_eq!(screen_line(&buf, ViewAt::Bottom, 9).row, 1);
+
+        buf.options.set("scrolloff=1").unwrap();
+        assert_eq!(screen_line(&buf, ViewAt::Top, 1).row, 2);
+        assert_eq!(screen_line(&buf, ViewAt::Bottom, 1).row, 3);
+    }
+}